@@ -0,0 +1,40 @@
+-- Restore operations history
+
+CREATE TABLE restores
+(
+    id               SERIAL PRIMARY KEY,
+    job_id           UUID                     NOT NULL REFERENCES backup_jobs (id) ON DELETE CASCADE,
+    device_id        VARCHAR(255)             NOT NULL REFERENCES devices (id) ON DELETE CASCADE,
+    snapshot_id      VARCHAR(255),
+    target_path      TEXT                     NOT NULL,
+    include_patterns TEXT[],
+    exclude_patterns TEXT[],
+    overwrite_policy VARCHAR(50)              NOT NULL DEFAULT 'always',
+    start_time       TIMESTAMP WITH TIME ZONE NOT NULL,
+    end_time         TIMESTAMP WITH TIME ZONE,
+    status           VARCHAR(50)              NOT NULL,
+    exit_code        INTEGER,
+    error_message    TEXT,
+    files_restored   INTEGER,
+    files_skipped    INTEGER,
+    bytes_restored   BIGINT,
+    duration_seconds INTEGER,
+    restic_output    TEXT,
+    restic_errors    TEXT,
+    triggered_by     VARCHAR(50),
+    created_at       TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    metadata         JSONB                             DEFAULT '{}'::jsonb,
+    CONSTRAINT check_restore_status CHECK (status IN ('running', 'success', 'failed', 'cancelled')),
+    CONSTRAINT check_overwrite_policy CHECK (overwrite_policy IN ('always', 'if-changed', 'if-newer', 'never'))
+);
+
+CREATE INDEX idx_restores_job ON restores (job_id);
+CREATE INDEX idx_restores_device_start ON restores (device_id, start_time DESC);
+
+COMMENT ON TABLE restores IS 'Execution history of restore operations';
+COMMENT ON COLUMN restores.snapshot_id IS 'restic snapshot that was restored (NULL if resolution failed)';
+COMMENT ON COLUMN restores.target_path IS 'Directory the snapshot was restored into';
+COMMENT ON COLUMN restores.include_patterns IS 'Patterns to include (restic --include)';
+COMMENT ON COLUMN restores.exclude_patterns IS 'Patterns to exclude (restic --exclude)';
+COMMENT ON COLUMN restores.overwrite_policy IS 'restic --overwrite behavior for existing files';
+COMMENT ON COLUMN restores.triggered_by IS 'How the restore was initiated';
@@ -0,0 +1,64 @@
+-- Per-job retention policies and repository maintenance history
+
+ALTER TABLE backup_jobs
+    ADD COLUMN keep_last    INTEGER,
+    ADD COLUMN keep_hourly  INTEGER,
+    ADD COLUMN keep_daily   INTEGER,
+    ADD COLUMN keep_weekly  INTEGER,
+    ADD COLUMN keep_monthly INTEGER,
+    ADD COLUMN keep_yearly  INTEGER,
+    ADD COLUMN keep_within  VARCHAR(50),
+    ADD CONSTRAINT check_retention_non_negative CHECK (
+        COALESCE(keep_last, 0) >= 0 AND
+        COALESCE(keep_hourly, 0) >= 0 AND
+        COALESCE(keep_daily, 0) >= 0 AND
+        COALESCE(keep_weekly, 0) >= 0 AND
+        COALESCE(keep_monthly, 0) >= 0 AND
+        COALESCE(keep_yearly, 0) >= 0
+        );
+
+COMMENT ON COLUMN backup_jobs.keep_last IS 'restic forget --keep-last (NULL = not used)';
+COMMENT ON COLUMN backup_jobs.keep_hourly IS 'restic forget --keep-hourly (NULL = not used)';
+COMMENT ON COLUMN backup_jobs.keep_daily IS 'restic forget --keep-daily (NULL = not used)';
+COMMENT ON COLUMN backup_jobs.keep_weekly IS 'restic forget --keep-weekly (NULL = not used)';
+COMMENT ON COLUMN backup_jobs.keep_monthly IS 'restic forget --keep-monthly (NULL = not used)';
+COMMENT ON COLUMN backup_jobs.keep_yearly IS 'restic forget --keep-yearly (NULL = not used)';
+COMMENT ON COLUMN backup_jobs.keep_within IS 'restic forget --keep-within duration (e.g., "30d", "1y6m")';
+
+CREATE TABLE maintenance_runs
+(
+    id                SERIAL PRIMARY KEY,
+    device_id         VARCHAR(255)             NOT NULL REFERENCES devices (id) ON DELETE CASCADE,
+    job_id            UUID REFERENCES backup_jobs (id) ON DELETE CASCADE,
+    run_id            INTEGER REFERENCES runs (id) ON DELETE SET NULL,
+    task_type         VARCHAR(50)              NOT NULL,
+    start_time        TIMESTAMP WITH TIME ZONE NOT NULL,
+    end_time          TIMESTAMP WITH TIME ZONE,
+    status            VARCHAR(50)              NOT NULL,
+    exit_code         INTEGER,
+    error_message     TEXT,
+    snapshots_kept    INTEGER,
+    snapshots_removed INTEGER,
+    duration_seconds  INTEGER,
+    restic_output     TEXT,
+    restic_errors     TEXT,
+    triggered_by      VARCHAR(50),
+    created_at        TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    metadata          JSONB                             DEFAULT '{}'::jsonb,
+    CONSTRAINT check_maintenance_task_type CHECK (task_type IN ('forget', 'prune')),
+    CONSTRAINT check_maintenance_status CHECK (status IN ('running', 'success', 'failed', 'cancelled'))
+);
+
+CREATE INDEX idx_maintenance_runs_task_start ON maintenance_runs (task_type, start_time DESC);
+CREATE INDEX idx_maintenance_runs_job ON maintenance_runs (job_id);
+CREATE INDEX idx_maintenance_runs_device_start ON maintenance_runs (device_id, start_time DESC);
+
+COMMENT ON TABLE maintenance_runs IS 'Execution history of repository maintenance tasks (forget, prune)';
+COMMENT ON COLUMN maintenance_runs.job_id IS 'Job the task was scoped to (NULL for repository-wide tasks)';
+COMMENT ON COLUMN maintenance_runs.run_id IS 'Backup run that triggered the task, if any';
+COMMENT ON COLUMN maintenance_runs.task_type IS 'Type of maintenance task';
+COMMENT ON COLUMN maintenance_runs.snapshots_removed IS 'Number of snapshots removed by forget';
+
+INSERT INTO settings (device_id, key, value, description)
+VALUES (NULL, 'prune_interval_hours', '168', 'How often the repository is pruned (0 = never)'),
+       (NULL, 'maintenance_window', '', 'Local time window for repository maintenance, e.g. "01:00-05:00" (empty = any time)');
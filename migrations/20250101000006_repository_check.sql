@@ -0,0 +1,32 @@
+-- Repository integrity checks as a maintenance task
+
+ALTER TABLE maintenance_runs
+    DROP CONSTRAINT check_maintenance_task_type,
+    ADD CONSTRAINT check_maintenance_task_type CHECK (task_type IN ('forget', 'prune', 'check')),
+    ADD COLUMN read_data_subset VARCHAR(50);
+
+COMMENT ON TABLE maintenance_runs IS 'Execution history of repository maintenance tasks (forget, prune, check)';
+COMMENT ON COLUMN maintenance_runs.read_data_subset IS 'restic check --read-data-subset used by the check (e.g., "3/10"), NULL = no data read';
+COMMENT ON COLUMN maintenance_runs.error_message IS 'Error message, for checks the errors reported by restic';
+
+-- Latest maintenance task of each type, so failed checks and prunes are as visible as failed backups
+CREATE VIEW latest_maintenance_runs AS
+SELECT DISTINCT ON (task_type)
+    id,
+    task_type,
+    device_id,
+    start_time,
+    end_time,
+    status,
+    error_message,
+    read_data_subset,
+    triggered_by
+FROM maintenance_runs
+WHERE job_id IS NULL
+ORDER BY task_type, start_time DESC;
+
+COMMENT ON VIEW latest_maintenance_runs IS 'Most recent repository-wide maintenance task of each type';
+
+INSERT INTO settings (device_id, key, value, description)
+VALUES (NULL, 'check_interval_hours', '168', 'How often the repository integrity is checked (0 = never)'),
+       (NULL, 'check_read_data_subsets', '0', 'Split data verification into this many subsets, one per check (0 = metadata only)');
@@ -0,0 +1,8 @@
+-- Live progress of running backups
+
+ALTER TABLE runs
+    ADD COLUMN progress            JSONB,
+    ADD COLUMN progress_updated_at TIMESTAMP WITH TIME ZONE;
+
+COMMENT ON COLUMN runs.progress IS 'Latest restic status while the run is in progress (percent_done, files_done, bytes_done, current_files, seconds_remaining, ...)';
+COMMENT ON COLUMN runs.progress_updated_at IS 'When progress was last written by the client';
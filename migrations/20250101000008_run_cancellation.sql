@@ -0,0 +1,12 @@
+-- Cancellation of running backups
+
+ALTER TABLE runs
+    ADD COLUMN cancel_requested_at TIMESTAMP WITH TIME ZONE,
+    ADD COLUMN cancel_requested_by VARCHAR(255),
+    ADD COLUMN cancel_reason       TEXT;
+
+CREATE INDEX idx_runs_cancel_requested ON runs (id) WHERE cancel_requested_at IS NOT NULL AND status = 'running';
+
+COMMENT ON COLUMN runs.cancel_requested_at IS 'When cancellation of the run was requested (NULL = not requested)';
+COMMENT ON COLUMN runs.cancel_requested_by IS 'Who requested the cancellation (e.g., "cli (user)", "api")';
+COMMENT ON COLUMN runs.cancel_reason IS 'Reason given for the cancellation';
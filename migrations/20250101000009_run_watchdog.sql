@@ -0,0 +1,12 @@
+-- Execution timeout and stall detection for backups
+
+ALTER TABLE runs
+    ADD COLUMN failure_reason VARCHAR(50);
+
+CREATE INDEX idx_runs_failure_reason ON runs (failure_reason) WHERE failure_reason IS NOT NULL;
+
+COMMENT ON COLUMN runs.failure_reason IS 'Machine-readable failure reason for alerting (e.g., "timeout", "stalled")';
+
+INSERT INTO settings (device_id, key, value, description)
+VALUES (NULL, 'backup_max_runtime_minutes', '0', 'Maximum backup runtime unless set in job metadata (0 = no limit)'),
+       (NULL, 'backup_stall_timeout_minutes', '30', 'Stop a backup after this many minutes without restic progress (0 = never)');
@@ -0,0 +1,54 @@
+-- Pre/post backup hooks and their execution history
+
+CREATE TABLE backup_hooks
+(
+    id              UUID PRIMARY KEY                  DEFAULT gen_random_uuid(),
+    job_id          UUID                     NOT NULL REFERENCES backup_jobs (id) ON DELETE CASCADE,
+    description     TEXT,
+    command         TEXT                     NOT NULL,
+    timing          VARCHAR(50)              NOT NULL,
+    on_error        VARCHAR(50)              NOT NULL DEFAULT 'continue',
+    timeout_seconds INTEGER                  NOT NULL DEFAULT 300,
+    sort_order      INTEGER                  NOT NULL DEFAULT 0,
+    enabled         BOOLEAN                  NOT NULL DEFAULT true,
+    created_at      TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at      TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    metadata        JSONB                             DEFAULT '{}'::jsonb,
+    CONSTRAINT check_hook_timing CHECK (timing IN ('start', 'end', 'failure')),
+    CONSTRAINT check_hook_on_error CHECK (on_error IN ('stop', 'continue')),
+    CONSTRAINT check_hook_timeout CHECK (timeout_seconds > 0)
+);
+
+CREATE INDEX idx_backup_hooks_job ON backup_hooks (job_id, timing, sort_order);
+
+COMMENT ON TABLE backup_hooks IS 'Commands run around backups of a job';
+COMMENT ON COLUMN backup_hooks.id IS 'UUID (for Relica compatibility)';
+COMMENT ON COLUMN backup_hooks.command IS 'Command line, executed by the platform shell';
+COMMENT ON COLUMN backup_hooks.timing IS 'start = before restic, end = after every backup, failure = after failed backups';
+COMMENT ON COLUMN backup_hooks.on_error IS 'stop = abort the backup (start) or skip remaining hooks (end, failure); continue = ignore the failure';
+COMMENT ON COLUMN backup_hooks.timeout_seconds IS 'Hook is killed and considered failed after this time';
+COMMENT ON COLUMN backup_hooks.sort_order IS 'Execution order among hooks of the same job and timing';
+
+CREATE TABLE hook_executions
+(
+    id               SERIAL PRIMARY KEY,
+    run_id           INTEGER                  NOT NULL REFERENCES runs (id) ON DELETE CASCADE,
+    hook_id          UUID REFERENCES backup_hooks (id) ON DELETE SET NULL,
+    timing           VARCHAR(50)              NOT NULL,
+    command          TEXT                     NOT NULL,
+    start_time       TIMESTAMP WITH TIME ZONE NOT NULL,
+    end_time         TIMESTAMP WITH TIME ZONE NOT NULL,
+    status           VARCHAR(50)              NOT NULL,
+    exit_code        INTEGER,
+    error_message    TEXT,
+    stdout           TEXT,
+    stderr           TEXT,
+    duration_seconds INTEGER,
+    created_at       TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    CONSTRAINT check_hook_execution_status CHECK (status IN ('success', 'failed', 'timeout'))
+);
+
+CREATE INDEX idx_hook_executions_run ON hook_executions (run_id);
+
+COMMENT ON TABLE hook_executions IS 'Output of hooks executed for a backup run';
+COMMENT ON COLUMN hook_executions.command IS 'Command as executed (kept if the hook is changed or deleted later)';
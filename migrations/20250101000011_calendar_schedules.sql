@@ -0,0 +1,40 @@
+-- Relica-style calendar schedules
+
+ALTER TABLE schedules
+    ADD COLUMN calendar JSONB;
+
+ALTER TABLE schedules
+    DROP CONSTRAINT check_schedule_type;
+ALTER TABLE schedules
+    ADD CONSTRAINT check_schedule_type CHECK (schedule_type IN ('cron', 'interval', 'calendar'));
+
+ALTER TABLE schedules
+    DROP CONSTRAINT check_cron_expression;
+ALTER TABLE schedules
+    ADD CONSTRAINT check_cron_expression CHECK (
+        (schedule_type = 'cron' AND cron_expression IS NOT NULL) OR
+        (schedule_type = 'interval' AND interval_seconds IS NOT NULL) OR
+        (schedule_type = 'calendar' AND calendar IS NOT NULL)
+        );
+
+COMMENT ON COLUMN schedules.schedule_type IS 'Type of schedule: cron expression, fixed interval or calendar';
+COMMENT ON COLUMN schedules.calendar IS 'Calendar (if type=calendar): {"times_of_day": ["13:00"], "days_of_week": [1, 3], "days_of_month": [], "months_of_year": [], "flexible": true}; empty lists mean all, 0 = Sunday';
+
+CREATE OR REPLACE VIEW job_summary AS
+SELECT j.id           AS job_id,
+       j.device_id,
+       j.name         AS job_name,
+       j.enabled      AS job_enabled,
+       s.schedule_type,
+       s.cron_expression,
+       s.interval_seconds,
+       s.enabled      AS schedule_enabled,
+       s.last_run_at,
+       s.next_run_at,
+       lr.start_time  AS last_run_start,
+       lr.status      AS last_run_status,
+       lr.snapshot_id AS last_snapshot_id,
+       s.calendar
+FROM backup_jobs j
+         LEFT JOIN schedules s ON j.id = s.job_id
+         LEFT JOIN latest_runs lr ON j.id = lr.job_id;
@@ -0,0 +1,43 @@
+-- Time zones of schedules
+
+ALTER TABLE schedules
+    ADD COLUMN timezone VARCHAR(64);
+
+COMMENT ON COLUMN schedules.timezone IS 'IANA time zone the schedule is evaluated in (e.g., "Europe/Prague"); NULL uses the "timezone" setting of the device, then UTC for cron and the local time of the device for calendar schedules';
+
+INSERT INTO settings (device_id, key, value, description)
+VALUES (NULL, 'timezone', '', 'IANA time zone of schedules without their own, e.g. "Europe/Prague" (empty = UTC for cron, local time for calendar schedules)');
+
+-- Effective time zone: the schedule's, then the device setting, then the global setting.
+-- Names unknown to PostgreSQL are ignored, so a typo cannot break the view.
+CREATE OR REPLACE VIEW job_summary AS
+SELECT j.id           AS job_id,
+       j.device_id,
+       j.name         AS job_name,
+       j.enabled      AS job_enabled,
+       s.schedule_type,
+       s.cron_expression,
+       s.interval_seconds,
+       s.enabled      AS schedule_enabled,
+       s.last_run_at,
+       s.next_run_at,
+       lr.start_time  AS last_run_start,
+       lr.status      AS last_run_status,
+       lr.snapshot_id AS last_snapshot_id,
+       s.calendar,
+       stz.name       AS schedule_timezone,
+       s.next_run_at AT TIME ZONE stz.name AS next_run_local
+FROM backup_jobs j
+         LEFT JOIN schedules s ON j.id = s.job_id
+         LEFT JOIN latest_runs lr ON j.id = lr.job_id
+         LEFT JOIN LATERAL (
+    SELECT tz.name
+    FROM (VALUES (1, s.timezone),
+                 (2, (SELECT value FROM settings WHERE device_id = j.device_id AND key = 'timezone')),
+                 (3, (SELECT value FROM settings WHERE device_id IS NULL AND key = 'timezone'))) AS c (priority, name)
+             JOIN pg_timezone_names tz ON tz.name = c.name
+    ORDER BY c.priority
+    LIMIT 1
+    ) stz ON true;
+
+COMMENT ON VIEW job_summary IS 'Complete overview of jobs with schedule and last run info; next_run_local is the next run in the schedule time zone (NULL if none is configured)';
@@ -0,0 +1,8 @@
+-- Catch-up of runs missed while the device was off or asleep
+
+COMMENT ON COLUMN runs.triggered_by IS 'How the backup was initiated: "schedule", "catchup" (a missed scheduled run) or "manual"';
+
+INSERT INTO settings (device_id, key, value, description)
+VALUES (NULL, 'catchup_grace_minutes', '5', 'Minutes a scheduled run may be late before it counts as missed'),
+       (NULL, 'catchup_policy', 'run_once', 'Missed runs of schedules without their own policy: "run_once", "skip" or "run_if_overdue"'),
+       (NULL, 'catchup_overdue_minutes', '0', 'With "run_if_overdue", run a missed run only if it is overdue by more than this many minutes');
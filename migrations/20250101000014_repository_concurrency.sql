@@ -0,0 +1,4 @@
+-- Limit of concurrent backups per repository
+
+INSERT INTO settings (device_id, key, value, description)
+VALUES (NULL, 'max_concurrent_backups_per_repository', '0', 'Maximum concurrent backup jobs of a device against one repository (0 = max_concurrent_backups only)');
@@ -0,0 +1,24 @@
+-- Persistent queue of jobs waiting for execution
+
+CREATE TABLE job_queue
+(
+    id           BIGSERIAL PRIMARY KEY,
+    job_id       UUID                     NOT NULL REFERENCES backup_jobs (id) ON DELETE CASCADE,
+    device_id    VARCHAR(255)             NOT NULL REFERENCES devices (id) ON DELETE CASCADE,
+    triggered_by VARCHAR(50)              NOT NULL,
+    priority     INTEGER                  NOT NULL,
+    status       VARCHAR(50)              NOT NULL DEFAULT 'pending',
+    enqueued_at  TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    started_at   TIMESTAMP WITH TIME ZONE,
+    CONSTRAINT check_job_queue_status CHECK (status IN ('pending', 'running'))
+);
+
+-- A job is pending at most once, further requests are coalesced into the pending entry
+CREATE UNIQUE INDEX idx_job_queue_pending_job ON job_queue (job_id) WHERE status = 'pending';
+CREATE INDEX idx_job_queue_device ON job_queue (device_id, status, priority DESC, enqueued_at);
+
+COMMENT ON TABLE job_queue IS 'Jobs waiting for execution or executing; entries are removed when the run finishes';
+COMMENT ON COLUMN job_queue.triggered_by IS 'Trigger of the entry with the highest priority: manual, catchup, schedule';
+COMMENT ON COLUMN job_queue.priority IS 'Higher priority entries are executed first (manual > catchup > schedule)';
+COMMENT ON COLUMN job_queue.status IS 'pending = waiting for a free backup slot, running = being executed';
+COMMENT ON COLUMN job_queue.enqueued_at IS 'When the job was first queued; kept when requests are coalesced';
@@ -0,0 +1,11 @@
+-- Recovery of runs left 'running' by a client that crashed or lost power
+
+ALTER TABLE maintenance_runs
+    DROP CONSTRAINT check_maintenance_task_type,
+    ADD CONSTRAINT check_maintenance_task_type CHECK (task_type IN ('forget', 'prune', 'check', 'unlock'));
+
+COMMENT ON TABLE maintenance_runs IS 'Execution history of repository maintenance tasks (forget, prune, check, unlock)';
+COMMENT ON COLUMN runs.failure_reason IS 'Machine-readable failure reason for alerting (e.g., "timeout", "stalled", "abandoned")';
+
+INSERT INTO settings (device_id, key, value, description)
+VALUES (NULL, 'stale_run_timeout_minutes', '60', 'Mark running backups of the device that no client executes as abandoned after this many minutes without progress (0 = only on client start)');
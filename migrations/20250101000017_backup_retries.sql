@@ -0,0 +1,15 @@
+-- Automatic retries of backups that failed for a transient reason
+
+ALTER TABLE runs
+    ADD COLUMN attempt  INTEGER NOT NULL DEFAULT 1,
+    ADD COLUMN retry_of INTEGER REFERENCES runs (id) ON DELETE CASCADE;
+
+CREATE INDEX idx_runs_retry_of ON runs (retry_of) WHERE retry_of IS NOT NULL;
+
+COMMENT ON COLUMN runs.attempt IS 'Attempt number of the backup, 1 for the first one';
+COMMENT ON COLUMN runs.retry_of IS 'First attempt of the backup this run retries (NULL for first attempts)';
+COMMENT ON COLUMN runs.failure_reason IS 'Machine-readable failure reason for alerting (e.g., "timeout", "stalled", "abandoned", "repository_locked", "backend_unavailable", "wrong_password", "repository_not_found", "source_missing", "unreadable_files")';
+
+INSERT INTO settings (device_id, key, value, description)
+VALUES (NULL, 'backup_retry_attempts', '2', 'Retries of a backup that failed because the repository was locked or its backend unreachable, unless set in job metadata (0 = never)'),
+       (NULL, 'backup_retry_backoff_seconds', '60', 'Wait before the first retry of a backup, doubled for every further retry');
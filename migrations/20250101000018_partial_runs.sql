@@ -0,0 +1,9 @@
+-- Backups that created a snapshot although some source files could not be read
+
+ALTER TABLE runs
+    DROP CONSTRAINT check_status,
+    ADD CONSTRAINT check_status CHECK (status IN ('running', 'success', 'partial', 'failed', 'cancelled')),
+    ADD COLUMN failed_paths JSONB;
+
+COMMENT ON COLUMN runs.status IS 'Current status of the backup run: running, success, partial (snapshot created, but some source files could not be read), failed, cancelled';
+COMMENT ON COLUMN runs.failed_paths IS 'Source paths restic could not read during a partial backup: [{"path": ..., "during": ..., "error": ...}]';
@@ -0,0 +1,77 @@
+-- Repositories backed up to, replacing the single repository configured in settings
+
+CREATE TABLE repositories
+(
+    id          UUID PRIMARY KEY                  DEFAULT gen_random_uuid(),
+    name        VARCHAR(255)             NOT NULL UNIQUE,
+    url         TEXT                     NOT NULL,
+    password    TEXT                     NOT NULL,
+    cache_dir   TEXT,
+    environment JSONB                    NOT NULL DEFAULT '{}'::jsonb,
+    enabled     BOOLEAN                  NOT NULL DEFAULT true,
+    created_at  TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at  TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    metadata    JSONB                             DEFAULT '{}'::jsonb
+);
+
+COMMENT ON TABLE repositories IS 'restic repositories jobs back up to; jobs without repositories use repository_url from settings';
+COMMENT ON COLUMN repositories.name IS 'Unique name of the repository, e.g. "nas" or "offsite"';
+COMMENT ON COLUMN repositories.url IS 'restic repository URL (RESTIC_REPOSITORY)';
+COMMENT ON COLUMN repositories.password IS 'restic repository password (RESTIC_PASSWORD)';
+COMMENT ON COLUMN repositories.cache_dir IS 'restic cache directory; repository_cache_dir from settings if NULL';
+COMMENT ON COLUMN repositories.environment IS 'Environment variables of the repository backend, e.g. {"AWS_ACCESS_KEY_ID": "..."}';
+
+-- Destinations of backup jobs
+CREATE TABLE job_repositories
+(
+    job_id           UUID                     NOT NULL REFERENCES backup_jobs (id) ON DELETE CASCADE,
+    repository_id    UUID                     NOT NULL REFERENCES repositories (id) ON DELETE CASCADE,
+    last_success_at  TIMESTAMP WITH TIME ZONE,
+    last_run_id      INTEGER REFERENCES runs (id) ON DELETE SET NULL,
+    last_snapshot_id VARCHAR(255),
+    created_at       TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (job_id, repository_id)
+);
+
+CREATE INDEX idx_job_repositories_repository ON job_repositories (repository_id);
+
+COMMENT ON TABLE job_repositories IS 'Repositories a job backs up to, each one with a run of its own';
+COMMENT ON COLUMN job_repositories.last_success_at IS 'End of the last backup of the job that created a snapshot in the repository';
+COMMENT ON COLUMN job_repositories.last_snapshot_id IS 'Snapshot created by the last successful backup';
+
+ALTER TABLE runs
+    ADD COLUMN repository_id UUID REFERENCES repositories (id) ON DELETE SET NULL;
+
+ALTER TABLE maintenance_runs
+    ADD COLUMN repository_id UUID REFERENCES repositories (id) ON DELETE SET NULL;
+
+ALTER TABLE restores
+    ADD COLUMN repository_id UUID REFERENCES repositories (id) ON DELETE SET NULL;
+
+CREATE INDEX idx_runs_repository ON runs (repository_id);
+CREATE INDEX idx_maintenance_runs_repository_task ON maintenance_runs (repository_id, task_type, start_time DESC);
+
+COMMENT ON COLUMN runs.repository_id IS 'Repository the backup was made to (NULL = repository_url from settings)';
+COMMENT ON COLUMN maintenance_runs.repository_id IS 'Repository the task ran on (NULL = repository_url from settings)';
+COMMENT ON COLUMN restores.repository_id IS 'Repository the snapshot was restored from (NULL = repository_url from settings)';
+
+-- A failed check of one repository must not be hidden by a later successful check of another
+DROP VIEW latest_maintenance_runs;
+
+CREATE VIEW latest_maintenance_runs AS
+SELECT DISTINCT ON (repository_id, task_type)
+    id,
+    repository_id,
+    task_type,
+    device_id,
+    start_time,
+    end_time,
+    status,
+    error_message,
+    read_data_subset,
+    triggered_by
+FROM maintenance_runs
+WHERE job_id IS NULL
+ORDER BY repository_id, task_type, start_time DESC;
+
+COMMENT ON VIEW latest_maintenance_runs IS 'Most recent repository-wide maintenance task of each type in each repository (repository_id NULL = repository_url from settings)';
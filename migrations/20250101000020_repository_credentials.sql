@@ -0,0 +1,6 @@
+-- Typed backend credentials of repositories
+
+ALTER TABLE repositories
+    ADD COLUMN credentials JSONB;
+
+COMMENT ON COLUMN repositories.credentials IS 'Backend credentials, e.g. {"type": "s3", "access_key_id": "...", "secret_access_key": "..."}; the type must match the scheme of url (s3, b2, azure, rest, sftp)';
@@ -0,0 +1,10 @@
+-- Runs recorded in the local run journal of a client while the database was unavailable
+
+ALTER TABLE runs
+    ADD COLUMN journal_id UUID UNIQUE;
+
+ALTER TABLE hook_executions
+    ADD COLUMN journal_id UUID UNIQUE;
+
+COMMENT ON COLUMN runs.journal_id IS 'Journal entry of a run started while the database was unavailable, replayed once it was back (NULL = recorded directly)';
+COMMENT ON COLUMN hook_executions.journal_id IS 'Journal entry of a hook execution recorded while the database was unavailable (NULL = recorded directly)';
@@ -0,0 +1,18 @@
+-- Process executing a run or maintenance task, so a client only recovers the work of processes that are gone
+
+ALTER TABLE runs
+    ADD COLUMN owner_host VARCHAR(255),
+    ADD COLUMN owner_pid INTEGER;
+
+ALTER TABLE maintenance_runs
+    ADD COLUMN owner_host VARCHAR(255),
+    ADD COLUMN owner_pid INTEGER;
+
+COMMENT ON COLUMN runs.owner_host IS 'Host of the process executing the run (NULL = unknown)';
+COMMENT ON COLUMN runs.owner_pid IS 'Process id of the process executing the run on owner_host (NULL = unknown)';
+COMMENT ON COLUMN maintenance_runs.owner_host IS 'Host of the process executing the maintenance task (NULL = unknown)';
+COMMENT ON COLUMN maintenance_runs.owner_pid IS 'Process id of the process executing the maintenance task on owner_host (NULL = unknown)';
+
+UPDATE settings
+SET description = 'Mark running backups of the device without progress for this many minutes, and older maintenance tasks whose process cannot be checked, as abandoned (0 = never); work of processes that are gone is recovered right away'
+WHERE device_id IS NULL AND key = 'stale_run_timeout_minutes';
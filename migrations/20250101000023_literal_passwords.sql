@@ -0,0 +1,11 @@
+-- Repository passwords are parsed as secret references (env:, file:, command:, enc:). Passwords stored before that
+-- were literal values, so those starting with a reference prefix are kept literal with the plain: prefix.
+
+UPDATE settings
+SET value = 'plain:' || value
+WHERE key = 'repository_password'
+  AND value ~ '^(env|file|command|enc|plain):';
+
+UPDATE repositories
+SET password = 'plain:' || password
+WHERE password ~ '^(env|file|command|enc|plain):';
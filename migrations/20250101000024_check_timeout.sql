@@ -0,0 +1,30 @@
+-- Runtime limit of repository checks, and failure reasons of maintenance tasks like those of backups
+
+ALTER TABLE maintenance_runs
+    ADD COLUMN failure_reason VARCHAR(50);
+
+COMMENT ON COLUMN maintenance_runs.failure_reason IS 'Machine-readable failure reason for alerting (e.g., "timeout", "abandoned")';
+
+DROP VIEW latest_maintenance_runs;
+
+CREATE VIEW latest_maintenance_runs AS
+SELECT DISTINCT ON (repository_id, task_type)
+    id,
+    repository_id,
+    task_type,
+    device_id,
+    start_time,
+    end_time,
+    status,
+    error_message,
+    failure_reason,
+    read_data_subset,
+    triggered_by
+FROM maintenance_runs
+WHERE job_id IS NULL
+ORDER BY repository_id, task_type, start_time DESC;
+
+COMMENT ON VIEW latest_maintenance_runs IS 'Most recent repository-wide maintenance task of each type in each repository (repository_id NULL = repository_url from settings)';
+
+INSERT INTO settings (device_id, key, value, description)
+VALUES (NULL, 'check_max_runtime_minutes', '1440', 'Stop a repository check running longer than this many minutes (0 = no limit)');
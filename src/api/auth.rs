@@ -0,0 +1,500 @@
+use crate::api::server::AppState;
+use crate::config::LocalConfig;
+use crate::error::{ApiError, ConfigError, Result};
+use axum::extract::{Request, State};
+use axum::http::{header, HeaderMap, HeaderValue, Method, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::Path;
+use tracing::{info, warn};
+
+pub const TOKEN_COOKIE: &str = "rbackup2_token";
+
+const TOKEN_LENGTH: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    ReadOnly,
+    Admin,
+}
+
+#[derive(Debug, Clone)]
+pub struct ApiAuth {
+    admin_token: String,
+    read_only_token: Option<String>,
+    allowed_origins: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoginRequest {
+    pub token: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionResponse {
+    pub role: Role,
+}
+
+// Browsers send cookies on their own, so only cookie requests can be forged by other sites
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Credential {
+    Bearer(String),
+    Cookie(String),
+}
+
+impl Credential {
+    fn token(&self) -> &str {
+        match self {
+            Credential::Bearer(token) | Credential::Cookie(token) => token,
+        }
+    }
+}
+
+impl ApiAuth {
+    pub fn new(
+        admin_token: String,
+        read_only_token: Option<String>,
+        allowed_origins: Vec<String>,
+    ) -> Self {
+        Self {
+            admin_token,
+            read_only_token,
+            allowed_origins: allowed_origins
+                .into_iter()
+                .map(|origin| origin.trim_end_matches('/').to_string())
+                .collect(),
+        }
+    }
+
+    pub fn from_config(config: &LocalConfig, token_path: &Path) -> Result<Self> {
+        let admin_token = load_or_create_token(token_path)?;
+        let read_only_token = config.api_read_only_token()?;
+
+        if read_only_token.as_deref() == Some(admin_token.as_str()) {
+            return Err(ConfigError::ValidationFailed(
+                "client.api_read_only_token must differ from the admin token".to_string(),
+            )
+            .into());
+        }
+
+        Ok(Self::new(
+            admin_token,
+            read_only_token,
+            config.client.api_allowed_origins.clone(),
+        ))
+    }
+
+    pub fn role(&self, token: &str) -> Option<Role> {
+        if tokens_match(&self.admin_token, token) {
+            Some(Role::Admin)
+        } else if self
+            .read_only_token
+            .as_deref()
+            .is_some_and(|expected| tokens_match(expected, token))
+        {
+            Some(Role::ReadOnly)
+        } else {
+            None
+        }
+    }
+
+    fn origin_allowed(&self, origin: &str, host: Option<&str>) -> bool {
+        let same_origin = host.is_some_and(|host| {
+            origin
+                .strip_prefix("http://")
+                .or_else(|| origin.strip_prefix("https://"))
+                == Some(host)
+        });
+
+        same_origin || self.allowed_origins.iter().any(|allowed| allowed == origin)
+    }
+
+    fn check_origin(
+        &self,
+        method: &Method,
+        headers: &HeaderMap,
+        credential: Option<&Credential>,
+    ) -> Result<()> {
+        let host = headers
+            .get(header::HOST)
+            .and_then(|host| host.to_str().ok());
+
+        match headers.get(header::ORIGIN) {
+            Some(origin) => {
+                let origin = origin.to_str().unwrap_or_default();
+                if !self.origin_allowed(origin, host) {
+                    return Err(ApiError::Forbidden(format!(
+                        "requests from origin {} are not allowed",
+                        origin
+                    ))
+                    .into());
+                }
+            }
+            None => {
+                let cross_site = headers
+                    .get("sec-fetch-site")
+                    .is_some_and(|site| site == "cross-site");
+                if cross_site {
+                    return Err(ApiError::Forbidden(
+                        "cross-site requests are not allowed".to_string(),
+                    )
+                    .into());
+                }
+
+                // Browsers send an Origin with every POST, one without it is not from the UI
+                let safe = method == Method::GET || method == Method::HEAD;
+                if !safe && matches!(credential, Some(Credential::Cookie(_))) {
+                    return Err(ApiError::Forbidden(
+                        "requests authenticated by cookie need an Origin header".to_string(),
+                    )
+                    .into());
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn authorize(&self, credential: Option<&Credential>, required: Role) -> Result<Role> {
+        let credential = credential
+            .ok_or_else(|| ApiError::Unauthorized("an API token is required".to_string()))?;
+        let role = self
+            .role(credential.token())
+            .ok_or_else(|| ApiError::Unauthorized("invalid API token".to_string()))?;
+
+        if role < required {
+            return Err(
+                ApiError::Forbidden("the API token is only allowed to read".to_string()).into(),
+            );
+        }
+
+        Ok(role)
+    }
+}
+
+pub fn load_or_create_token(path: &Path) -> Result<String> {
+    let token_error = |e: std::io::Error| -> crate::error::AppError {
+        ConfigError::SecretFailed(format!("API token file {}: {}", path.display(), e)).into()
+    };
+
+    match std::fs::read_to_string(path) {
+        Ok(token) if !token.trim().is_empty() => {
+            warn_if_readable_by_others(path);
+            return Ok(token.trim().to_string());
+        }
+        Ok(_) => {
+            return Err(ConfigError::SecretFailed(format!(
+                "API token file {} is empty",
+                path.display()
+            ))
+            .into())
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(token_error(e)),
+    }
+
+    let mut bytes = [0u8; TOKEN_LENGTH];
+    SystemRandom::new()
+        .fill(&mut bytes)
+        .map_err(|_| ConfigError::SecretFailed("no secure random number generator".to_string()))?;
+    let token = URL_SAFE_NO_PAD.encode(bytes);
+
+    let mut options = std::fs::OpenOptions::new();
+    // Never overwrites the token of another process starting at the same time
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let mut file = options.open(path).map_err(token_error)?;
+    file.write_all(format!("{}\n", token).as_bytes())
+        .and_then(|_| file.sync_all())
+        .map_err(token_error)?;
+
+    info!("Generated HTTP API token in {}", path.display());
+    Ok(token)
+}
+
+#[cfg(unix)]
+fn warn_if_readable_by_others(path: &Path) {
+    use std::os::unix::fs::PermissionsExt;
+
+    if let Ok(metadata) = std::fs::metadata(path) {
+        if metadata.permissions().mode() & 0o077 != 0 {
+            warn!(
+                "API token file {} is accessible by other users, restrict it to mode 0600",
+                path.display()
+            );
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn warn_if_readable_by_others(_path: &Path) {}
+
+// Constant time, so the token cannot be guessed from response times
+fn tokens_match(expected: &str, given: &str) -> bool {
+    expected.len() == given.len()
+        && expected
+            .bytes()
+            .zip(given.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+fn credential(headers: &HeaderMap) -> Option<Credential> {
+    let bearer = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| {
+            let (scheme, token) = value.split_once(' ')?;
+            scheme
+                .eq_ignore_ascii_case("bearer")
+                .then(|| Credential::Bearer(token.trim().to_string()))
+        });
+
+    bearer.or_else(|| {
+        headers
+            .get_all(header::COOKIE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(';'))
+            .filter_map(|cookie| cookie.trim().split_once('='))
+            .find(|(name, _)| *name == TOKEN_COOKIE)
+            .map(|(_, token)| Credential::Cookie(token.to_string()))
+    })
+}
+
+pub async fn check_origin(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Result<Response> {
+    let headers = request.headers();
+    state
+        .auth
+        .check_origin(request.method(), headers, credential(headers).as_ref())?;
+
+    Ok(next.run(request).await)
+}
+
+pub async fn require_read_only(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Result<Response> {
+    state
+        .auth
+        .authorize(credential(request.headers()).as_ref(), Role::ReadOnly)?;
+
+    Ok(next.run(request).await)
+}
+
+pub async fn require_admin(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Result<Response> {
+    state
+        .auth
+        .authorize(credential(request.headers()).as_ref(), Role::Admin)?;
+
+    Ok(next.run(request).await)
+}
+
+pub async fn login(
+    State(state): State<AppState>,
+    Json(request): Json<LoginRequest>,
+) -> Result<Response> {
+    let role = state
+        .auth
+        .role(request.token.trim())
+        .ok_or_else(|| ApiError::Unauthorized("invalid API token".to_string()))?;
+
+    let cookie = format!(
+        "{}={}; HttpOnly; SameSite=Strict; Path=/",
+        TOKEN_COOKIE,
+        request.token.trim()
+    );
+    let cookie = HeaderValue::from_str(&cookie)
+        .map_err(|_| ApiError::InvalidRequest("invalid API token".to_string()))?;
+
+    Ok((
+        [(header::SET_COOKIE, cookie)],
+        Json(SessionResponse { role }),
+    )
+        .into_response())
+}
+
+pub async fn logout() -> impl IntoResponse {
+    (
+        StatusCode::NO_CONTENT,
+        [(
+            header::SET_COOKIE,
+            format!(
+                "{}=; HttpOnly; SameSite=Strict; Path=/; Max-Age=0",
+                TOKEN_COOKIE
+            ),
+        )],
+    )
+}
+
+pub async fn session(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<SessionResponse>> {
+    let role = state
+        .auth
+        .authorize(credential(&headers).as_ref(), Role::ReadOnly)?;
+
+    Ok(Json(SessionResponse { role }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn auth() -> ApiAuth {
+        ApiAuth::new(
+            "admin-token".to_string(),
+            Some("viewer-token".to_string()),
+            vec!["https://dashboard.example.com/".to_string()],
+        )
+    }
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.append(*name, HeaderValue::from_str(value).unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn test_roles() {
+        let auth = auth();
+
+        assert_eq!(auth.role("admin-token"), Some(Role::Admin));
+        assert_eq!(auth.role("viewer-token"), Some(Role::ReadOnly));
+        assert_eq!(auth.role("admin-toke"), None);
+        assert_eq!(auth.role(""), None);
+
+        let admin = Credential::Bearer("admin-token".to_string());
+        let viewer = Credential::Bearer("viewer-token".to_string());
+        assert!(auth.authorize(Some(&admin), Role::Admin).is_ok());
+        assert!(auth.authorize(Some(&viewer), Role::ReadOnly).is_ok());
+
+        let denied = auth.authorize(Some(&viewer), Role::Admin).unwrap_err();
+        assert!(matches!(
+            denied,
+            crate::error::AppError::Api(ApiError::Forbidden(_))
+        ));
+        let missing = auth.authorize(None, Role::ReadOnly).unwrap_err();
+        assert!(matches!(
+            missing,
+            crate::error::AppError::Api(ApiError::Unauthorized(_))
+        ));
+    }
+
+    #[test]
+    fn test_credential() {
+        assert_eq!(
+            credential(&headers(&[("authorization", "Bearer abc")])),
+            Some(Credential::Bearer("abc".to_string()))
+        );
+        assert_eq!(
+            credential(&headers(&[("cookie", "theme=dark; rbackup2_token=abc")])),
+            Some(Credential::Cookie("abc".to_string()))
+        );
+        assert_eq!(
+            credential(&headers(&[
+                ("authorization", "bearer header"),
+                ("cookie", "rbackup2_token=cookie")
+            ])),
+            Some(Credential::Bearer("header".to_string()))
+        );
+        assert_eq!(
+            credential(&headers(&[("authorization", "Basic abc")])),
+            None
+        );
+        assert_eq!(credential(&HeaderMap::new()), None);
+    }
+
+    #[test]
+    fn test_check_origin() {
+        let auth = auth();
+        let cookie = Credential::Cookie("admin-token".to_string());
+        let bearer = Credential::Bearer("admin-token".to_string());
+
+        let same_origin = headers(&[
+            ("host", "127.0.0.1:1201"),
+            ("origin", "http://127.0.0.1:1201"),
+        ]);
+        assert!(auth
+            .check_origin(&Method::POST, &same_origin, Some(&cookie))
+            .is_ok());
+
+        let allowed = headers(&[
+            ("host", "127.0.0.1:1201"),
+            ("origin", "https://dashboard.example.com"),
+        ]);
+        assert!(auth
+            .check_origin(&Method::GET, &allowed, Some(&bearer))
+            .is_ok());
+
+        let foreign = headers(&[
+            ("host", "127.0.0.1:1201"),
+            ("origin", "https://evil.example"),
+        ]);
+        assert!(auth
+            .check_origin(&Method::POST, &foreign, Some(&cookie))
+            .is_err());
+        assert!(auth.check_origin(&Method::GET, &foreign, None).is_err());
+
+        let cross_site = headers(&[("host", "127.0.0.1:1201"), ("sec-fetch-site", "cross-site")]);
+        assert!(auth
+            .check_origin(&Method::GET, &cross_site, Some(&cookie))
+            .is_err());
+
+        // Clients other than browsers send neither Origin nor cookies
+        let no_origin = headers(&[("host", "127.0.0.1:1201")]);
+        assert!(auth
+            .check_origin(&Method::POST, &no_origin, Some(&bearer))
+            .is_ok());
+        assert!(auth
+            .check_origin(&Method::POST, &no_origin, Some(&cookie))
+            .is_err());
+        assert!(auth
+            .check_origin(&Method::GET, &no_origin, Some(&cookie))
+            .is_ok());
+    }
+
+    #[test]
+    fn test_load_or_create_token() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let path = dir.path().join("config.token");
+
+        let token = load_or_create_token(&path).expect("Failed to create token");
+        assert_eq!(URL_SAFE_NO_PAD.decode(&token).unwrap().len(), TOKEN_LENGTH);
+        assert_eq!(load_or_create_token(&path).unwrap(), token);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        let other = load_or_create_token(&dir.path().join("other.token")).unwrap();
+        assert_ne!(other, token);
+    }
+}
@@ -0,0 +1,258 @@
+use crate::api::models::{
+    ActiveRunResponse, CancelRunRequest, FailedCheckResponse, JobResponse, RunDetailResponse,
+    RunHistoryQuery, RunHistoryResponse, RunSummary, StatusResponse, DEFAULT_RUNS_LIMIT,
+    MAX_RUNS_LIMIT,
+};
+use crate::api::server::AppState;
+use crate::backup;
+use crate::backup::journal::is_local_run;
+use crate::backup::registry::CancelRequest;
+use crate::db;
+use crate::db::models::{QueuedJob, Run, RunFilter};
+use crate::error::{ApiError, Result};
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::sse::{Event as SseEvent, KeepAlive, Sse};
+use axum::Json;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use tokio_stream::wrappers::errors::BroadcastStreamRecvError;
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::{Stream, StreamExt};
+use tracing::warn;
+use uuid::Uuid;
+
+const API_REQUESTER: &str = "api";
+
+pub async fn health() -> &'static str {
+    "OK"
+}
+
+pub async fn status(State(state): State<AppState>) -> Result<Json<StatusResponse>> {
+    let active_runs = state
+        .registry
+        .active_runs()
+        .await
+        .iter()
+        .map(ActiveRunResponse::from)
+        .collect();
+
+    let repository_ids: Vec<_> = state
+        .config
+        .lock()
+        .await
+        .device_destinations()
+        .iter()
+        .map(|d| d.map(|r| r.id))
+        .collect();
+
+    let (device, queue, failed_checks, database_connected) =
+        match db::get_device(&state.pool, state.device_id.clone()).await {
+            Ok(device) => {
+                let queue = db::get_job_queue(&state.pool, state.device_id.clone()).await?;
+                let failed_checks = db::get_failed_checks(&state.pool)
+                    .await?
+                    .into_iter()
+                    .filter(|check| repository_ids.contains(&check.repository_id))
+                    .map(FailedCheckResponse::from)
+                    .collect();
+                (device, queue, failed_checks, true)
+            }
+            Err(e) if e.is_database_unavailable() => {
+                warn!(
+                    "Database is unavailable, reporting status without it: {}",
+                    e
+                );
+                (None, Vec::new(), Vec::new(), false)
+            }
+            Err(e) => return Err(e),
+        };
+
+    Ok(Json(StatusResponse {
+        device_id: state.device_id.clone(),
+        device,
+        database_connected,
+        active_runs,
+        queue,
+        failed_checks,
+    }))
+}
+
+// A subscriber that falls behind misses the oldest events
+pub async fn events(
+    State(state): State<AppState>,
+) -> Sse<impl Stream<Item = std::result::Result<SseEvent, Infallible>>> {
+    let stream = BroadcastStream::new(state.events.subscribe()).filter_map(|event| match event {
+        Ok(event) => SseEvent::default()
+            .event(event.name())
+            .json_data(&event)
+            .ok()
+            .map(Ok),
+        Err(BroadcastStreamRecvError::Lagged(missed)) => {
+            warn!(missed = missed, "Event stream subscriber fell behind");
+            None
+        }
+    });
+
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+pub async fn list_jobs(State(state): State<AppState>) -> Result<Json<Vec<JobResponse>>> {
+    let jobs = state.config.lock().await.jobs.clone();
+    let schedules = state.scheduler.schedules().await;
+    let running: Vec<_> = state
+        .registry
+        .active_runs()
+        .await
+        .iter()
+        .map(|run| run.job_id)
+        .collect();
+
+    let mut last_runs: HashMap<Uuid, Run> =
+        match db::get_latest_runs(&state.pool, state.device_id.clone()).await {
+            Ok(runs) => runs.into_iter().map(|run| (run.job_id, run)).collect(),
+            Err(e) if e.is_database_unavailable() => {
+                warn!(
+                    "Database is unavailable, listing jobs without their last run: {}",
+                    e
+                );
+                HashMap::new()
+            }
+            Err(e) => return Err(e),
+        };
+
+    let jobs = jobs
+        .into_iter()
+        .map(|job| {
+            let job_schedules: Vec<_> = schedules
+                .iter()
+                .filter(|schedule| schedule.job_id == job.id)
+                .cloned()
+                .collect();
+            let next_run_at = job_schedules
+                .iter()
+                .filter(|schedule| schedule.enabled)
+                .filter_map(|schedule| schedule.next_run_at)
+                .min();
+
+            JobResponse {
+                schedules: job_schedules,
+                next_run_at,
+                last_run: last_runs.remove(&job.id).map(RunSummary::from),
+                running: running.contains(&job.id),
+                job,
+            }
+        })
+        .collect();
+
+    Ok(Json(jobs))
+}
+
+pub async fn list_runs(
+    State(state): State<AppState>,
+    Query(query): Query<RunHistoryQuery>,
+) -> Result<Json<RunHistoryResponse>> {
+    let limit = query.limit.unwrap_or(DEFAULT_RUNS_LIMIT);
+    if !(1..=MAX_RUNS_LIMIT).contains(&limit) {
+        return Err(ApiError::InvalidRequest(format!(
+            "limit must be between 1 and {}",
+            MAX_RUNS_LIMIT
+        ))
+        .into());
+    }
+    let offset = query.offset.unwrap_or(0);
+    if offset < 0 {
+        return Err(ApiError::InvalidRequest("offset must not be negative".to_string()).into());
+    }
+
+    let filter = RunFilter {
+        job_id: query.job_id,
+        status: query.status,
+        repository_id: query.repository_id,
+        triggered_by: query.triggered_by,
+        since: query.since,
+        until: query.until,
+    };
+
+    let (runs, total) =
+        db::list_runs(&state.pool, state.device_id.clone(), &filter, limit, offset).await?;
+
+    Ok(Json(RunHistoryResponse {
+        runs: runs.into_iter().map(RunSummary::from).collect(),
+        total,
+        limit,
+        offset,
+    }))
+}
+
+pub async fn get_run(
+    State(state): State<AppState>,
+    Path(run_id): Path<i32>,
+) -> Result<Json<RunDetailResponse>> {
+    let run = state
+        .journal
+        .get_run(&state.pool, run_id)
+        .await?
+        .filter(|run| run.device_id == state.device_id)
+        .ok_or_else(|| ApiError::NotFound(format!("run {}", run_id)))?;
+
+    let hook_executions = if is_local_run(run_id) {
+        Vec::new()
+    } else {
+        db::get_hook_executions(&state.pool, run_id).await?
+    };
+
+    Ok(Json(RunDetailResponse {
+        run,
+        hook_executions,
+    }))
+}
+
+pub async fn cancel_run(
+    State(state): State<AppState>,
+    Path(run_id): Path<i32>,
+    request: Option<Json<CancelRunRequest>>,
+) -> Result<StatusCode> {
+    let reason = request
+        .and_then(|Json(request)| request.reason)
+        .unwrap_or_else(|| "Cancelled through the API".to_string());
+    let request = CancelRequest {
+        reason,
+        requested_by: API_REQUESTER.to_string(),
+    };
+
+    let requested = backup::cancel_backup(
+        &state.pool,
+        &state.journal,
+        &state.registry,
+        &state.device_id,
+        run_id,
+        request,
+    )
+    .await?;
+    if !requested {
+        return Err(ApiError::InvalidRequest(format!("run {} is not running", run_id)).into());
+    }
+
+    Ok(StatusCode::ACCEPTED)
+}
+
+pub async fn trigger_job(
+    State(state): State<AppState>,
+    Path(job_id): Path<Uuid>,
+) -> Result<(StatusCode, Json<QueuedJob>)> {
+    let known = state
+        .config
+        .lock()
+        .await
+        .jobs
+        .iter()
+        .any(|job| job.id == job_id);
+    if !known {
+        return Err(ApiError::NotFound(format!("job {}", job_id)).into());
+    }
+
+    let entry = state.scheduler.trigger_manual_backup(job_id).await?;
+
+    Ok((StatusCode::ACCEPTED, Json(entry)))
+}
@@ -0,0 +1,163 @@
+use crate::backup::output::BackupProgress;
+use crate::backup::registry::ActiveRun;
+use crate::db::models::{
+    BackupJob, Device, HookExecution, MaintenanceRun, QueuedJob, Run, Schedule,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+pub const DEFAULT_RUNS_LIMIT: i64 = 50;
+pub const MAX_RUNS_LIMIT: i64 = 500;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatusResponse {
+    pub device_id: String,
+    // None while the database is unavailable
+    pub device: Option<Device>,
+    pub database_connected: bool,
+    pub active_runs: Vec<ActiveRunResponse>,
+    pub queue: Vec<QueuedJob>,
+    // Repositories of the device whose latest check failed
+    pub failed_checks: Vec<FailedCheckResponse>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FailedCheckResponse {
+    pub maintenance_run_id: i32,
+    pub repository_id: Option<Uuid>,
+    pub device_id: String,
+    pub start_time: DateTime<Utc>,
+    pub end_time: Option<DateTime<Utc>>,
+    pub error_message: Option<String>,
+    pub failure_reason: Option<String>,
+    pub read_data_subset: Option<String>,
+}
+
+impl From<MaintenanceRun> for FailedCheckResponse {
+    fn from(run: MaintenanceRun) -> Self {
+        Self {
+            maintenance_run_id: run.id,
+            repository_id: run.repository_id,
+            device_id: run.device_id,
+            start_time: run.start_time,
+            end_time: run.end_time,
+            error_message: run.error_message,
+            failure_reason: run.failure_reason,
+            read_data_subset: run.read_data_subset,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActiveRunResponse {
+    pub run_id: i32,
+    pub job_id: Uuid,
+    pub started_at: DateTime<Utc>,
+    pub progress: Option<BackupProgress>,
+}
+
+impl From<&ActiveRun> for ActiveRunResponse {
+    fn from(run: &ActiveRun) -> Self {
+        Self {
+            run_id: run.run_id,
+            job_id: run.job_id,
+            started_at: run.started_at,
+            progress: run.progress.borrow().clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobResponse {
+    #[serde(flatten)]
+    pub job: BackupJob,
+    pub schedules: Vec<Schedule>,
+    pub next_run_at: Option<DateTime<Utc>>,
+    pub last_run: Option<RunSummary>,
+    pub running: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunSummary {
+    pub id: i32,
+    pub job_id: Uuid,
+    pub repository_id: Option<Uuid>,
+    pub start_time: DateTime<Utc>,
+    pub end_time: Option<DateTime<Utc>>,
+    pub status: String,
+    pub exit_code: Option<i32>,
+    pub error_message: Option<String>,
+    pub failure_reason: Option<String>,
+    pub files_new: Option<i32>,
+    pub files_changed: Option<i32>,
+    pub files_unmodified: Option<i32>,
+    pub data_added_bytes: Option<i64>,
+    pub total_files_processed: Option<i32>,
+    pub total_bytes_processed: Option<i64>,
+    pub duration_seconds: Option<i32>,
+    pub snapshot_id: Option<String>,
+    pub triggered_by: String,
+    pub attempt: i32,
+    pub retry_of: Option<i32>,
+}
+
+impl From<Run> for RunSummary {
+    fn from(run: Run) -> Self {
+        Self {
+            id: run.id,
+            job_id: run.job_id,
+            repository_id: run.repository_id,
+            start_time: run.start_time,
+            end_time: run.end_time,
+            status: run.status,
+            exit_code: run.exit_code,
+            error_message: run.error_message,
+            failure_reason: run.failure_reason,
+            files_new: run.files_new,
+            files_changed: run.files_changed,
+            files_unmodified: run.files_unmodified,
+            data_added_bytes: run.data_added_bytes,
+            total_files_processed: run.total_files_processed,
+            total_bytes_processed: run.total_bytes_processed,
+            duration_seconds: run.duration_seconds,
+            snapshot_id: run.snapshot_id,
+            triggered_by: run.triggered_by,
+            attempt: run.attempt,
+            retry_of: run.retry_of,
+        }
+    }
+}
+
+// `since` and `until` bound the start time of the runs
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RunHistoryQuery {
+    pub job_id: Option<Uuid>,
+    pub status: Option<String>,
+    pub repository_id: Option<Uuid>,
+    pub triggered_by: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunHistoryResponse {
+    pub runs: Vec<RunSummary>,
+    pub total: i64,
+    pub limit: i64,
+    pub offset: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunDetailResponse {
+    #[serde(flatten)]
+    pub run: Run,
+    pub hook_executions: Vec<HookExecution>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CancelRunRequest {
+    pub reason: Option<String>,
+}
@@ -0,0 +1,96 @@
+use crate::api::auth::{self, ApiAuth};
+use crate::api::handlers;
+use crate::backup::journal::RunJournal;
+use crate::backup::registry::RunRegistry;
+use crate::config::remote::RemoteConfig;
+use crate::error::{ApiError, Result};
+use crate::events::EventBus;
+use crate::scheduler::Scheduler;
+use crate::ui;
+use axum::extract::Request;
+use axum::middleware::{self, Next};
+use axum::response::Response;
+use axum::routing::{get, post};
+use axum::Router;
+use sqlx::PgPool;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tracing::{debug, info};
+
+#[derive(Clone)]
+pub struct AppState {
+    pub pool: Arc<PgPool>,
+    pub config: Arc<Mutex<RemoteConfig>>,
+    pub scheduler: Arc<Scheduler>,
+    pub registry: RunRegistry,
+    pub journal: RunJournal,
+    pub events: EventBus,
+    pub auth: Arc<ApiAuth>,
+    pub device_id: String,
+}
+
+// Reading needs a read-only or admin token, changes need the admin token
+pub fn router(state: AppState) -> Router {
+    let read = Router::new()
+        .route("/session", get(auth::session))
+        .route("/status", get(handlers::status))
+        .route("/events", get(handlers::events))
+        .route("/jobs", get(handlers::list_jobs))
+        .route("/runs", get(handlers::list_runs))
+        .route("/runs/{run_id}", get(handlers::get_run))
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            auth::require_read_only,
+        ));
+
+    let admin = Router::new()
+        .route("/jobs/{job_id}/run", post(handlers::trigger_job))
+        .route("/runs/{run_id}/cancel", post(handlers::cancel_run))
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            auth::require_admin,
+        ));
+
+    Router::new()
+        .route("/health", get(handlers::health))
+        .route("/login", post(auth::login))
+        .route("/logout", post(auth::logout))
+        .merge(read)
+        .merge(admin)
+        .merge(ui::router())
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            auth::check_origin,
+        ))
+        .layer(middleware::from_fn(log_request))
+        .with_state(state)
+}
+
+pub async fn serve(bind: &str, state: AppState) -> Result<()> {
+    let listener = tokio::net::TcpListener::bind(bind)
+        .await
+        .map_err(|e| ApiError::InternalError(format!("Failed to bind {}: {}", bind, e)))?;
+
+    info!("HTTP API listening on {}", bind);
+
+    axum::serve(listener, router(state))
+        .await
+        .map_err(|e| ApiError::InternalError(format!("HTTP server failed: {}", e)))?;
+
+    Ok(())
+}
+
+async fn log_request(request: Request, next: Next) -> Response {
+    let method = request.method().clone();
+    let uri = request.uri().clone();
+
+    let response = next.run(request).await;
+
+    debug!(
+        method = %method,
+        uri = %uri,
+        status = response.status().as_u16(),
+        "HTTP request"
+    );
+    response
+}
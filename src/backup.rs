@@ -1,76 +1,182 @@
+pub mod backend;
+pub mod check;
+pub mod failure;
+pub mod hooks;
+pub mod journal;
 pub mod output;
+pub mod recovery;
+pub mod registry;
 pub mod restic;
+pub mod restore;
+pub mod retention;
+pub mod retry;
+pub mod watchdog;
 
 use crate::config::remote::RemoteConfig;
 use crate::db;
-use crate::db::models::BackupJob;
-use crate::error::Result;
+use crate::db::models::{BackupJob, Repository};
+use crate::error::{AppError, BackupError, Result};
+use crate::events::Event;
 use chrono::Utc;
-use output::{parse_restic_json_output, BackupStats};
+use failure::ResticFailure;
+use hooks::HookTiming;
+use journal::{RunCancellation, RunJournal, RunUpdate};
+use output::{
+    parse_backup_errors, parse_restic_json_output, parse_status_line, BackupStats, FailedPath,
+};
+use registry::{CancelRequest, RunHandle, RunRegistry};
 use restic::ResticCommand;
+use retry::RetryPolicy;
 use sqlx::PgPool;
 use std::process::Output;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, BufReader};
+use tokio::process::Child;
 use tracing::{debug, error, info, warn};
+use watchdog::{RunLimits, Watchdog, WatchdogTrigger};
+
+const PROGRESS_DB_UPDATE_INTERVAL: Duration = Duration::from_secs(15);
+
+// How long restic gets to stop after being interrupted
+const CANCEL_GRACE_PERIOD: Duration = Duration::from_secs(30);
+
+// Exit code of restic when the snapshot was created, but some source files could not be read
+const PARTIAL_BACKUP_EXIT_CODE: i32 = 3;
+
+// The rest of the failed paths of a partial run is only counted
+const MAX_STORED_FAILED_PATHS: usize = 1000;
+
+pub fn repository_label(repository: Option<&Repository>) -> &str {
+    repository.map(|r| r.name.as_str()).unwrap_or("default")
+}
+
+enum StopReason {
+    Cancelled(CancelRequest),
+    Watchdog(WatchdogTrigger),
+}
+
+struct ResticExecution {
+    output: Output,
+    stopped: Option<StopReason>,
+}
+
+fn interrupt_restic(child: &mut Child, trace_id: &str) -> tokio::time::Instant {
+    if let Err(e) = restic::interrupt(child) {
+        warn!(trace_id = trace_id, "Failed to interrupt restic: {}", e);
+    }
+
+    tokio::time::Instant::now() + CANCEL_GRACE_PERIOD
+}
 
 async fn update_run_with_failure(
     pool: &PgPool,
+    journal: &RunJournal,
     run_id: i32,
     error_msg: String,
     exit_code: Option<i32>,
     stdout: Option<String>,
     stderr: Option<String>,
 ) -> Result<()> {
-    db::update_run(
-        pool,
-        run_id,
-        Utc::now(),
-        "failed".to_string(),
+    let update = RunUpdate {
+        end_time: Utc::now(),
+        status: "failed".to_string(),
         exit_code,
-        Some(error_msg),
-        None,
-        None,
-        None,
-        None,
-        None,
-        stdout,
-        stderr,
-    )
-    .await?;
-    Ok(())
+        error_message: Some(error_msg),
+        files_new: None,
+        files_changed: None,
+        files_unmodified: None,
+        data_added_bytes: None,
+        snapshot_id: None,
+        restic_output: stdout,
+        restic_errors: stderr,
+    };
+
+    journal.update_run(pool, run_id, update).await
 }
 
 async fn update_run_with_success(
     pool: &PgPool,
+    journal: &RunJournal,
     run_id: i32,
     exit_code: i32,
     stats: &BackupStats,
     stdout: String,
     stderr: Option<String>,
 ) -> Result<()> {
-    db::update_run(
-        pool,
-        run_id,
-        Utc::now(),
-        "success".to_string(),
-        Some(exit_code),
-        None,
-        Some(stats.files_new),
-        Some(stats.files_changed),
-        Some(stats.files_unmodified),
-        Some(stats.data_added_bytes),
-        Some(stats.snapshot_id.clone()),
-        Some(stdout),
-        stderr,
-    )
-    .await?;
-    Ok(())
+    let update = RunUpdate {
+        end_time: Utc::now(),
+        status: "success".to_string(),
+        exit_code: Some(exit_code),
+        error_message: None,
+        files_new: Some(stats.files_new),
+        files_changed: Some(stats.files_changed),
+        files_unmodified: Some(stats.files_unmodified),
+        data_added_bytes: Some(stats.data_added_bytes),
+        snapshot_id: Some(stats.snapshot_id.clone()),
+        restic_output: Some(stdout),
+        restic_errors: stderr,
+    };
+
+    journal.update_run(pool, run_id, update).await
 }
 
+// Allow many arguments: the run is recorded with the restic output and the failed paths
+#[allow(clippy::too_many_arguments)]
+async fn update_run_with_partial_success(
+    pool: &PgPool,
+    journal: &RunJournal,
+    run_id: i32,
+    exit_code: i32,
+    stats: &BackupStats,
+    failed_paths: &[FailedPath],
+    stdout: String,
+    stderr: Option<String>,
+) -> Result<()> {
+    let error_msg = match failed_paths.len() {
+        0 => "Snapshot created, but some source files could not be read".to_string(),
+        count => format!(
+            "Snapshot created, but {} source files could not be read",
+            count
+        ),
+    };
+
+    let update = RunUpdate {
+        end_time: Utc::now(),
+        status: "partial".to_string(),
+        exit_code: Some(exit_code),
+        error_message: Some(error_msg),
+        files_new: Some(stats.files_new),
+        files_changed: Some(stats.files_changed),
+        files_unmodified: Some(stats.files_unmodified),
+        data_added_bytes: Some(stats.data_added_bytes),
+        snapshot_id: Some(stats.snapshot_id.clone()),
+        restic_output: Some(stdout),
+        restic_errors: stderr,
+    };
+    journal.update_run(pool, run_id, update).await?;
+
+    let stored = &failed_paths[..failed_paths.len().min(MAX_STORED_FAILED_PATHS)];
+    journal
+        .set_run_failed_paths(
+            pool,
+            run_id,
+            serde_json::to_value(stored).unwrap_or_default(),
+        )
+        .await
+}
+
+// Allow many arguments: progress is stored through the journal's id of the run
+#[allow(clippy::too_many_arguments)]
 async fn execute_restic_command(
     restic_cmd: &ResticCommand,
     job: &BackupJob,
+    pool: &PgPool,
+    journal: &RunJournal,
+    run_id: i32,
+    handle: &mut RunHandle,
+    limits: RunLimits,
     trace_id: &str,
-) -> Result<Output> {
+) -> Result<ResticExecution> {
     let mut command = restic_cmd.build_backup_command(job);
 
     debug!(
@@ -78,10 +184,143 @@ async fn execute_restic_command(
         "Executing restic backup command for job '{}'", job.name
     );
 
-    command.output().await.map_err(|e| {
+    let execution_error = |e: std::io::Error| {
         let error_msg = format!("Failed to execute restic: {}", e);
         error!(trace_id = trace_id, "{}", error_msg);
-        crate::error::BackupError::ExecutionFailed(error_msg).into()
+        crate::error::AppError::from(crate::error::BackupError::ExecutionFailed(error_msg))
+    };
+
+    let mut child = command.spawn().map_err(execution_error)?;
+
+    let stdout = child.stdout.take().expect("restic stdout is piped");
+    let mut stderr = child.stderr.take().expect("restic stderr is piped");
+
+    let stderr_reader = tokio::spawn(async move {
+        let mut buffer = Vec::new();
+        stderr.read_to_end(&mut buffer).await.map(|_| buffer)
+    });
+
+    let mut lines = BufReader::new(stdout).lines();
+    let mut captured_stdout = String::new();
+    let mut last_progress_update: Option<Instant> = None;
+    let mut watchdog = Watchdog::new(limits, tokio::time::Instant::now());
+    let mut stopped: Option<StopReason> = None;
+    let mut cancel_open = true;
+    let mut kill_deadline: Option<tokio::time::Instant> = None;
+
+    loop {
+        let now = tokio::time::Instant::now();
+        let watchdog_deadline = watchdog.deadline().filter(|_| stopped.is_none());
+        let watchdog_sleep = tokio::time::sleep_until(watchdog_deadline.unwrap_or(now));
+        let kill_sleep = tokio::time::sleep_until(kill_deadline.unwrap_or(now));
+
+        tokio::select! {
+            line = lines.next_line() => {
+                let line = match line.map_err(execution_error)? {
+                    Some(line) => line,
+                    None => break,
+                };
+
+                if let Some(progress) = parse_status_line(&line) {
+                    watchdog.observe(&progress, tokio::time::Instant::now());
+
+                    // Local runs only publish progress until the journal was replayed
+                    let db_run_id = journal.resolve(run_id);
+                    let update_due = last_progress_update
+                        .map(|last| last.elapsed() >= PROGRESS_DB_UPDATE_INTERVAL)
+                        .unwrap_or(true);
+
+                    if let Some(db_run_id) = db_run_id.filter(|_| update_due) {
+                        last_progress_update = Some(Instant::now());
+
+                        let progress_json = serde_json::to_value(&progress).unwrap_or_default();
+                        if let Err(e) = db::update_run_progress(pool, db_run_id, progress_json).await {
+                            warn!(
+                                trace_id = trace_id,
+                                run_id = run_id,
+                                "Failed to store backup progress: {}",
+                                e
+                            );
+                        }
+                    }
+
+                    handle.report_progress(progress);
+                    continue;
+                }
+
+                captured_stdout.push_str(&line);
+                captured_stdout.push('\n');
+            }
+            changed = handle.cancel.changed(), if cancel_open && stopped.is_none() => {
+                if changed.is_err() {
+                    cancel_open = false;
+                    continue;
+                }
+
+                let request = match handle.cancel.borrow_and_update().clone() {
+                    Some(request) => request,
+                    None => continue,
+                };
+
+                info!(
+                    trace_id = trace_id,
+                    run_id = run_id,
+                    requested_by = %request.requested_by,
+                    reason = %request.reason,
+                    "Cancelling backup, interrupting restic"
+                );
+
+                kill_deadline = Some(interrupt_restic(&mut child, trace_id));
+                stopped = Some(StopReason::Cancelled(request));
+            }
+            _ = watchdog_sleep, if watchdog_deadline.is_some() => {
+                let trigger = match watchdog.check(tokio::time::Instant::now()) {
+                    Some(trigger) => trigger,
+                    None => continue,
+                };
+
+                warn!(
+                    trace_id = trace_id,
+                    run_id = run_id,
+                    failure_reason = trigger.failure_reason(),
+                    "Stopping backup, {}",
+                    trigger
+                );
+
+                kill_deadline = Some(interrupt_restic(&mut child, trace_id));
+                stopped = Some(StopReason::Watchdog(trigger));
+            }
+            _ = kill_sleep, if kill_deadline.is_some() => {
+                warn!(
+                    trace_id = trace_id,
+                    run_id = run_id,
+                    "restic did not stop within {} seconds, killing it",
+                    CANCEL_GRACE_PERIOD.as_secs()
+                );
+
+                if let Err(e) = child.start_kill() {
+                    warn!(trace_id = trace_id, "Failed to kill restic: {}", e);
+                }
+                kill_deadline = None;
+            }
+        }
+    }
+
+    let status = child.wait().await.map_err(execution_error)?;
+
+    let stderr = stderr_reader
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))
+        .and_then(|result| result)
+        .map_err(execution_error)?;
+
+    Ok(ResticExecution {
+        output: Output {
+            status,
+            stdout: captured_stdout.into_bytes(),
+            stderr,
+        },
+        stopped,
     })
 }
 
@@ -93,33 +332,287 @@ fn extract_error_message(stderr: &str) -> String {
     }
 }
 
+// Runs started while the database was unavailable are only cancelled in this process
+pub async fn cancel_backup(
+    pool: &PgPool,
+    journal: &RunJournal,
+    registry: &RunRegistry,
+    device_id: &str,
+    run_id: i32,
+    request: CancelRequest,
+) -> Result<bool> {
+    let db_run_id = match journal.resolve(run_id) {
+        Some(db_run_id) => db_run_id,
+        None => return Ok(registry.cancel(run_id, request).await),
+    };
+
+    let requested = db::request_run_cancellation(
+        pool,
+        db_run_id,
+        device_id.to_string(),
+        request.requested_by.clone(),
+        request.reason.clone(),
+    )
+    .await?;
+
+    if requested {
+        registry.cancel(run_id, request).await;
+    }
+
+    Ok(requested)
+}
+
+// Every retry is a run of its own, linked to the first attempt by retry_of
+#[allow(clippy::too_many_arguments)]
 pub async fn execute_backup(
     job: &BackupJob,
     config: &RemoteConfig,
     pool: &PgPool,
+    journal: &RunJournal,
+    registry: &RunRegistry,
+    repository: Option<&Repository>,
+    triggered_by: &str,
     trace_id: String,
 ) -> Result<i32> {
     info!(
         trace_id = trace_id,
         job_id = %job.id,
         job_name = %job.name,
+        repository = repository_label(repository),
+        triggered_by = triggered_by,
         "Starting backup execution"
     );
 
-    let run_id = db::create_run(pool, job.id, job.device_id.clone(), "manual".to_string()).await?;
-    debug!(trace_id = trace_id, run_id = run_id, "Created run record");
+    let repository_id = repository.map(|r| r.id);
 
-    let restic_cmd = ResticCommand::new(config)?;
+    let policy = RetryPolicy::for_job(job, config);
+    let mut run_ids = Vec::new();
+    let mut attempt: u32 = 1;
 
-    let output = match execute_restic_command(&restic_cmd, job, &trace_id).await {
-        Ok(output) => output,
+    let result = loop {
+        let run_id = match journal
+            .create_run(
+                pool,
+                job.id,
+                job.device_id.clone(),
+                repository_id,
+                triggered_by.to_string(),
+                run_ids.first().copied(),
+                attempt as i32,
+            )
+            .await
+        {
+            Ok(run_id) => run_id,
+            Err(e) => break Err(e),
+        };
+        run_ids.push(run_id);
+        debug!(
+            trace_id = trace_id,
+            run_id = run_id,
+            attempt = attempt,
+            "Created run record"
+        );
+        registry.events().publish(Event::RunStarted {
+            run_id,
+            job_id: job.id,
+            repository_id,
+            triggered_by: triggered_by.to_string(),
+            attempt: attempt as i32,
+        });
+
+        let result = execute_attempt(
+            job, config, pool, journal, registry, repository, run_id, &trace_id,
+        )
+        .await;
+
+        match journal.get_run(pool, run_id).await {
+            Ok(Some(run)) => registry.events().publish(Event::run_finished(&run)),
+            Ok(None) => {}
+            Err(e) => warn!(
+                trace_id = trace_id,
+                run_id = run_id,
+                "Failed to load finished run for its event: {}",
+                e
+            ),
+        }
+
+        let failure = match &result {
+            Err(AppError::Backup(BackupError::ResticFailed(failure, _))) => *failure,
+            _ => break result,
+        };
+        if !failure.is_transient() || attempt > policy.retries {
+            break result;
+        }
+
+        let delay = policy.delay(attempt);
+        warn!(
+            trace_id = trace_id,
+            run_id = run_id,
+            attempt = attempt,
+            retry_in_seconds = delay.as_secs(),
+            "Backup failed because the {}, retrying",
+            failure
+        );
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    };
+
+    for run_id in run_ids {
+        journal.release(run_id);
+    }
+
+    result
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn execute_attempt(
+    job: &BackupJob,
+    config: &RemoteConfig,
+    pool: &PgPool,
+    journal: &RunJournal,
+    registry: &RunRegistry,
+    repository: Option<&Repository>,
+    run_id: i32,
+    trace_id: &str,
+) -> Result<i32> {
+    let mut handle = registry.register(run_id, job.id).await;
+
+    let result = match hooks::run_hooks(
+        job,
+        config,
+        pool,
+        journal,
+        run_id,
+        HookTiming::Start,
+        trace_id,
+    )
+    .await
+    {
+        Ok(()) => {
+            run_backup(
+                job,
+                config,
+                pool,
+                journal,
+                repository,
+                run_id,
+                &mut handle,
+                trace_id,
+            )
+            .await
+        }
+        Err(e) => {
+            error!(
+                trace_id = trace_id,
+                run_id = run_id,
+                "Backup aborted by start hook: {}",
+                e
+            );
+            update_run_with_failure(pool, journal, run_id, e.to_string(), None, None, None).await?;
+            journal
+                .set_run_failure_reason(pool, run_id, "hook".to_string())
+                .await?;
+            Err(e)
+        }
+    };
+
+    registry.unregister(run_id).await;
+
+    run_finish_hooks(job, config, pool, journal, run_id, trace_id).await;
+
+    result
+}
+
+// Hook failures at this point are only logged, they do not change the result of the backup
+async fn run_finish_hooks(
+    job: &BackupJob,
+    config: &RemoteConfig,
+    pool: &PgPool,
+    journal: &RunJournal,
+    run_id: i32,
+    trace_id: &str,
+) {
+    if let Err(e) = hooks::run_hooks(
+        job,
+        config,
+        pool,
+        journal,
+        run_id,
+        HookTiming::End,
+        trace_id,
+    )
+    .await
+    {
+        warn!(trace_id = trace_id, run_id = run_id, "{}", e);
+    }
+
+    let failed = match journal.get_run(pool, run_id).await {
+        Ok(run) => run.is_some_and(|run| run.status == "failed"),
+        Err(e) => {
+            warn!(
+                trace_id = trace_id,
+                run_id = run_id,
+                "Failed to load run for failure hooks: {}",
+                e
+            );
+            false
+        }
+    };
+
+    if failed {
+        if let Err(e) = hooks::run_hooks(
+            job,
+            config,
+            pool,
+            journal,
+            run_id,
+            HookTiming::Failure,
+            trace_id,
+        )
+        .await
+        {
+            warn!(trace_id = trace_id, run_id = run_id, "{}", e);
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn run_backup(
+    job: &BackupJob,
+    config: &RemoteConfig,
+    pool: &PgPool,
+    journal: &RunJournal,
+    repository: Option<&Repository>,
+    run_id: i32,
+    handle: &mut RunHandle,
+    trace_id: &str,
+) -> Result<i32> {
+    let restic_cmd = ResticCommand::new(config, repository)?;
+
+    let limits = RunLimits::for_job(job, config);
+
+    let execution = match execute_restic_command(
+        &restic_cmd,
+        job,
+        pool,
+        journal,
+        run_id,
+        handle,
+        limits,
+        trace_id,
+    )
+    .await
+    {
+        Ok(execution) => execution,
         Err(e) => {
             let error_msg = e.to_string();
-            update_run_with_failure(pool, run_id, error_msg.clone(), None, None, None).await?;
+            update_run_with_failure(pool, journal, run_id, error_msg.clone(), None, None, None)
+                .await?;
             return Err(e);
         }
     };
 
+    let output = execution.output;
     let exit_code = output.status.code().unwrap_or(-1);
     let stdout = String::from_utf8_lossy(&output.stdout).to_string();
     let stderr = String::from_utf8_lossy(&output.stderr).to_string();
@@ -130,17 +623,82 @@ pub async fn execute_backup(
         "Backup command completed"
     );
 
-    if !output.status.success() {
+    // A backup that completed before restic received the interrupt is kept as successful
+    match execution.stopped.filter(|_| !output.status.success()) {
+        Some(StopReason::Cancelled(request)) => {
+            warn!(
+                trace_id = trace_id,
+                run_id = run_id,
+                requested_by = %request.requested_by,
+                "Backup cancelled: {}",
+                request.reason
+            );
+
+            let cancellation = RunCancellation {
+                end_time: Utc::now(),
+                exit_code: output.status.code(),
+                requested_by: request.requested_by.clone(),
+                reason: request.reason.clone(),
+                restic_output: Some(stdout),
+                restic_errors: Some(stderr).filter(|s| !s.is_empty()),
+            };
+            journal
+                .update_run_cancelled(pool, run_id, cancellation)
+                .await?;
+
+            return Err(crate::error::BackupError::Cancelled(format!(
+                "cancelled by {}: {}",
+                request.requested_by, request.reason
+            ))
+            .into());
+        }
+        Some(StopReason::Watchdog(trigger)) => {
+            let error_msg = format!("Backup stopped: {}", trigger);
+            error!(
+                trace_id = trace_id,
+                run_id = run_id,
+                failure_reason = trigger.failure_reason(),
+                "{}",
+                error_msg
+            );
+
+            update_run_with_failure(
+                pool,
+                journal,
+                run_id,
+                error_msg.clone(),
+                Some(exit_code),
+                Some(stdout),
+                Some(stderr),
+            )
+            .await?;
+            journal
+                .set_run_failure_reason(pool, run_id, trigger.failure_reason().to_string())
+                .await?;
+
+            return Err(crate::error::BackupError::TimedOut(trigger.to_string()).into());
+        }
+        None => {}
+    }
+
+    // The snapshot of the readable source files is still created and kept
+    let partial =
+        exit_code == PARTIAL_BACKUP_EXIT_CODE && parse_restic_json_output(&stdout).is_ok();
+
+    if !output.status.success() && !partial {
         let error_msg = extract_error_message(&stderr);
+        let failure = ResticFailure::classify(output.status.code(), &stderr);
         warn!(
             trace_id = trace_id,
             exit_code = exit_code,
+            failure_reason = failure.failure_reason(),
             "Backup failed: {}",
             error_msg
         );
 
         update_run_with_failure(
             pool,
+            journal,
             run_id,
             error_msg.clone(),
             Some(exit_code),
@@ -148,8 +706,13 @@ pub async fn execute_backup(
             Some(stderr),
         )
         .await?;
+        if let Some(reason) = failure.failure_reason() {
+            journal
+                .set_run_failure_reason(pool, run_id, reason.to_string())
+                .await?;
+        }
 
-        return Err(crate::error::BackupError::ExecutionFailed(error_msg).into());
+        return Err(BackupError::ResticFailed(failure, error_msg).into());
     }
 
     let stats = match parse_restic_json_output(&stdout) {
@@ -160,6 +723,7 @@ pub async fn execute_backup(
 
             update_run_with_failure(
                 pool,
+                journal,
                 run_id,
                 error_msg,
                 Some(exit_code),
@@ -172,14 +736,11 @@ pub async fn execute_backup(
         }
     };
 
-    info!(
-        trace_id = trace_id,
-        snapshot_id = %stats.snapshot_id,
-        files_new = stats.files_new,
-        files_changed = stats.files_changed,
-        data_added_mb = stats.data_added_bytes / 1024 / 1024,
-        "Backup completed successfully"
-    );
+    let failed_paths = if partial {
+        parse_backup_errors(&stdout, &stderr)
+    } else {
+        Vec::new()
+    };
 
     let stderr_opt = if !stderr.is_empty() {
         Some(stderr)
@@ -187,7 +748,68 @@ pub async fn execute_backup(
         None
     };
 
-    update_run_with_success(pool, run_id, exit_code, &stats, stdout, stderr_opt).await?;
+    if partial {
+        warn!(
+            trace_id = trace_id,
+            snapshot_id = %stats.snapshot_id,
+            failed_paths = failed_paths.len(),
+            "Backup completed, but some source files could not be read"
+        );
+
+        update_run_with_partial_success(
+            pool,
+            journal,
+            run_id,
+            exit_code,
+            &stats,
+            &failed_paths,
+            stdout,
+            stderr_opt,
+        )
+        .await?;
+    } else {
+        info!(
+            trace_id = trace_id,
+            snapshot_id = %stats.snapshot_id,
+            files_new = stats.files_new,
+            files_changed = stats.files_changed,
+            data_added_mb = stats.data_added_bytes / 1024 / 1024,
+            "Backup completed successfully"
+        );
+
+        update_run_with_success(pool, journal, run_id, exit_code, &stats, stdout, stderr_opt)
+            .await?;
+    }
+
+    if let Some(repository) = repository {
+        journal
+            .record_repository_success(
+                pool,
+                job.id,
+                repository.id,
+                run_id,
+                stats.snapshot_id.clone(),
+            )
+            .await?;
+    }
+
+    if let Err(e) = retention::execute_forget(
+        job,
+        config,
+        pool,
+        repository,
+        journal.resolve(run_id),
+        trace_id,
+    )
+    .await
+    {
+        warn!(
+            trace_id = trace_id,
+            run_id = run_id,
+            "Failed to apply retention policy: {}",
+            e
+        );
+    }
 
     Ok(run_id)
 }
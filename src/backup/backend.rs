@@ -0,0 +1,503 @@
+use crate::config::remote::RemoteConfig;
+use crate::config::secret::Secret;
+use crate::db::models::{BackendCredentials, Repository};
+use crate::error::{BackupError, Result};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    Local,
+    Sftp,
+    Rest,
+    S3,
+    B2,
+    Azure,
+    GoogleCloudStorage,
+    Swift,
+    Rclone,
+}
+
+impl Backend {
+    pub fn from_url(url: &str) -> Self {
+        match url.split_once(':').map(|(scheme, _)| scheme) {
+            Some("sftp") => Backend::Sftp,
+            Some("rest") => Backend::Rest,
+            Some("s3") => Backend::S3,
+            Some("b2") => Backend::B2,
+            Some("azure") => Backend::Azure,
+            Some("gs") => Backend::GoogleCloudStorage,
+            Some("swift") => Backend::Swift,
+            Some("rclone") => Backend::Rclone,
+            // Local paths, including Windows paths like C:\backup
+            _ => Backend::Local,
+        }
+    }
+
+    fn required_variables(&self) -> &'static [&'static str] {
+        match self {
+            Backend::B2 => &["B2_ACCOUNT_ID", "B2_ACCOUNT_KEY"],
+            Backend::Azure => &["AZURE_ACCOUNT_NAME"],
+            _ => &[],
+        }
+    }
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Backend::Local => write!(f, "local"),
+            Backend::Sftp => write!(f, "sftp"),
+            Backend::Rest => write!(f, "rest"),
+            Backend::S3 => write!(f, "s3"),
+            Backend::B2 => write!(f, "b2"),
+            Backend::Azure => write!(f, "azure"),
+            Backend::GoogleCloudStorage => write!(f, "gs"),
+            Backend::Swift => write!(f, "swift"),
+            Backend::Rclone => write!(f, "rclone"),
+        }
+    }
+}
+
+impl BackendCredentials {
+    pub fn backend(&self) -> Backend {
+        match self {
+            BackendCredentials::S3 { .. } => Backend::S3,
+            BackendCredentials::B2 { .. } => Backend::B2,
+            BackendCredentials::Azure { .. } => Backend::Azure,
+            BackendCredentials::Rest { .. } => Backend::Rest,
+            BackendCredentials::Sftp { .. } => Backend::Sftp,
+        }
+    }
+
+    pub fn validate(&self, backend: Backend) -> std::result::Result<(), String> {
+        if self.backend() != backend {
+            return Err(format!(
+                "{} credentials cannot be used with a {} repository",
+                self.backend(),
+                backend
+            ));
+        }
+
+        let required: Vec<(&str, &str)> = match self {
+            BackendCredentials::S3 {
+                access_key_id,
+                secret_access_key,
+                ..
+            } => vec![
+                ("access_key_id", access_key_id),
+                ("secret_access_key", secret_access_key),
+            ],
+            BackendCredentials::B2 {
+                account_id,
+                account_key,
+            } => vec![("account_id", account_id), ("account_key", account_key)],
+            BackendCredentials::Azure {
+                account_name,
+                account_key,
+                account_sas,
+                ..
+            } => {
+                if account_key.is_some() == account_sas.is_some() {
+                    return Err(
+                        "azure credentials need exactly one of account_key and account_sas"
+                            .to_string(),
+                    );
+                }
+                vec![("account_name", account_name)]
+            }
+            BackendCredentials::Rest { username, password } => {
+                vec![("username", username), ("password", password)]
+            }
+            BackendCredentials::Sftp { command, args } => {
+                // restic refuses to start with both options set
+                if command.is_some() && args.is_some() {
+                    return Err("sftp credentials cannot set both command and args".to_string());
+                }
+                vec![]
+            }
+        };
+
+        match required.iter().find(|(_, value)| value.trim().is_empty()) {
+            Some((field, _)) => Err(format!("{} credentials: {} is empty", backend, field)),
+            None => Ok(()),
+        }
+    }
+
+    // Keys and passwords may be secret references, resolved like repository passwords
+    pub fn environment_variables(&self) -> Result<Vec<(&'static str, String)>> {
+        let variables = match self {
+            BackendCredentials::S3 {
+                access_key_id,
+                secret_access_key,
+                session_token,
+                region,
+            } => vec![
+                ("AWS_ACCESS_KEY_ID", Some(access_key_id), false),
+                ("AWS_SECRET_ACCESS_KEY", Some(secret_access_key), true),
+                ("AWS_SESSION_TOKEN", session_token.as_ref(), true),
+                ("AWS_DEFAULT_REGION", region.as_ref(), false),
+            ],
+            BackendCredentials::B2 {
+                account_id,
+                account_key,
+            } => vec![
+                ("B2_ACCOUNT_ID", Some(account_id), false),
+                ("B2_ACCOUNT_KEY", Some(account_key), true),
+            ],
+            BackendCredentials::Azure {
+                account_name,
+                account_key,
+                account_sas,
+                endpoint_suffix,
+            } => vec![
+                ("AZURE_ACCOUNT_NAME", Some(account_name), false),
+                ("AZURE_ACCOUNT_KEY", account_key.as_ref(), true),
+                ("AZURE_ACCOUNT_SAS", account_sas.as_ref(), true),
+                ("AZURE_ENDPOINT_SUFFIX", endpoint_suffix.as_ref(), false),
+            ],
+            BackendCredentials::Rest { username, password } => vec![
+                ("RESTIC_REST_USERNAME", Some(username), false),
+                ("RESTIC_REST_PASSWORD", Some(password), true),
+            ],
+            BackendCredentials::Sftp { .. } => vec![],
+        };
+
+        variables
+            .into_iter()
+            .filter_map(|(key, value, secret)| value.map(|value| (key, value, secret)))
+            .map(|(key, value, secret)| {
+                let value = match secret {
+                    true => Secret::parse(value).resolve()?,
+                    false => value.clone(),
+                };
+                Ok((key, value))
+            })
+            .collect()
+    }
+
+    pub fn extended_options(&self) -> Vec<String> {
+        match self {
+            BackendCredentials::Sftp { command, args } => [
+                command.as_ref().map(|c| format!("sftp.command={}", c)),
+                args.as_ref().map(|a| format!("sftp.args={}", a)),
+            ]
+            .into_iter()
+            .flatten()
+            .collect(),
+            _ => vec![],
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq)]
+pub enum ResticPassword {
+    Value(String),
+    File(PathBuf),
+    Command(String),
+}
+
+impl ResticPassword {
+    // File and command secrets are left to restic, so the password never passes through here
+    pub fn from_secret(secret: &Secret) -> Result<Self> {
+        match secret {
+            Secret::File(path) => Ok(ResticPassword::File(path.clone())),
+            Secret::Command(command) => Ok(ResticPassword::Command(command.clone())),
+            other => other.resolve().map(ResticPassword::Value),
+        }
+    }
+}
+
+impl fmt::Debug for ResticPassword {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResticPassword::Value(_) => write!(f, "Value(***)"),
+            ResticPassword::File(path) => write!(f, "File({})", path.display()),
+            ResticPassword::Command(command) => write!(f, "Command({})", command),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BackendSettings {
+    pub url: String,
+    pub password: ResticPassword,
+    pub cache_dir: Option<String>,
+    pub environment: HashMap<String, String>,
+    pub options: Vec<String>,
+}
+
+impl BackendSettings {
+    // Typed credentials take precedence over variables of the same name in the environment
+    pub fn resolve(config: &RemoteConfig, repository: Option<&Repository>) -> Result<Self> {
+        let (url, password, credentials) = match repository {
+            Some(repository) => (
+                repository.url.clone(),
+                repository.password.clone(),
+                repository.credentials.as_ref().map(|c| c.0.clone()),
+            ),
+            None => {
+                let url = config.repository_url().ok_or_else(|| {
+                    BackupError::ConfigurationError("Repository URL not set".to_string())
+                })?;
+
+                let password = config.repository_password().ok_or_else(|| {
+                    BackupError::ConfigurationError("Repository password not set".to_string())
+                })?;
+
+                (
+                    url.clone(),
+                    password.clone(),
+                    config.repository_credentials()?,
+                )
+            }
+        };
+
+        let name = super::repository_label(repository);
+        let password = ResticPassword::from_secret(&Secret::parse(&password))?;
+
+        let cache_dir = repository
+            .and_then(|r| r.cache_dir.clone())
+            .or_else(|| config.repository_cache_dir().cloned());
+
+        let mut environment: HashMap<String, String> = repository
+            .map(|r| r.environment_variables().into_iter().collect())
+            .unwrap_or_default();
+
+        let backend = Backend::from_url(&url);
+        let mut options = Vec::new();
+
+        if let Some(credentials) = credentials {
+            credentials.validate(backend).map_err(|e| {
+                BackupError::ConfigurationError(format!("Repository {}: {}", name, e))
+            })?;
+
+            for (key, value) in credentials.environment_variables()? {
+                environment.insert(key.to_string(), value);
+            }
+            options = credentials.extended_options();
+        }
+
+        if let Some(missing) = backend
+            .required_variables()
+            .iter()
+            .find(|key| !environment.contains_key(**key))
+        {
+            return Err(BackupError::ConfigurationError(format!(
+                "Repository {}: {} repository needs credentials ({} is not set)",
+                name, backend, missing
+            ))
+            .into());
+        }
+
+        Ok(Self {
+            url,
+            password,
+            cache_dir,
+            environment,
+            options,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn s3_credentials() -> BackendCredentials {
+        BackendCredentials::S3 {
+            access_key_id: "AKIAEXAMPLE".to_string(),
+            secret_access_key: "secret".to_string(),
+            session_token: None,
+            region: Some("eu-central-1".to_string()),
+        }
+    }
+
+    fn config(url: &str, credentials: Option<&str>) -> RemoteConfig {
+        let mut settings = HashMap::new();
+        settings.insert("repository_url".to_string(), url.to_string());
+        settings.insert("repository_password".to_string(), "secret".to_string());
+        if let Some(credentials) = credentials {
+            settings.insert(
+                "repository_credentials".to_string(),
+                credentials.to_string(),
+            );
+        }
+
+        RemoteConfig {
+            jobs: vec![],
+            schedules: vec![],
+            hooks: vec![],
+            repositories: vec![],
+            job_repositories: vec![],
+            settings,
+        }
+    }
+
+    #[test]
+    fn test_backend_from_url() {
+        assert_eq!(Backend::from_url("/srv/restic"), Backend::Local);
+        assert_eq!(Backend::from_url("C:\\backup"), Backend::Local);
+        assert_eq!(Backend::from_url("sftp:user@host:/srv"), Backend::Sftp);
+        assert_eq!(
+            Backend::from_url("rest:https://host:8000/repo"),
+            Backend::Rest
+        );
+        assert_eq!(
+            Backend::from_url("s3:https://s3.amazonaws.com/bucket"),
+            Backend::S3
+        );
+        assert_eq!(Backend::from_url("b2:bucket:path"), Backend::B2);
+        assert_eq!(Backend::from_url("azure:container:/"), Backend::Azure);
+    }
+
+    #[test]
+    fn test_validate_credentials() {
+        assert!(s3_credentials().validate(Backend::S3).is_ok());
+        assert_eq!(
+            s3_credentials().validate(Backend::B2),
+            Err("s3 credentials cannot be used with a b2 repository".to_string())
+        );
+
+        let empty_key = BackendCredentials::B2 {
+            account_id: "id".to_string(),
+            account_key: " ".to_string(),
+        };
+        assert_eq!(
+            empty_key.validate(Backend::B2),
+            Err("b2 credentials: account_key is empty".to_string())
+        );
+
+        let azure = BackendCredentials::Azure {
+            account_name: "account".to_string(),
+            account_key: Some("key".to_string()),
+            account_sas: Some("sas".to_string()),
+            endpoint_suffix: None,
+        };
+        assert!(azure.validate(Backend::Azure).is_err());
+
+        let sftp = BackendCredentials::Sftp {
+            command: Some("ssh -i /root/.ssh/backup host -s sftp".to_string()),
+            args: Some("-p 2222".to_string()),
+        };
+        assert!(sftp.validate(Backend::Sftp).is_err());
+    }
+
+    #[test]
+    fn test_credentials_environment_and_options() {
+        assert_eq!(
+            s3_credentials()
+                .environment_variables()
+                .expect("Failed to resolve credentials"),
+            vec![
+                ("AWS_ACCESS_KEY_ID", "AKIAEXAMPLE".to_string()),
+                ("AWS_SECRET_ACCESS_KEY", "secret".to_string()),
+                ("AWS_DEFAULT_REGION", "eu-central-1".to_string()),
+            ]
+        );
+
+        let sftp = BackendCredentials::Sftp {
+            command: None,
+            args: Some("-i /root/.ssh/backup".to_string()),
+        };
+        assert!(sftp
+            .environment_variables()
+            .expect("Failed to resolve credentials")
+            .is_empty());
+        assert_eq!(
+            sftp.extended_options(),
+            vec!["sftp.args=-i /root/.ssh/backup"]
+        );
+    }
+
+    #[test]
+    fn test_credentials_secret_references() {
+        std::env::set_var("RBACKUP2_TEST_B2_KEY", "from-env");
+        let b2 = BackendCredentials::B2 {
+            account_id: "env:not-a-reference".to_string(),
+            account_key: "env:RBACKUP2_TEST_B2_KEY".to_string(),
+        };
+        assert_eq!(
+            b2.environment_variables()
+                .expect("Failed to resolve credentials"),
+            vec![
+                ("B2_ACCOUNT_ID", "env:not-a-reference".to_string()),
+                ("B2_ACCOUNT_KEY", "from-env".to_string()),
+            ]
+        );
+
+        let rest = BackendCredentials::Rest {
+            username: "backup".to_string(),
+            password: "env:RBACKUP2_TEST_UNSET".to_string(),
+        };
+        assert!(rest.environment_variables().is_err());
+    }
+
+    #[test]
+    fn test_credentials_deserialization() {
+        let credentials: BackendCredentials =
+            serde_json::from_str(r#"{"type": "rest", "username": "backup", "password": "secret"}"#)
+                .expect("Failed to parse credentials");
+        assert_eq!(credentials.backend(), Backend::Rest);
+
+        // Misspelled fields must not be silently ignored
+        assert!(serde_json::from_str::<BackendCredentials>(
+            r#"{"type": "s3", "access_key": "a", "secret_access_key": "b"}"#
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_restic_password_from_secret() {
+        assert_eq!(
+            ResticPassword::from_secret(&Secret::parse("file:/etc/rbackup2/repository"))
+                .expect("Failed to resolve password"),
+            ResticPassword::File(PathBuf::from("/etc/rbackup2/repository"))
+        );
+        assert_eq!(
+            ResticPassword::from_secret(&Secret::parse("command:pass show restic"))
+                .expect("Failed to resolve password"),
+            ResticPassword::Command("pass show restic".to_string())
+        );
+        assert!(ResticPassword::from_secret(&Secret::parse("env:RBACKUP2_TEST_UNSET")).is_err());
+    }
+
+    #[test]
+    fn test_resolve_default_repository() {
+        let settings = BackendSettings::resolve(
+            &config(
+                "rest:http://localhost:8000/",
+                Some(r#"{"type": "rest", "username": "backup", "password": "pw"}"#),
+            ),
+            None,
+        )
+        .expect("Failed to resolve backend settings");
+
+        assert_eq!(
+            settings.environment.get("RESTIC_REST_USERNAME"),
+            Some(&"backup".to_string())
+        );
+        assert_eq!(
+            settings.environment.get("RESTIC_REST_PASSWORD"),
+            Some(&"pw".to_string())
+        );
+        assert_eq!(
+            settings.password,
+            ResticPassword::Value("secret".to_string())
+        );
+
+        // Credentials of another backend are rejected
+        assert!(BackendSettings::resolve(
+            &config(
+                "/srv/restic",
+                Some(r#"{"type": "rest", "username": "backup", "password": "pw"}"#),
+            ),
+            None,
+        )
+        .is_err());
+
+        // B2 cannot work without credentials
+        assert!(BackendSettings::resolve(&config("b2:bucket:path", None), None).is_err());
+    }
+}
@@ -0,0 +1,440 @@
+use crate::backup::output::parse_check_errors;
+use crate::backup::restic::ResticCommand;
+use crate::backup::retention::update_maintenance_with_failure;
+use crate::backup::{interrupt_restic, repository_label, CANCEL_GRACE_PERIOD};
+use crate::config::remote::RemoteConfig;
+use crate::db;
+use crate::db::models::Repository;
+use crate::error::{BackupError, Result};
+use crate::events::{Event, EventBus};
+use chrono::Utc;
+use sqlx::PgPool;
+use std::fmt;
+use std::process::Output;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::process::Command;
+use tokio::task::JoinHandle;
+use tokio::time::Duration;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReadDataSubset {
+    pub index: u32,
+    pub total: u32,
+}
+
+impl ReadDataSubset {
+    pub fn parse(value: &str) -> Option<Self> {
+        let (index, total) = value.split_once('/')?;
+        let index: u32 = index.trim().parse().ok()?;
+        let total: u32 = total.trim().parse().ok()?;
+
+        if total == 0 || index == 0 || index > total {
+            return None;
+        }
+
+        Some(Self { index, total })
+    }
+
+    // Starts over when the number of subsets changed or the previous check read no data
+    pub fn next_after(last: Option<&str>, total: u32) -> Option<Self> {
+        if total == 0 {
+            return None;
+        }
+
+        let index = match last.and_then(Self::parse) {
+            Some(last) if last.total == total => last.index % total + 1,
+            _ => 1,
+        };
+
+        Some(Self { index, total })
+    }
+}
+
+impl fmt::Display for ReadDataSubset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.index, self.total)
+    }
+}
+
+// Output of restic check; `timed_out` if it was stopped for exceeding the maximum runtime
+struct CheckExecution {
+    output: Output,
+    timed_out: bool,
+}
+
+// Stopped after max_runtime like a backup stopped by the watchdog
+async fn execute_check_command(
+    mut command: Command,
+    max_runtime: Option<Duration>,
+    trace_id: &str,
+) -> std::io::Result<CheckExecution> {
+    let mut child = command.spawn()?;
+
+    let stdout_reader = read_to_end(child.stdout.take().expect("restic stdout is piped"));
+    let stderr_reader = read_to_end(child.stderr.take().expect("restic stderr is piped"));
+
+    let mut timed_out = false;
+    let status = match max_runtime {
+        Some(limit) => match tokio::time::timeout(limit, child.wait()).await {
+            Ok(status) => status?,
+            Err(_) => {
+                warn!(
+                    trace_id = trace_id,
+                    failure_reason = "timeout",
+                    "Stopping repository check, it exceeded the maximum runtime of {} minutes",
+                    limit.as_secs() / 60
+                );
+                timed_out = true;
+
+                let kill_deadline = interrupt_restic(&mut child, trace_id);
+                match tokio::time::timeout_at(kill_deadline, child.wait()).await {
+                    Ok(status) => status?,
+                    Err(_) => {
+                        warn!(
+                            trace_id = trace_id,
+                            "restic did not stop within {} seconds, killing it",
+                            CANCEL_GRACE_PERIOD.as_secs()
+                        );
+                        child.start_kill()?;
+                        child.wait().await?
+                    }
+                }
+            }
+        },
+        None => child.wait().await?,
+    };
+
+    let join = |e: tokio::task::JoinError| std::io::Error::other(e.to_string());
+    let stdout = stdout_reader.await.map_err(join)??;
+    let stderr = stderr_reader.await.map_err(join)??;
+
+    Ok(CheckExecution {
+        output: Output {
+            status,
+            stdout,
+            stderr,
+        },
+        timed_out,
+    })
+}
+
+fn read_to_end<R>(mut reader: R) -> JoinHandle<std::io::Result<Vec<u8>>>
+where
+    R: AsyncRead + Unpin + Send + 'static,
+{
+    tokio::spawn(async move {
+        let mut buffer = Vec::new();
+        reader.read_to_end(&mut buffer).await.map(|_| buffer)
+    })
+}
+
+// Allow many arguments: the check is recorded with the restic output and the failure reason
+#[allow(clippy::too_many_arguments)]
+async fn record_check_failure(
+    pool: &PgPool,
+    events: &EventBus,
+    maintenance_run_id: i32,
+    repository_id: Option<Uuid>,
+    error_msg: String,
+    failure_reason: Option<&str>,
+    exit_code: Option<i32>,
+    stdout: Option<String>,
+    stderr: Option<String>,
+) -> Result<()> {
+    update_maintenance_with_failure(
+        pool,
+        maintenance_run_id,
+        error_msg.clone(),
+        exit_code,
+        stdout,
+        stderr,
+    )
+    .await?;
+
+    if let Some(reason) = failure_reason {
+        db::set_maintenance_failure_reason(pool, maintenance_run_id, reason.to_string()).await?;
+    }
+
+    events.publish(Event::MaintenanceFinished {
+        maintenance_run_id,
+        task_type: "check".to_string(),
+        repository_id,
+        status: "failed".to_string(),
+        error_message: Some(error_msg),
+        failure_reason: failure_reason.map(str::to_string),
+    });
+
+    Ok(())
+}
+
+pub async fn execute_check(
+    config: &RemoteConfig,
+    pool: &PgPool,
+    events: &EventBus,
+    device_id: String,
+    repository: Option<&Repository>,
+    trace_id: &str,
+) -> Result<i32> {
+    let repository_id = repository.map(|r| r.id);
+    let last_check = db::get_last_maintenance_run(pool, "check".to_string(), repository_id).await?;
+    let read_data_subset = ReadDataSubset::next_after(
+        last_check
+            .as_ref()
+            .and_then(|r| r.read_data_subset.as_deref()),
+        config.check_read_data_subsets(),
+    );
+
+    info!(
+        trace_id = trace_id,
+        repository = repository_label(repository),
+        read_data_subset = ?read_data_subset.map(|s| s.to_string()),
+        "Starting repository check"
+    );
+
+    let maintenance_run_id = db::create_maintenance_run(
+        pool,
+        device_id,
+        repository_id,
+        None,
+        None,
+        "check".to_string(),
+        read_data_subset.map(|s| s.to_string()),
+        "schedule".to_string(),
+    )
+    .await?;
+
+    let restic_cmd = match ResticCommand::new(config, repository) {
+        Ok(restic_cmd) => restic_cmd,
+        Err(e) => {
+            record_check_failure(
+                pool,
+                events,
+                maintenance_run_id,
+                repository_id,
+                e.to_string(),
+                None,
+                None,
+                None,
+                None,
+            )
+            .await?;
+            return Err(e);
+        }
+    };
+
+    let max_runtime_minutes = config.check_max_runtime_minutes();
+    let max_runtime = Some(max_runtime_minutes)
+        .filter(|minutes| *minutes > 0)
+        .map(|minutes| Duration::from_secs(minutes * 60));
+
+    let execution = match execute_check_command(
+        restic_cmd.build_check_command(read_data_subset.as_ref()),
+        max_runtime,
+        trace_id,
+    )
+    .await
+    {
+        Ok(execution) => execution,
+        Err(e) => {
+            let error_msg = format!("Failed to execute restic: {}", e);
+            error!(trace_id = trace_id, "{}", error_msg);
+            record_check_failure(
+                pool,
+                events,
+                maintenance_run_id,
+                repository_id,
+                error_msg.clone(),
+                None,
+                None,
+                None,
+                None,
+            )
+            .await?;
+            return Err(BackupError::ExecutionFailed(error_msg).into());
+        }
+    };
+
+    let output = execution.output;
+    let exit_code = output.status.code().unwrap_or(-1);
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+
+    if execution.timed_out {
+        let error_msg = format!(
+            "restic check exceeded the maximum runtime of {} minutes",
+            max_runtime_minutes
+        );
+
+        record_check_failure(
+            pool,
+            events,
+            maintenance_run_id,
+            repository_id,
+            error_msg.clone(),
+            Some("timeout"),
+            output.status.code(),
+            Some(stdout),
+            Some(stderr),
+        )
+        .await?;
+
+        return Err(BackupError::ExecutionFailed(format!(
+            "Repository check failed: {}",
+            error_msg
+        ))
+        .into());
+    }
+
+    if !output.status.success() {
+        let errors = parse_check_errors(&stdout, &stderr);
+        let error_msg = if !errors.is_empty() {
+            errors.join("\n")
+        } else if !stderr.is_empty() {
+            stderr.clone()
+        } else {
+            "restic check failed with no error message".to_string()
+        };
+
+        error!(
+            trace_id = trace_id,
+            exit_code = exit_code,
+            errors = errors.len(),
+            "Repository check failed: {}",
+            error_msg
+        );
+
+        record_check_failure(
+            pool,
+            events,
+            maintenance_run_id,
+            repository_id,
+            error_msg.clone(),
+            None,
+            Some(exit_code),
+            Some(stdout),
+            Some(stderr),
+        )
+        .await?;
+
+        return Err(BackupError::ExecutionFailed(format!(
+            "Repository check failed: {}",
+            error_msg
+        ))
+        .into());
+    }
+
+    info!(trace_id = trace_id, "Repository check found no errors");
+
+    let stderr_opt = Some(stderr).filter(|s| !s.is_empty());
+    db::update_maintenance_run(
+        pool,
+        maintenance_run_id,
+        Utc::now(),
+        "success".to_string(),
+        Some(exit_code),
+        None,
+        None,
+        None,
+        Some(stdout),
+        stderr_opt,
+    )
+    .await?;
+
+    events.publish(Event::MaintenanceFinished {
+        maintenance_run_id,
+        task_type: "check".to_string(),
+        repository_id,
+        status: "success".to_string(),
+        error_message: None,
+        failure_reason: None,
+    });
+
+    Ok(maintenance_run_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_read_data_subset() {
+        assert_eq!(
+            ReadDataSubset::parse("3/10"),
+            Some(ReadDataSubset {
+                index: 3,
+                total: 10
+            })
+        );
+        assert_eq!(ReadDataSubset::parse("0/10"), None);
+        assert_eq!(ReadDataSubset::parse("11/10"), None);
+        assert_eq!(ReadDataSubset::parse("5%"), None);
+        assert_eq!(ReadDataSubset::parse(""), None);
+    }
+
+    #[test]
+    fn test_read_data_subset_rotation() {
+        let first = ReadDataSubset::next_after(None, 3).expect("Expected a subset");
+        assert_eq!(first.to_string(), "1/3");
+
+        let second = ReadDataSubset::next_after(Some("1/3"), 3).expect("Expected a subset");
+        assert_eq!(second.to_string(), "2/3");
+
+        let wrapped = ReadDataSubset::next_after(Some("3/3"), 3).expect("Expected a subset");
+        assert_eq!(wrapped.to_string(), "1/3");
+    }
+
+    #[test]
+    fn test_read_data_subset_rotation_restarts_when_total_changes() {
+        let subset = ReadDataSubset::next_after(Some("4/5"), 10).expect("Expected a subset");
+        assert_eq!(subset.to_string(), "1/10");
+
+        assert_eq!(ReadDataSubset::next_after(Some("1/3"), 0), None);
+    }
+
+    #[cfg(unix)]
+    fn piped_shell(script: &str) -> Command {
+        let mut command = Command::new("sh");
+        command
+            .arg("-c")
+            .arg(script)
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped());
+        command
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_check_command_output() {
+        let execution = execute_check_command(
+            piped_shell("echo checked; echo warning >&2; exit 1"),
+            Some(Duration::from_secs(60)),
+            "test",
+        )
+        .await
+        .expect("Failed to run command");
+
+        assert!(!execution.timed_out);
+        assert_eq!(execution.output.status.code(), Some(1));
+        assert_eq!(execution.output.stdout, b"checked\n");
+        assert_eq!(execution.output.stderr, b"warning\n");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_check_command_interrupted_after_max_runtime() {
+        let started = std::time::Instant::now();
+        let execution = execute_check_command(
+            piped_shell("exec sleep 60"),
+            Some(Duration::from_millis(100)),
+            "test",
+        )
+        .await
+        .expect("Failed to run command");
+
+        assert!(execution.timed_out);
+        assert!(!execution.output.status.success());
+        assert!(started.elapsed() < std::time::Duration::from_secs(10));
+    }
+}
@@ -0,0 +1,180 @@
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResticFailure {
+    RepositoryLocked,
+    BackendUnavailable,
+    WrongPassword,
+    RepositoryNotFound,
+    SourceMissing,
+    UnreadableFiles,
+    Other,
+}
+
+// Lowercase fragments of restic and SSH error messages, checked in order
+const PATTERNS: &[(&str, ResticFailure)] = &[
+    ("wrong password", ResticFailure::WrongPassword),
+    (
+        "repository is already locked",
+        ResticFailure::RepositoryLocked,
+    ),
+    ("unable to create lock", ResticFailure::RepositoryLocked),
+    (
+        "is there a repository at the following location",
+        ResticFailure::RepositoryNotFound,
+    ),
+    (
+        "repository does not exist",
+        ResticFailure::RepositoryNotFound,
+    ),
+    (
+        "source directories/files do not exist",
+        ResticFailure::SourceMissing,
+    ),
+    (
+        "target directories/files do not exist",
+        ResticFailure::SourceMissing,
+    ),
+    ("connection refused", ResticFailure::BackendUnavailable),
+    ("connection reset", ResticFailure::BackendUnavailable),
+    ("connection timed out", ResticFailure::BackendUnavailable),
+    ("no route to host", ResticFailure::BackendUnavailable),
+    ("network is unreachable", ResticFailure::BackendUnavailable),
+    ("i/o timeout", ResticFailure::BackendUnavailable),
+    (
+        "temporary failure in name resolution",
+        ResticFailure::BackendUnavailable,
+    ),
+    ("no such host", ResticFailure::BackendUnavailable),
+    (
+        "unable to start the sftp session",
+        ResticFailure::BackendUnavailable,
+    ),
+    ("ssh command exited", ResticFailure::BackendUnavailable),
+    ("service unavailable", ResticFailure::BackendUnavailable),
+];
+
+impl ResticFailure {
+    // The exit codes of restic 0.17 and newer take precedence over the error output
+    pub fn classify(exit_code: Option<i32>, stderr: &str) -> Self {
+        match exit_code {
+            Some(3) => return ResticFailure::UnreadableFiles,
+            Some(10) => return ResticFailure::RepositoryNotFound,
+            Some(11) => return ResticFailure::RepositoryLocked,
+            Some(12) => return ResticFailure::WrongPassword,
+            _ => {}
+        }
+
+        let stderr = stderr.to_lowercase();
+        PATTERNS
+            .iter()
+            .find(|(pattern, _)| stderr.contains(pattern))
+            .map(|(_, failure)| *failure)
+            .unwrap_or(ResticFailure::Other)
+    }
+
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            ResticFailure::RepositoryLocked | ResticFailure::BackendUnavailable
+        )
+    }
+
+    pub fn failure_reason(&self) -> Option<&'static str> {
+        match self {
+            ResticFailure::RepositoryLocked => Some("repository_locked"),
+            ResticFailure::BackendUnavailable => Some("backend_unavailable"),
+            ResticFailure::WrongPassword => Some("wrong_password"),
+            ResticFailure::RepositoryNotFound => Some("repository_not_found"),
+            ResticFailure::SourceMissing => Some("source_missing"),
+            ResticFailure::UnreadableFiles => Some("unreadable_files"),
+            ResticFailure::Other => None,
+        }
+    }
+}
+
+impl fmt::Display for ResticFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResticFailure::RepositoryLocked => write!(f, "repository is locked"),
+            ResticFailure::BackendUnavailable => write!(f, "repository backend is unreachable"),
+            ResticFailure::WrongPassword => write!(f, "wrong repository password"),
+            ResticFailure::RepositoryNotFound => write!(f, "repository does not exist"),
+            ResticFailure::SourceMissing => write!(f, "source paths do not exist"),
+            ResticFailure::UnreadableFiles => write!(f, "some source files could not be read"),
+            ResticFailure::Other => write!(f, "restic failed"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_by_exit_code() {
+        assert_eq!(
+            ResticFailure::classify(Some(3), ""),
+            ResticFailure::UnreadableFiles
+        );
+        assert_eq!(
+            ResticFailure::classify(Some(11), ""),
+            ResticFailure::RepositoryLocked
+        );
+        assert_eq!(
+            ResticFailure::classify(Some(12), "Fatal: unable to open config file"),
+            ResticFailure::WrongPassword
+        );
+        assert_eq!(ResticFailure::classify(Some(1), ""), ResticFailure::Other);
+    }
+
+    #[test]
+    fn test_classify_by_stderr() {
+        let cases = [
+            (
+                "unable to create lock in backend: repository is already locked exclusively by PID 1234 on host by user (UID 1000, GID 1000)",
+                ResticFailure::RepositoryLocked,
+            ),
+            (
+                "Fatal: wrong password or no key found",
+                ResticFailure::WrongPassword,
+            ),
+            (
+                "Fatal: unable to open repository at sftp:user@host:/srv/restic: unable to start the sftp session, error: EOF",
+                ResticFailure::BackendUnavailable,
+            ),
+            (
+                "Fatal: unable to open config file: Stat: dial tcp 10.0.0.1:443: connect: connection refused",
+                ResticFailure::BackendUnavailable,
+            ),
+            (
+                "Fatal: unable to open config file: Stat: stat /srv/restic/config: no such file or directory\nIs there a repository at the following location?",
+                ResticFailure::RepositoryNotFound,
+            ),
+            (
+                "Fatal: all source directories/files do not exist",
+                ResticFailure::SourceMissing,
+            ),
+            ("Fatal: invalid exclude pattern", ResticFailure::Other),
+        ];
+
+        for (stderr, expected) in cases {
+            assert_eq!(
+                ResticFailure::classify(Some(1), stderr),
+                expected,
+                "{}",
+                stderr
+            );
+        }
+    }
+
+    #[test]
+    fn test_transient_failures() {
+        assert!(ResticFailure::RepositoryLocked.is_transient());
+        assert!(ResticFailure::BackendUnavailable.is_transient());
+        assert!(!ResticFailure::WrongPassword.is_transient());
+        assert!(!ResticFailure::SourceMissing.is_transient());
+        assert!(!ResticFailure::UnreadableFiles.is_transient());
+        assert!(!ResticFailure::Other.is_transient());
+    }
+}
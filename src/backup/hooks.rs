@@ -0,0 +1,480 @@
+use crate::backup::journal::{HookRecord, RunJournal};
+use crate::config::remote::RemoteConfig;
+use crate::db::models::{BackupHook, BackupJob, Run};
+use crate::error::{BackupError, Result};
+use chrono::Utc;
+use sqlx::PgPool;
+use std::process::Stdio;
+use std::str::FromStr;
+use std::time::Duration;
+use tokio::process::Command;
+use tracing::{debug, info, warn};
+
+const MAX_HOOK_OUTPUT_BYTES: usize = 64 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookTiming {
+    Start,
+    End,
+    Failure,
+}
+
+impl HookTiming {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HookTiming::Start => "start",
+            HookTiming::End => "end",
+            HookTiming::Failure => "failure",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OnError {
+    // Other timings than start only skip the remaining hooks
+    Stop,
+    Continue,
+}
+
+impl FromStr for OnError {
+    type Err = BackupError;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        match value {
+            "stop" => Ok(OnError::Stop),
+            "continue" => Ok(OnError::Continue),
+            other => Err(BackupError::ConfigurationError(format!(
+                "Invalid hook on_error value: {}",
+                other
+            ))),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct HookOutcome {
+    status: &'static str,
+    exit_code: Option<i32>,
+    error_message: Option<String>,
+    stdout: Option<String>,
+    stderr: Option<String>,
+}
+
+pub fn hook_environment(job: &BackupJob, run: &Run, timing: HookTiming) -> Vec<(String, String)> {
+    let mut env = vec![
+        ("RBACKUP_JOB_ID".to_string(), job.id.to_string()),
+        ("RBACKUP_JOB_NAME".to_string(), job.name.clone()),
+        ("RBACKUP_DEVICE_ID".to_string(), run.device_id.clone()),
+        ("RBACKUP_RUN_ID".to_string(), run.id.to_string()),
+        ("RBACKUP_ATTEMPT".to_string(), run.attempt.to_string()),
+        (
+            "RBACKUP_HOOK_TIMING".to_string(),
+            timing.as_str().to_string(),
+        ),
+        ("RBACKUP_STATUS".to_string(), run.status.clone()),
+    ];
+
+    let optional = [
+        ("RBACKUP_SNAPSHOT_ID", run.snapshot_id.clone()),
+        ("RBACKUP_EXIT_CODE", run.exit_code.map(|v| v.to_string())),
+        ("RBACKUP_FILES_NEW", run.files_new.map(|v| v.to_string())),
+        (
+            "RBACKUP_FILES_CHANGED",
+            run.files_changed.map(|v| v.to_string()),
+        ),
+        (
+            "RBACKUP_FILES_UNMODIFIED",
+            run.files_unmodified.map(|v| v.to_string()),
+        ),
+        (
+            "RBACKUP_DATA_ADDED_BYTES",
+            run.data_added_bytes.map(|v| v.to_string()),
+        ),
+        (
+            "RBACKUP_DURATION_SECONDS",
+            run.duration_seconds.map(|v| v.to_string()),
+        ),
+        ("RBACKUP_ERROR", run.error_message.clone()),
+    ];
+
+    for (key, value) in optional {
+        if let Some(value) = value {
+            env.push((key.to_string(), value));
+        }
+    }
+
+    env
+}
+
+fn truncate_output(bytes: &[u8]) -> Option<String> {
+    if bytes.is_empty() {
+        return None;
+    }
+
+    let output = String::from_utf8_lossy(bytes);
+    if output.len() <= MAX_HOOK_OUTPUT_BYTES {
+        return Some(output.to_string());
+    }
+
+    let mut end = MAX_HOOK_OUTPUT_BYTES;
+    while !output.is_char_boundary(end) {
+        end -= 1;
+    }
+    Some(format!("{}\n[output truncated]", &output[..end]))
+}
+
+fn shell_command(command: &str) -> Command {
+    if cfg!(windows) {
+        let mut cmd = Command::new("cmd");
+        cmd.arg("/C").arg(command);
+        cmd
+    } else {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg(command);
+        cmd
+    }
+}
+
+async fn execute_hook(hook: &BackupHook, env: &[(String, String)]) -> HookOutcome {
+    let mut command = shell_command(&hook.command);
+    command
+        .envs(env.iter().map(|(key, value)| (key, value)))
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    #[cfg(unix)]
+    command.process_group(0);
+
+    let failed = |error_message: String| HookOutcome {
+        status: "failed",
+        exit_code: None,
+        error_message: Some(error_message),
+        stdout: None,
+        stderr: None,
+    };
+
+    let child = match command.spawn() {
+        Ok(child) => child,
+        Err(e) => return failed(format!("Failed to start hook: {}", e)),
+    };
+
+    let timeout = Duration::from_secs(hook.timeout_seconds.max(1) as u64);
+    let pid = child.id();
+
+    // On timeout the child is dropped, which kills it
+    match tokio::time::timeout(timeout, child.wait_with_output()).await {
+        Err(_) => {
+            kill_process_group(pid);
+            HookOutcome {
+                status: "timeout",
+                exit_code: None,
+                error_message: Some(format!(
+                    "Hook did not finish within {} seconds",
+                    timeout.as_secs()
+                )),
+                stdout: None,
+                stderr: None,
+            }
+        }
+        Ok(Err(e)) => failed(format!("Failed to wait for hook: {}", e)),
+        Ok(Ok(output)) => {
+            let exit_code = output.status.code();
+            let success = output.status.success();
+
+            HookOutcome {
+                status: if success { "success" } else { "failed" },
+                exit_code,
+                error_message: if success {
+                    None
+                } else {
+                    Some(match exit_code {
+                        Some(code) => format!("Hook exited with code {}", code),
+                        None => "Hook was terminated by a signal".to_string(),
+                    })
+                },
+                stdout: truncate_output(&output.stdout),
+                stderr: truncate_output(&output.stderr),
+            }
+        }
+    }
+}
+
+// Kills the hook with the processes it started, e.g. the other commands of a pipeline
+fn kill_process_group(pid: Option<u32>) {
+    #[cfg(unix)]
+    if let Some(pid) = pid {
+        // SAFETY: kill(2) has no memory safety requirements; the hook leads its own process group.
+        unsafe {
+            libc::kill(-(pid as libc::pid_t), libc::SIGKILL);
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = pid;
+}
+
+pub async fn run_hooks(
+    job: &BackupJob,
+    config: &RemoteConfig,
+    pool: &PgPool,
+    journal: &RunJournal,
+    run_id: i32,
+    timing: HookTiming,
+    trace_id: &str,
+) -> Result<()> {
+    let hooks = config.hooks_for_job(job.id, timing.as_str());
+    if hooks.is_empty() {
+        return Ok(());
+    }
+
+    let run = journal.get_run(pool, run_id).await?.ok_or_else(|| {
+        BackupError::ExecutionFailed(format!("Run {} not found when running hooks", run_id))
+    })?;
+    let env = hook_environment(job, &run, timing);
+
+    for hook in hooks {
+        debug!(
+            trace_id = trace_id,
+            run_id = run_id,
+            hook_id = %hook.id,
+            timing = timing.as_str(),
+            "Running hook: {}",
+            hook.command
+        );
+
+        let start_time = Utc::now();
+        let outcome = execute_hook(hook, &env).await;
+
+        let record = HookRecord {
+            hook_id: Some(hook.id),
+            timing: timing.as_str().to_string(),
+            command: hook.command.clone(),
+            start_time,
+            end_time: Utc::now(),
+            status: outcome.status.to_string(),
+            exit_code: outcome.exit_code,
+            error_message: outcome.error_message.clone(),
+            stdout: outcome.stdout,
+            stderr: outcome.stderr,
+        };
+        journal.create_hook_execution(pool, run_id, record).await?;
+
+        let error_message = match outcome.error_message {
+            Some(error_message) => error_message,
+            None => {
+                info!(
+                    trace_id = trace_id,
+                    run_id = run_id,
+                    hook_id = %hook.id,
+                    timing = timing.as_str(),
+                    "Hook completed"
+                );
+                continue;
+            }
+        };
+
+        let on_error = hook.on_error.parse().unwrap_or(OnError::Continue);
+
+        warn!(
+            trace_id = trace_id,
+            run_id = run_id,
+            hook_id = %hook.id,
+            timing = timing.as_str(),
+            on_error = %hook.on_error,
+            "Hook failed: {}",
+            error_message
+        );
+
+        if on_error == OnError::Stop {
+            return Err(BackupError::HookFailed(format!(
+                "{} hook '{}': {}",
+                timing.as_str(),
+                hook.command,
+                error_message
+            ))
+            .into());
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hook(command: &str, timeout_seconds: i32) -> BackupHook {
+        BackupHook {
+            id: uuid::Uuid::new_v4(),
+            job_id: uuid::Uuid::new_v4(),
+            description: None,
+            command: command.to_string(),
+            timing: "end".to_string(),
+            on_error: "continue".to_string(),
+            timeout_seconds,
+            sort_order: 0,
+            enabled: true,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            metadata: serde_json::json!({}),
+        }
+    }
+
+    #[test]
+    fn test_hook_environment() {
+        let job_id = uuid::Uuid::new_v4();
+        let job = BackupJob {
+            id: job_id,
+            device_id: "test-device".to_string(),
+            name: "home".to_string(),
+            description: None,
+            source_paths: vec!["/home".to_string()],
+            exclude_patterns: None,
+            tags: None,
+            restic_args: serde_json::json!([]),
+            enabled: true,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            metadata: serde_json::json!({}),
+            origin_name: None,
+            origin_id: None,
+            account_id: None,
+            retention: Default::default(),
+        };
+        let run: Run = serde_json::from_value(serde_json::json!({
+            "id": 12,
+            "job_id": job_id,
+            "device_id": "test-device",
+            "start_time": Utc::now(),
+            "end_time": Utc::now(),
+            "status": "success",
+            "exit_code": 0,
+            "error_message": null,
+            "files_new": 3,
+            "files_changed": 1,
+            "files_unmodified": 100,
+            "dirs_new": null,
+            "dirs_changed": null,
+            "dirs_unmodified": null,
+            "data_added_bytes": 2048,
+            "total_files_processed": null,
+            "total_bytes_processed": null,
+            "duration_seconds": 42,
+            "snapshot_id": "abc123",
+            "restic_output": null,
+            "restic_errors": null,
+            "triggered_by": "schedule",
+            "created_at": Utc::now(),
+            "metadata": {},
+            "progress": null,
+            "progress_updated_at": null,
+            "cancel_requested_at": null,
+            "cancel_requested_by": null,
+            "cancel_reason": null,
+            "failure_reason": null,
+            "attempt": 1,
+            "retry_of": null,
+            "failed_paths": null,
+            "repository_id": null
+        }))
+        .expect("Run should deserialize");
+
+        let env: std::collections::HashMap<String, String> =
+            hook_environment(&job, &run, HookTiming::End)
+                .into_iter()
+                .collect();
+
+        assert_eq!(env["RBACKUP_JOB_ID"], job_id.to_string());
+        assert_eq!(env["RBACKUP_JOB_NAME"], "home");
+        assert_eq!(env["RBACKUP_RUN_ID"], "12");
+        assert_eq!(env["RBACKUP_ATTEMPT"], "1");
+        assert_eq!(env["RBACKUP_HOOK_TIMING"], "end");
+        assert_eq!(env["RBACKUP_STATUS"], "success");
+        assert_eq!(env["RBACKUP_SNAPSHOT_ID"], "abc123");
+        assert_eq!(env["RBACKUP_FILES_NEW"], "3");
+        assert_eq!(env["RBACKUP_DATA_ADDED_BYTES"], "2048");
+        assert!(!env.contains_key("RBACKUP_ERROR"));
+    }
+
+    #[test]
+    fn test_on_error_parsing() {
+        assert_eq!("stop".parse::<OnError>().unwrap(), OnError::Stop);
+        assert_eq!("continue".parse::<OnError>().unwrap(), OnError::Continue);
+        assert!("abort".parse::<OnError>().is_err());
+    }
+
+    #[test]
+    fn test_truncate_output() {
+        assert_eq!(truncate_output(b""), None);
+        assert_eq!(truncate_output(b"done\n").as_deref(), Some("done\n"));
+
+        let long = "é".repeat(MAX_HOOK_OUTPUT_BYTES);
+        let truncated = truncate_output(long.as_bytes()).unwrap();
+        assert!(truncated.ends_with("[output truncated]"));
+        assert!(truncated.len() <= MAX_HOOK_OUTPUT_BYTES + "\n[output truncated]".len());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_execute_hook_captures_output_and_exit_code() {
+        let env = vec![("RBACKUP_STATUS".to_string(), "failed".to_string())];
+
+        let outcome = execute_hook(&hook("echo \"status=$RBACKUP_STATUS\"", 10), &env).await;
+        assert_eq!(outcome.status, "success");
+        assert_eq!(outcome.exit_code, Some(0));
+        assert_eq!(outcome.stdout.as_deref(), Some("status=failed\n"));
+
+        let outcome = execute_hook(&hook("echo oops >&2; exit 4", 10), &env).await;
+        assert_eq!(outcome.status, "failed");
+        assert_eq!(outcome.exit_code, Some(4));
+        assert_eq!(outcome.stderr.as_deref(), Some("oops\n"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_execute_hook_timeout() {
+        let outcome = execute_hook(&hook("sleep 5", 1), &[]).await;
+        assert_eq!(outcome.status, "timeout");
+        assert!(outcome.error_message.is_some());
+    }
+
+    // Processes of the group that have not exited, from /proc/<pid>/stat
+    #[cfg(target_os = "linux")]
+    fn live_processes_in_group(pgid: &str) -> usize {
+        std::fs::read_dir("/proc")
+            .expect("Failed to list processes")
+            .filter_map(|entry| std::fs::read_to_string(entry.ok()?.path().join("stat")).ok())
+            .filter(|stat| {
+                // After the command name: state, ppid, pgrp
+                let fields: Vec<&str> = match stat.rsplit_once(')') {
+                    Some((_, rest)) => rest.split_whitespace().collect(),
+                    None => return false,
+                };
+                fields.len() > 2 && fields[0] != "Z" && fields[2] == pgid
+            })
+            .count()
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_execute_hook_timeout_kills_pipeline() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let pid_file = dir.path().join("hook.pid");
+        let command = format!("echo $$ > {}; sleep 60 | cat", pid_file.display());
+
+        let started = std::time::Instant::now();
+        let outcome = execute_hook(&hook(&command, 1), &[]).await;
+        assert_eq!(outcome.status, "timeout");
+        assert!(started.elapsed() < Duration::from_secs(10));
+
+        let pgid = std::fs::read_to_string(&pid_file).expect("Failed to read pid file");
+        let pgid = pgid.trim();
+        for _ in 0..50 {
+            if live_processes_in_group(pgid) == 0 {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        panic!("Processes of the hook are still running");
+    }
+}
@@ -1,4 +1,5 @@
 use crate::error::{BackupError, Result};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use tracing::{debug, warn};
 
@@ -16,6 +17,54 @@ pub struct BackupStats {
     pub snapshot_id: String,
 }
 
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RestoreStats {
+    pub total_files: i32,
+    pub files_restored: i32,
+    pub files_skipped: i32,
+    pub total_bytes: i64,
+    pub bytes_restored: i64,
+    pub bytes_skipped: i64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BackupProgress {
+    pub percent_done: f64,
+    pub files_done: i64,
+    pub total_files: i64,
+    pub bytes_done: i64,
+    pub total_bytes: i64,
+    pub current_files: Vec<String>,
+    pub seconds_elapsed: Option<i64>,
+    pub seconds_remaining: Option<i64>,
+    pub error_count: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FailedPath {
+    pub path: String,
+    pub during: Option<String>,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ForgetStats {
+    pub snapshots_kept: i32,
+    pub snapshots_removed: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub id: String,
+    pub short_id: Option<String>,
+    pub time: DateTime<Utc>,
+    #[serde(default)]
+    pub paths: Vec<String>,
+    pub hostname: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
 #[derive(Debug, Deserialize)]
 struct ResticMessageType {
     message_type: String,
@@ -35,6 +84,77 @@ struct ResticSummary {
     snapshot_id: Option<String>,
 }
 
+#[derive(Debug, Deserialize)]
+struct ResticStatus {
+    message_type: String,
+    percent_done: Option<f64>,
+    files_done: Option<i64>,
+    total_files: Option<i64>,
+    bytes_done: Option<i64>,
+    total_bytes: Option<i64>,
+    current_files: Option<Vec<String>>,
+    seconds_elapsed: Option<i64>,
+    seconds_remaining: Option<i64>,
+    error_count: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ResticError {
+    message_type: String,
+    error: Option<serde_json::Value>,
+    during: Option<String>,
+    item: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ResticForgetGroup {
+    keep: Option<Vec<serde_json::Value>>,
+    remove: Option<Vec<serde_json::Value>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ResticRestoreSummary {
+    total_files: Option<i32>,
+    files_restored: Option<i32>,
+    files_skipped: Option<i32>,
+    total_bytes: Option<i64>,
+    bytes_restored: Option<i64>,
+    bytes_skipped: Option<i64>,
+}
+
+fn find_summary_line(stdout: &str) -> Option<&str> {
+    stdout.lines().map(str::trim).find(|line| {
+        !line.is_empty()
+            && serde_json::from_str::<ResticMessageType>(line)
+                .map(|mt| mt.message_type == "summary")
+                .unwrap_or(false)
+    })
+}
+
+pub fn parse_status_line(line: &str) -> Option<BackupProgress> {
+    let line = line.trim();
+    if !line.starts_with('{') {
+        return None;
+    }
+
+    let status: ResticStatus = serde_json::from_str(line).ok()?;
+    if status.message_type != "status" {
+        return None;
+    }
+
+    Some(BackupProgress {
+        percent_done: status.percent_done.unwrap_or(0.0),
+        files_done: status.files_done.unwrap_or(0),
+        total_files: status.total_files.unwrap_or(0),
+        bytes_done: status.bytes_done.unwrap_or(0),
+        total_bytes: status.total_bytes.unwrap_or(0),
+        current_files: status.current_files.unwrap_or_default(),
+        seconds_elapsed: status.seconds_elapsed,
+        seconds_remaining: status.seconds_remaining,
+        error_count: status.error_count.unwrap_or(0),
+    })
+}
+
 pub fn parse_restic_json_output(stdout: &str) -> Result<BackupStats> {
     let mut summary: Option<ResticSummary> = None;
 
@@ -87,6 +207,122 @@ pub fn parse_restic_json_output(stdout: &str) -> Result<BackupStats> {
     })
 }
 
+// restic 0.17 and newer report {"message": ...}, older versions the Go error as JSON
+pub fn parse_backup_errors(stdout: &str, stderr: &str) -> Vec<FailedPath> {
+    stdout
+        .lines()
+        .chain(stderr.lines())
+        .map(str::trim)
+        .filter(|line| line.starts_with('{'))
+        .filter_map(|line| serde_json::from_str::<ResticError>(line).ok())
+        .filter(|msg| msg.message_type == "error")
+        .map(|msg| {
+            let error = match msg.error {
+                Some(serde_json::Value::Object(fields)) => match fields.get("message") {
+                    Some(serde_json::Value::String(message)) => message.clone(),
+                    _ => serde_json::Value::Object(fields).to_string(),
+                },
+                Some(serde_json::Value::String(message)) => message,
+                Some(other) => other.to_string(),
+                None => String::new(),
+            };
+
+            FailedPath {
+                path: msg.item.unwrap_or_default(),
+                during: msg.during,
+                error,
+            }
+        })
+        .collect()
+}
+
+// restic only emits a restore summary since 0.17
+pub fn parse_restore_json_output(stdout: &str) -> Result<RestoreStats> {
+    let line = match find_summary_line(stdout) {
+        Some(line) => line,
+        None => {
+            debug!("No summary message found in restic restore output");
+            return Ok(RestoreStats::default());
+        }
+    };
+
+    let summary: ResticRestoreSummary = serde_json::from_str(line).map_err(|e| {
+        BackupError::OutputParseFailed(format!("Failed to parse restore summary: {}", e))
+    })?;
+
+    Ok(RestoreStats {
+        total_files: summary.total_files.unwrap_or(0),
+        files_restored: summary.files_restored.unwrap_or(0),
+        files_skipped: summary.files_skipped.unwrap_or(0),
+        total_bytes: summary.total_bytes.unwrap_or(0),
+        bytes_restored: summary.bytes_restored.unwrap_or(0),
+        bytes_skipped: summary.bytes_skipped.unwrap_or(0),
+    })
+}
+
+pub fn parse_snapshots_json(stdout: &str) -> Result<Vec<Snapshot>> {
+    let trimmed = stdout.trim();
+    if trimmed.is_empty() || trimmed == "null" {
+        return Ok(Vec::new());
+    }
+
+    serde_json::from_str(trimmed).map_err(|e| {
+        BackupError::OutputParseFailed(format!("Failed to parse snapshot list: {}", e)).into()
+    })
+}
+
+pub fn parse_forget_json_output(stdout: &str) -> Result<ForgetStats> {
+    let line = stdout
+        .lines()
+        .map(str::trim)
+        .find(|line| line.starts_with('['))
+        .unwrap_or("[]");
+
+    let groups: Option<Vec<ResticForgetGroup>> = serde_json::from_str(line).map_err(|e| {
+        BackupError::OutputParseFailed(format!("Failed to parse forget output: {}", e))
+    })?;
+
+    let mut stats = ForgetStats::default();
+
+    for group in groups.unwrap_or_default() {
+        stats.snapshots_kept += group.keep.map(|k| k.len() as i32).unwrap_or(0);
+        stats.snapshots_removed += group.remove.map(|r| r.len() as i32).unwrap_or(0);
+    }
+
+    Ok(stats)
+}
+
+pub fn parse_check_errors(stdout: &str, stderr: &str) -> Vec<String> {
+    let mut errors = Vec::new();
+
+    for output in [stdout, stderr] {
+        let mut in_error = false;
+
+        for line in output.lines() {
+            let trimmed = line.trim();
+            if trimmed.is_empty() {
+                in_error = false;
+                continue;
+            }
+
+            let lower = trimmed.to_lowercase();
+            if lower.starts_with("error")
+                || lower.starts_with("fatal:")
+                || lower.contains("does not match")
+            {
+                errors.push(trimmed.to_string());
+                in_error = true;
+            } else if in_error && line.starts_with(char::is_whitespace) {
+                errors.push(trimmed.to_string());
+            } else {
+                in_error = false;
+            }
+        }
+    }
+
+    errors
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -123,4 +359,151 @@ mod tests {
         let result = parse_restic_json_output(json_output);
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_parse_restore_json_output() {
+        let json_output = r#"{"message_type":"status","seconds_elapsed":1,"percent_done":0.5,"total_files":4,"files_restored":2}
+{"message_type":"summary","seconds_elapsed":2,"total_files":4,"files_restored":3,"files_skipped":1,"total_bytes":4096,"bytes_restored":3072,"bytes_skipped":1024}"#;
+
+        let stats = parse_restore_json_output(json_output).expect("Failed to parse restore stats");
+        assert_eq!(stats.total_files, 4);
+        assert_eq!(stats.files_restored, 3);
+        assert_eq!(stats.files_skipped, 1);
+        assert_eq!(stats.bytes_restored, 3072);
+        assert_eq!(stats.bytes_skipped, 1024);
+    }
+
+    #[test]
+    fn test_parse_restore_json_output_without_summary() {
+        let stats = parse_restore_json_output("").expect("Missing summary should not fail");
+        assert_eq!(stats.files_restored, 0);
+    }
+
+    #[test]
+    fn test_parse_snapshots_json() {
+        let json_output = r#"[{"time":"2025-01-01T10:00:00.123456789+01:00","tree":"abc","paths":["/home/user"],"hostname":"device1","username":"user","tags":["backup:cb3104d6-5d04-4cd6-9a8e-73c6c4b61fe9"],"id":"0123456789abcdef","short_id":"01234567"}]"#;
+
+        let snapshots = parse_snapshots_json(json_output).expect("Failed to parse snapshots");
+        assert_eq!(snapshots.len(), 1);
+        assert_eq!(snapshots[0].id, "0123456789abcdef");
+        assert_eq!(snapshots[0].short_id.as_deref(), Some("01234567"));
+        assert_eq!(snapshots[0].paths, vec!["/home/user".to_string()]);
+        assert_eq!(
+            snapshots[0].time.to_rfc3339(),
+            "2025-01-01T09:00:00.123456789+00:00"
+        );
+    }
+
+    #[test]
+    fn test_parse_snapshots_json_empty() {
+        assert!(parse_snapshots_json("[]").expect("Empty list").is_empty());
+        assert!(parse_snapshots_json("null").expect("Null list").is_empty());
+    }
+
+    #[test]
+    fn test_parse_forget_json_output() {
+        let json_output = r#"[{"tags":["backup:abc"],"host":"","paths":null,"keep":[{"id":"1"},{"id":"2"}],"remove":[{"id":"3"}],"reasons":[]}]"#;
+
+        let stats = parse_forget_json_output(json_output).expect("Failed to parse forget output");
+        assert_eq!(stats.snapshots_kept, 2);
+        assert_eq!(stats.snapshots_removed, 1);
+    }
+
+    #[test]
+    fn test_parse_forget_json_output_nothing_removed() {
+        let json_output = r#"[{"tags":null,"host":"","paths":null,"keep":[{"id":"1"}],"remove":null,"reasons":[]}]"#;
+
+        let stats = parse_forget_json_output(json_output).expect("Failed to parse forget output");
+        assert_eq!(stats.snapshots_kept, 1);
+        assert_eq!(stats.snapshots_removed, 0);
+
+        let empty = parse_forget_json_output("").expect("Empty output should parse");
+        assert_eq!(empty.snapshots_removed, 0);
+    }
+
+    #[test]
+    fn test_parse_check_errors() {
+        let stdout = "load indexes
+check all packs
+check snapshots, trees and blobs
+error for tree 4645312b:
+  tree 4645312b: file \"notes.txt\" blob 16 size could not be found
+  tree 4645312b, blob 6b1bf7e9: not found in index
+[0:00] 100.00%  1 / 1 snapshots
+read 1/10 of the data
+Pack ID does not match, want 3a1b2c3d, got 9f8e7d6c
+";
+        let stderr = "Fatal: repository contains errors\n";
+
+        let errors = parse_check_errors(stdout, stderr);
+        assert_eq!(errors.len(), 5);
+        assert_eq!(errors[0], "error for tree 4645312b:");
+        assert!(errors[2].contains("not found in index"));
+        assert!(errors[3].starts_with("Pack ID does not match"));
+        assert_eq!(errors[4], "Fatal: repository contains errors");
+    }
+
+    #[test]
+    fn test_parse_check_errors_clean_repository() {
+        let stdout = "load indexes
+check all packs
+check snapshots, trees and blobs
+no errors were found
+";
+
+        assert!(parse_check_errors(stdout, "").is_empty());
+    }
+
+    #[test]
+    fn test_parse_status_line() {
+        let line = r#"{"message_type":"status","seconds_elapsed":12,"seconds_remaining":30,"percent_done":0.285,"total_files":400,"files_done":114,"total_bytes":2097152,"bytes_done":597688,"current_files":["/home/user/a.txt","/home/user/b.txt"]}"#;
+
+        let progress = parse_status_line(line).expect("Expected a status message");
+        assert_eq!(progress.percent_done, 0.285);
+        assert_eq!(progress.files_done, 114);
+        assert_eq!(progress.total_files, 400);
+        assert_eq!(progress.bytes_done, 597688);
+        assert_eq!(progress.total_bytes, 2097152);
+        assert_eq!(progress.current_files.len(), 2);
+        assert_eq!(progress.seconds_elapsed, Some(12));
+        assert_eq!(progress.seconds_remaining, Some(30));
+        assert_eq!(progress.error_count, 0);
+    }
+
+    #[test]
+    fn test_parse_status_line_ignores_other_messages() {
+        let summary = r#"{"message_type":"summary","files_new":1,"snapshot_id":"abc"}"#;
+        assert!(parse_status_line(summary).is_none());
+        assert!(parse_status_line("scan finished").is_none());
+        assert!(parse_status_line("").is_none());
+
+        let initial = r#"{"message_type":"status","percent_done":0}"#;
+        let progress = parse_status_line(initial).expect("Expected a status message");
+        assert_eq!(progress, BackupProgress::default());
+    }
+
+    #[test]
+    fn test_parse_backup_errors() {
+        let stderr = r#"{"message_type":"error","error":{"message":"open /home/user/secret: permission denied"},"during":"archival","item":"/home/user/secret"}
+{"message_type":"error","error":{"Op":"lstat","Path":"/home/user/gone","Err":2},"during":"scan","item":"/home/user/gone"}
+Warning: at least one source file could not be read"#;
+        let stdout = r#"{"message_type":"status","percent_done":1}
+{"message_type":"summary","files_new":1,"snapshot_id":"abc"}"#;
+
+        let failed = parse_backup_errors(stdout, stderr);
+        assert_eq!(failed.len(), 2);
+        assert_eq!(
+            failed[0],
+            FailedPath {
+                path: "/home/user/secret".to_string(),
+                during: Some("archival".to_string()),
+                error: "open /home/user/secret: permission denied".to_string(),
+            }
+        );
+        assert_eq!(failed[1].path, "/home/user/gone");
+        assert_eq!(failed[1].during.as_deref(), Some("scan"));
+        assert!(failed[1].error.contains("lstat"));
+
+        assert!(parse_backup_errors(stdout, "").is_empty());
+    }
 }
@@ -0,0 +1,239 @@
+use crate::backup::journal::RunJournal;
+use crate::backup::registry::RunRegistry;
+use crate::backup::repository_label;
+use crate::backup::restic::ResticCommand;
+use crate::backup::retention::{
+    run_maintenance_command, update_maintenance_with_failure, update_maintenance_with_success,
+};
+use crate::config::remote::RemoteConfig;
+use crate::db;
+use crate::db::models::{Repository, RunOwner};
+use crate::error::Result;
+use chrono::{DateTime, Duration, Utc};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+const ABANDONED_RUN_ERROR: &str =
+    "Run abandoned: the client executing it stopped (crash or power loss) before the backup finished";
+
+// Local runs keep their local id in the registry after the journal was replayed
+pub async fn live_runs(registry: &RunRegistry, journal: &RunJournal) -> HashMap<i32, i32> {
+    registry
+        .run_ids()
+        .await
+        .into_iter()
+        .filter_map(|run_id| journal.resolve(run_id).map(|db_run_id| (db_run_id, run_id)))
+        .collect()
+}
+
+// Work of processes on this host is recovered once they exited, idle work after idle_timeout
+pub async fn recover_stale_runs(
+    config: &RemoteConfig,
+    pool: &PgPool,
+    device_id: String,
+    live_run_ids: Vec<i32>,
+    idle_timeout: Option<Duration>,
+    trace_id: &str,
+) -> Result<usize> {
+    let idle_before = idle_timeout.map(|timeout| Utc::now() - timeout);
+    let is_idle = |since: DateTime<Utc>| idle_before.is_some_and(|before| since < before);
+
+    let stale_run_ids = db::get_running_runs(pool, device_id.clone())
+        .await?
+        .into_iter()
+        .filter(|run| !live_run_ids.contains(&run.id))
+        .filter(|run| {
+            owner_alive(run.owner_host.as_deref(), run.owner_pid) == Some(false)
+                || is_idle(run.progress_updated_at.unwrap_or(run.start_time))
+        })
+        .map(|run| run.id)
+        .collect();
+
+    let runs = db::abandon_runs(pool, stale_run_ids, ABANDONED_RUN_ERROR.to_string()).await?;
+
+    for run in &runs {
+        warn!(
+            trace_id = trace_id,
+            run_id = run.id,
+            job_id = %run.job_id,
+            start_time = %run.start_time,
+            "Marked stale backup run as abandoned"
+        );
+    }
+
+    let stale_maintenance_run_ids = db::get_running_maintenance_runs(pool, device_id.clone())
+        .await?
+        .into_iter()
+        .filter(|maintenance_run| {
+            match owner_alive(
+                maintenance_run.owner_host.as_deref(),
+                maintenance_run.owner_pid,
+            ) {
+                Some(alive) => !alive,
+                None => is_idle(maintenance_run.start_time),
+            }
+        })
+        .map(|maintenance_run| maintenance_run.id)
+        .collect();
+
+    let maintenance_runs = db::abandon_maintenance_runs(
+        pool,
+        stale_maintenance_run_ids,
+        ABANDONED_RUN_ERROR.to_string(),
+    )
+    .await?;
+
+    for maintenance_run in &maintenance_runs {
+        warn!(
+            trace_id = trace_id,
+            maintenance_run_id = maintenance_run.id,
+            task = %maintenance_run.task_type,
+            "Marked stale maintenance task as abandoned"
+        );
+    }
+
+    let recovered = runs.len() + maintenance_runs.len();
+    let mut repository_ids: Vec<Option<Uuid>> = Vec::new();
+    for repository_id in runs
+        .iter()
+        .map(|r| r.repository_id)
+        .chain(maintenance_runs.iter().map(|r| r.repository_id))
+    {
+        if !repository_ids.contains(&repository_id) {
+            repository_ids.push(repository_id);
+        }
+    }
+
+    for repository_id in repository_ids {
+        let repository = match repository_id {
+            Some(id) => match config.repository(id) {
+                Some(repository) => Some(repository),
+                None => {
+                    warn!(
+                        trace_id = trace_id,
+                        repository_id = %id,
+                        "Repository of recovered runs is not configured, skipping unlock"
+                    );
+                    continue;
+                }
+            },
+            None => None,
+        };
+
+        if let Err(e) = execute_unlock(config, pool, device_id.clone(), repository, trace_id).await
+        {
+            warn!(
+                trace_id = trace_id,
+                repository = repository_label(repository),
+                "Failed to remove stale repository locks: {}",
+                e
+            );
+        }
+    }
+
+    Ok(recovered)
+}
+
+// Whether the process owning a run is still running; `None` if it cannot be checked from here
+fn owner_alive(host: Option<&str>, pid: Option<i32>) -> Option<bool> {
+    let current = RunOwner::current();
+    match (host, pid) {
+        (Some(host), Some(pid)) if host == current.host => {
+            if pid == current.pid {
+                Some(true)
+            } else {
+                process_exists(pid)
+            }
+        }
+        _ => None,
+    }
+}
+
+#[cfg(unix)]
+fn process_exists(pid: i32) -> Option<bool> {
+    if pid <= 0 {
+        return None;
+    }
+    // SAFETY: kill(2) with signal 0 only checks whether the process exists and sends nothing.
+    let result = unsafe { libc::kill(pid as libc::pid_t, 0) };
+    if result == 0 {
+        return Some(true);
+    }
+    match std::io::Error::last_os_error().raw_os_error() {
+        Some(libc::ESRCH) => Some(false),
+        Some(libc::EPERM) => Some(true),
+        _ => None,
+    }
+}
+
+#[cfg(not(unix))]
+fn process_exists(_pid: i32) -> Option<bool> {
+    None
+}
+
+pub async fn execute_unlock(
+    config: &RemoteConfig,
+    pool: &PgPool,
+    device_id: String,
+    repository: Option<&Repository>,
+    trace_id: &str,
+) -> Result<i32> {
+    info!(
+        trace_id = trace_id,
+        repository = repository_label(repository),
+        "Removing stale repository locks"
+    );
+
+    let maintenance_run_id = db::create_maintenance_run(
+        pool,
+        device_id,
+        repository.map(|r| r.id),
+        None,
+        None,
+        "unlock".to_string(),
+        None,
+        "recovery".to_string(),
+    )
+    .await?;
+
+    let restic_cmd = match ResticCommand::new(config, repository) {
+        Ok(restic_cmd) => restic_cmd,
+        Err(e) => {
+            update_maintenance_with_failure(
+                pool,
+                maintenance_run_id,
+                e.to_string(),
+                None,
+                None,
+                None,
+            )
+            .await?;
+            return Err(e);
+        }
+    };
+
+    let output = run_maintenance_command(
+        pool,
+        maintenance_run_id,
+        restic_cmd.build_unlock_command(),
+        "unlock",
+        trace_id,
+    )
+    .await?;
+
+    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+    update_maintenance_with_success(
+        pool,
+        maintenance_run_id,
+        output.status.code().unwrap_or(0),
+        None,
+        String::from_utf8_lossy(&output.stdout).to_string(),
+        Some(stderr).filter(|s| !s.is_empty()),
+    )
+    .await?;
+
+    info!(trace_id = trace_id, "Stale repository locks removed");
+    Ok(maintenance_run_id)
+}
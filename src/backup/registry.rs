@@ -0,0 +1,374 @@
+use crate::backup::output::BackupProgress;
+use crate::events::{Event, EventBus};
+use chrono::{DateTime, Utc};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{watch, Mutex, Notify};
+use uuid::Uuid;
+
+const PROGRESS_EVENT_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CancelRequest {
+    pub reason: String,
+    pub requested_by: String,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct ActiveRun {
+    pub run_id: i32,
+    pub job_id: Uuid,
+    pub started_at: DateTime<Utc>,
+    pub progress: watch::Receiver<Option<BackupProgress>>,
+    cancel: Arc<watch::Sender<Option<CancelRequest>>>,
+}
+
+pub struct RunHandle {
+    pub progress: watch::Sender<Option<BackupProgress>>,
+    pub cancel: watch::Receiver<Option<CancelRequest>>,
+    run_id: i32,
+    job_id: Uuid,
+    events: EventBus,
+    last_progress_event: Option<Instant>,
+}
+
+impl RunHandle {
+    pub fn report_progress(&mut self, progress: BackupProgress) {
+        let event_due = self
+            .last_progress_event
+            .is_none_or(|last| last.elapsed() >= PROGRESS_EVENT_INTERVAL);
+
+        if event_due {
+            self.last_progress_event = Some(Instant::now());
+            self.events.publish(Event::RunProgress {
+                run_id: self.run_id,
+                job_id: self.job_id,
+                progress: progress.clone(),
+            });
+        }
+
+        self.progress.send_replace(Some(progress));
+    }
+}
+
+// An entry of the job queued meanwhile stays queued until the claim is released
+#[derive(Debug)]
+pub struct JobClaim {
+    job_id: Uuid,
+    claimed_jobs: Arc<std::sync::Mutex<HashSet<Uuid>>>,
+    released: Arc<Notify>,
+}
+
+impl Drop for JobClaim {
+    fn drop(&mut self) {
+        if let Ok(mut jobs) = self.claimed_jobs.lock() {
+            jobs.remove(&self.job_id);
+        }
+        self.released.notify_waiters();
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RunRegistry {
+    runs: Arc<Mutex<HashMap<i32, ActiveRun>>>,
+    // Released from `Drop`, hence not an async mutex
+    claimed_jobs: Arc<std::sync::Mutex<HashSet<Uuid>>>,
+    claim_released: Arc<Notify>,
+    events: EventBus,
+}
+
+impl RunRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_events(events: EventBus) -> Self {
+        Self {
+            events,
+            ..Self::default()
+        }
+    }
+
+    pub fn events(&self) -> &EventBus {
+        &self.events
+    }
+
+    pub async fn register(&self, run_id: i32, job_id: Uuid) -> RunHandle {
+        let (progress_tx, progress_rx) = watch::channel(None);
+        let (cancel_tx, cancel_rx) = watch::channel(None);
+
+        let run = ActiveRun {
+            run_id,
+            job_id,
+            started_at: Utc::now(),
+            progress: progress_rx,
+            cancel: Arc::new(cancel_tx),
+        };
+
+        self.runs.lock().await.insert(run_id, run);
+
+        RunHandle {
+            progress: progress_tx,
+            cancel: cancel_rx,
+            run_id,
+            job_id,
+            events: self.events.clone(),
+            last_progress_event: None,
+        }
+    }
+
+    pub fn claim_job(&self, job_id: Uuid) -> Option<JobClaim> {
+        let mut jobs = self
+            .claimed_jobs
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        if !jobs.insert(job_id) {
+            return None;
+        }
+
+        Some(JobClaim {
+            job_id,
+            claimed_jobs: self.claimed_jobs.clone(),
+            released: self.claim_released.clone(),
+        })
+    }
+
+    pub async fn wait_for_job(&self, job_id: Uuid) {
+        loop {
+            let released = self.claim_released.notified();
+            tokio::pin!(released);
+            released.as_mut().enable();
+
+            let claimed = self
+                .claimed_jobs
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .contains(&job_id);
+            if !claimed {
+                return;
+            }
+
+            released.await;
+        }
+    }
+
+    #[allow(dead_code)]
+    pub async fn run_for_job(&self, job_id: Uuid) -> Option<ActiveRun> {
+        self.runs
+            .lock()
+            .await
+            .values()
+            .find(|run| run.job_id == job_id)
+            .cloned()
+    }
+
+    pub async fn unregister(&self, run_id: i32) {
+        self.runs.lock().await.remove(&run_id);
+    }
+
+    #[allow(dead_code)]
+    pub async fn get(&self, run_id: i32) -> Option<ActiveRun> {
+        self.runs.lock().await.get(&run_id).cloned()
+    }
+
+    #[allow(dead_code)]
+    pub async fn subscribe(&self, run_id: i32) -> Option<watch::Receiver<Option<BackupProgress>>> {
+        self.get(run_id).await.map(|run| run.progress)
+    }
+
+    // Only the first request is kept
+    pub async fn cancel(&self, run_id: i32, request: CancelRequest) -> bool {
+        match self.get(run_id).await {
+            Some(run) => {
+                run.cancel.send_if_modified(|current| {
+                    if current.is_none() {
+                        *current = Some(request);
+                        true
+                    } else {
+                        false
+                    }
+                });
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub async fn run_ids(&self) -> Vec<i32> {
+        let mut run_ids: Vec<i32> = self.runs.lock().await.keys().copied().collect();
+        run_ids.sort_unstable();
+        run_ids
+    }
+
+    #[allow(dead_code)]
+    pub async fn active_runs(&self) -> Vec<ActiveRun> {
+        let mut runs: Vec<ActiveRun> = self.runs.lock().await.values().cloned().collect();
+        runs.sort_by_key(|run| run.run_id);
+        runs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_register_and_subscribe() {
+        let registry = RunRegistry::new();
+        let job_id = Uuid::new_v4();
+
+        let handle = registry.register(42, job_id).await;
+        let mut rx = registry
+            .subscribe(42)
+            .await
+            .expect("Run should be registered");
+        assert!(rx.borrow().is_none());
+
+        let progress = BackupProgress {
+            percent_done: 0.5,
+            files_done: 10,
+            ..Default::default()
+        };
+        handle.progress.send_replace(Some(progress.clone()));
+
+        rx.changed().await.expect("Sender should still be alive");
+        assert_eq!(rx.borrow().as_ref(), Some(&progress));
+
+        let runs = registry.active_runs().await;
+        assert_eq!(runs.len(), 1);
+        assert_eq!(runs[0].job_id, job_id);
+    }
+
+    #[tokio::test]
+    async fn test_report_progress_publishes_events() {
+        let events = EventBus::new();
+        let mut rx = events.subscribe();
+        let registry = RunRegistry::with_events(events);
+        let job_id = Uuid::new_v4();
+
+        let mut handle = registry.register(7, job_id).await;
+        let progress = registry.subscribe(7).await.unwrap();
+
+        for files_done in [1, 2] {
+            handle.report_progress(BackupProgress {
+                files_done,
+                ..Default::default()
+            });
+        }
+
+        // Reported right away, while events are limited to one per interval
+        assert_eq!(progress.borrow().as_ref().unwrap().files_done, 2);
+        match rx.try_recv().unwrap() {
+            Event::RunProgress {
+                run_id,
+                job_id: event_job_id,
+                progress,
+            } => {
+                assert_eq!(run_id, 7);
+                assert_eq!(event_job_id, job_id);
+                assert_eq!(progress.files_done, 1);
+            }
+            event => panic!("Unexpected event {:?}", event),
+        }
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_unregister() {
+        let registry = RunRegistry::new();
+
+        let _handle = registry.register(1, Uuid::new_v4()).await;
+        registry.unregister(1).await;
+
+        assert!(registry.subscribe(1).await.is_none());
+        assert!(registry.active_runs().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_claim_job_refuses_duplicates() {
+        let registry = RunRegistry::new();
+        let job_id = Uuid::new_v4();
+
+        let claim = registry.claim_job(job_id).expect("Job should be free");
+        assert!(registry.claim_job(job_id).is_none());
+        assert!(registry.claim_job(Uuid::new_v4()).is_some());
+
+        drop(claim);
+        assert!(registry.claim_job(job_id).is_some());
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_job() {
+        let registry = RunRegistry::new();
+        let job_id = Uuid::new_v4();
+
+        registry.wait_for_job(job_id).await;
+
+        let claim = registry.claim_job(job_id).expect("Job should be free");
+        let waiter = tokio::spawn({
+            let registry = registry.clone();
+            async move { registry.wait_for_job(job_id).await }
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!waiter.is_finished());
+
+        drop(claim);
+        tokio::time::timeout(Duration::from_secs(1), waiter)
+            .await
+            .expect("Waiter not woken")
+            .expect("Waiter failed");
+    }
+
+    #[tokio::test]
+    async fn test_run_for_job() {
+        let registry = RunRegistry::new();
+        let job_id = Uuid::new_v4();
+
+        let _other = registry.register(1, Uuid::new_v4()).await;
+        let _handle = registry.register(2, job_id).await;
+
+        let run = registry
+            .run_for_job(job_id)
+            .await
+            .expect("Job should be running");
+        assert_eq!(run.run_id, 2);
+        assert!(registry.run_for_job(Uuid::new_v4()).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_cancel_keeps_first_request() {
+        let registry = RunRegistry::new();
+
+        let mut handle = registry.register(7, Uuid::new_v4()).await;
+        assert_eq!(registry.run_ids().await, vec![7]);
+
+        let first = CancelRequest {
+            reason: "disk full".to_string(),
+            requested_by: "cli".to_string(),
+        };
+        assert!(registry.cancel(7, first.clone()).await);
+        assert!(
+            registry
+                .cancel(
+                    7,
+                    CancelRequest {
+                        reason: "other".to_string(),
+                        requested_by: "api".to_string(),
+                    },
+                )
+                .await
+        );
+
+        handle
+            .cancel
+            .changed()
+            .await
+            .expect("Registry should still hold the sender");
+        assert_eq!(handle.cancel.borrow().as_ref(), Some(&first));
+
+        assert!(!registry.cancel(8, first).await);
+    }
+}
@@ -1,44 +1,38 @@
+use crate::backup::backend::{BackendSettings, ResticPassword};
+use crate::backup::check::ReadDataSubset;
+use crate::backup::restore::{OverwritePolicy, RestoreOptions};
 use crate::config::remote::RemoteConfig;
-use crate::db::models::BackupJob;
+use crate::db::models::{BackupJob, Repository};
 use crate::error::{AppError, BackupError, Result};
 use std::collections::HashMap;
 use std::path::PathBuf;
 use std::process::Stdio;
-use tokio::process::Command;
+use tokio::process::{Child, Command};
 use tracing::debug;
 
+const BACKUP_PROGRESS_FPS: &str = "0.5";
+
 pub struct ResticCommand {
     binary_path: PathBuf,
     repository_url: String,
-    repository_password: String,
+    repository_password: ResticPassword,
     cache_dir: Option<String>,
     environment: HashMap<String, String>,
+    options: Vec<String>,
 }
 
 impl ResticCommand {
-    pub fn new(config: &RemoteConfig) -> Result<Self> {
-        let repository_url = config
-            .repository_url()
-            .ok_or_else(|| BackupError::ConfigurationError("Repository URL not set".to_string()))?
-            .clone();
-
-        let repository_password = config
-            .repository_password()
-            .ok_or_else(|| {
-                BackupError::ConfigurationError("Repository password not set".to_string())
-            })?
-            .clone();
-
-        let cache_dir = config.repository_cache_dir().cloned();
-
+    pub fn new(config: &RemoteConfig, repository: Option<&Repository>) -> Result<Self> {
+        let settings = BackendSettings::resolve(config, repository)?;
         let binary_path = Self::find_restic_binary()?;
 
         Ok(Self {
             binary_path,
-            repository_url,
-            repository_password,
-            cache_dir,
-            environment: HashMap::new(),
+            repository_url: settings.url,
+            repository_password: settings.password,
+            cache_dir: settings.cache_dir,
+            environment: settings.environment,
+            options: settings.options,
         })
     }
 
@@ -60,11 +54,15 @@ impl ResticCommand {
         )))
     }
 
-    pub fn build_backup_command(&self, job: &BackupJob) -> Command {
+    fn base_command(&self) -> Command {
         let mut cmd = Command::new(&self.binary_path);
 
         cmd.env("RESTIC_REPOSITORY", &self.repository_url);
-        cmd.env("RESTIC_PASSWORD", &self.repository_password);
+        match &self.repository_password {
+            ResticPassword::Value(password) => cmd.env("RESTIC_PASSWORD", password),
+            ResticPassword::File(path) => cmd.env("RESTIC_PASSWORD_FILE", path),
+            ResticPassword::Command(command) => cmd.env("RESTIC_PASSWORD_COMMAND", command),
+        };
 
         if let Some(cache_dir) = &self.cache_dir {
             if !cache_dir.is_empty() {
@@ -76,6 +74,24 @@ impl ResticCommand {
             cmd.env(key, value);
         }
 
+        for option in &self.options {
+            cmd.arg("-o").arg(option);
+        }
+
+        cmd.stdout(Stdio::piped());
+        cmd.stderr(Stdio::piped());
+
+        cmd
+    }
+
+    pub fn build_backup_command(&self, job: &BackupJob) -> Command {
+        let mut cmd = self.base_command();
+
+        // restic emits JSON status messages up to 60 times per second by default
+        if !self.environment.contains_key("RESTIC_PROGRESS_FPS") {
+            cmd.env("RESTIC_PROGRESS_FPS", BACKUP_PROGRESS_FPS);
+        }
+
         cmd.arg("backup");
         cmd.arg("--json");
 
@@ -108,59 +124,117 @@ impl ResticCommand {
             }
         }
 
-        cmd.stdout(Stdio::piped());
-        cmd.stderr(Stdio::piped());
-
         cmd
     }
 
-    #[allow(dead_code)]
-    pub fn add_environment(&mut self, key: String, value: String) {
-        self.environment.insert(key, value);
+    pub fn build_snapshots_command(&self, job: &BackupJob) -> Command {
+        let mut cmd = self.base_command();
+
+        cmd.arg("snapshots");
+        cmd.arg("--json");
+        cmd.arg("--tag").arg(format!("backup:{}", job.id));
+
+        cmd
     }
 
-    #[allow(dead_code)]
-    pub async fn check_repository(&self) -> Result<()> {
-        let mut cmd = Command::new(&self.binary_path);
+    pub fn build_restore_command(&self, snapshot_id: &str, options: &RestoreOptions) -> Command {
+        let mut cmd = self.base_command();
 
-        cmd.env("RESTIC_REPOSITORY", &self.repository_url);
-        cmd.env("RESTIC_PASSWORD", &self.repository_password);
+        cmd.arg("restore");
+        cmd.arg(snapshot_id);
+        cmd.arg("--json");
+        cmd.arg("--target").arg(&options.target);
 
-        if let Some(cache_dir) = &self.cache_dir {
-            if !cache_dir.is_empty() {
-                cmd.env("RESTIC_CACHE_DIR", cache_dir);
-            }
+        for pattern in &options.include {
+            cmd.arg("--include").arg(pattern);
         }
 
-        cmd.arg("snapshots");
+        for pattern in &options.exclude {
+            cmd.arg("--exclude").arg(pattern);
+        }
+
+        // Only passed when needed, so restores keep working with restic older than 0.17
+        if options.overwrite != OverwritePolicy::Always {
+            cmd.arg("--overwrite").arg(options.overwrite.as_str());
+        }
+
+        cmd
+    }
+
+    // Without grouping the policy covers all snapshots of the job, even if their paths changed
+    pub fn build_forget_command(&self, job: &BackupJob) -> Command {
+        let mut cmd = self.base_command();
+
+        cmd.arg("forget");
         cmd.arg("--json");
-        cmd.arg("--last");
+        cmd.arg("--tag").arg(format!("backup:{}", job.id));
+        cmd.arg("--group-by").arg("");
 
-        cmd.stdout(Stdio::piped());
-        cmd.stderr(Stdio::piped());
+        for arg in job.retention.keep_arguments() {
+            cmd.arg(arg);
+        }
+
+        cmd
+    }
+
+    pub fn build_prune_command(&self) -> Command {
+        let mut cmd = self.base_command();
 
-        let output = cmd.output().await.map_err(|e| {
-            AppError::Backup(BackupError::ExecutionFailed(format!(
-                "Failed to execute restic: {}",
-                e
-            )))
-        })?;
+        cmd.arg("prune");
 
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            return Err(AppError::Backup(BackupError::ExecutionFailed(format!(
-                "Repository check failed: {}",
-                stderr
-            ))));
+        cmd
+    }
+
+    pub fn build_check_command(&self, read_data_subset: Option<&ReadDataSubset>) -> Command {
+        let mut cmd = self.base_command();
+
+        cmd.arg("check");
+
+        if let Some(subset) = read_data_subset {
+            cmd.arg(format!("--read-data-subset={}", subset));
         }
 
-        Ok(())
+        cmd
+    }
+
+    // Locks of running restic processes are kept
+    pub fn build_unlock_command(&self) -> Command {
+        let mut cmd = self.base_command();
+
+        cmd.arg("unlock");
+
+        cmd
+    }
+}
+
+// SIGINT makes restic abort without a snapshot and remove its lock; elsewhere it is killed
+pub fn interrupt(child: &mut Child) -> std::io::Result<()> {
+    #[cfg(unix)]
+    {
+        match child.id() {
+            Some(pid) => {
+                // SAFETY: the pid is our child, which was not reaped yet since `id()` returns it
+                let result = unsafe { libc::kill(pid as libc::pid_t, libc::SIGINT) };
+                if result == 0 {
+                    Ok(())
+                } else {
+                    Err(std::io::Error::last_os_error())
+                }
+            }
+            None => Ok(()),
+        }
+    }
+
+    #[cfg(not(unix))]
+    {
+        child.start_kill()
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::db::models::BackendCredentials;
     use std::collections::HashMap;
 
     fn create_test_config() -> RemoteConfig {
@@ -174,6 +248,9 @@ mod tests {
         RemoteConfig {
             jobs: vec![],
             schedules: vec![],
+            hooks: vec![],
+            repositories: vec![],
+            job_repositories: vec![],
             settings,
         }
     }
@@ -181,7 +258,7 @@ mod tests {
     #[test]
     fn test_restic_command_creation() {
         let config = create_test_config();
-        let result = ResticCommand::new(&config);
+        let result = ResticCommand::new(&config, None);
 
         if which::which(if cfg!(target_os = "windows") {
             "restic.exe"
@@ -193,7 +270,10 @@ mod tests {
             assert!(result.is_ok());
             let cmd = result.expect("Failed to create ResticCommand");
             assert_eq!(cmd.repository_url, "sftp:user@host:/path");
-            assert_eq!(cmd.repository_password, "secret");
+            assert_eq!(
+                cmd.repository_password,
+                ResticPassword::Value("secret".to_string())
+            );
         } else {
             assert!(result.is_err());
         }
@@ -212,6 +292,9 @@ mod tests {
         let config = RemoteConfig {
             jobs: vec![],
             schedules: vec![],
+            hooks: vec![],
+            repositories: vec![],
+            job_repositories: vec![],
             settings,
         };
 
@@ -222,10 +305,204 @@ mod tests {
         })
         .is_ok()
         {
-            let result = ResticCommand::new(&config);
+            let result = ResticCommand::new(&config, None);
             assert!(result.is_ok());
             let cmd = result.expect("Failed to create ResticCommand");
             assert_eq!(cmd.cache_dir, Some("/tmp/cache".to_string()));
         }
     }
+
+    #[test]
+    fn test_restic_command_for_repository() {
+        let mut config = create_test_config();
+        config
+            .settings
+            .insert("repository_cache_dir".to_string(), "/tmp/cache".to_string());
+
+        let repository = Repository {
+            id: uuid::Uuid::new_v4(),
+            name: "offsite".to_string(),
+            url: "s3:https://s3.example.com/backups".to_string(),
+            password: "offsite-secret".to_string(),
+            cache_dir: None,
+            environment: serde_json::json!({"AWS_ACCESS_KEY_ID": "key"}),
+            enabled: true,
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+            metadata: serde_json::json!({}),
+            credentials: Some(sqlx::types::Json(BackendCredentials::S3 {
+                access_key_id: "typed-key".to_string(),
+                secret_access_key: "typed-secret".to_string(),
+                session_token: None,
+                region: None,
+            })),
+        };
+
+        if let Ok(cmd) = ResticCommand::new(&config, Some(&repository)) {
+            assert_eq!(cmd.repository_url, "s3:https://s3.example.com/backups");
+            assert_eq!(
+                cmd.repository_password,
+                ResticPassword::Value("offsite-secret".to_string())
+            );
+            assert_eq!(cmd.cache_dir, Some("/tmp/cache".to_string()));
+            // Typed credentials take precedence over the free-form environment
+            assert_eq!(
+                cmd.environment.get("AWS_ACCESS_KEY_ID"),
+                Some(&"typed-key".to_string())
+            );
+            assert_eq!(
+                cmd.environment.get("AWS_SECRET_ACCESS_KEY"),
+                Some(&"typed-secret".to_string())
+            );
+        }
+    }
+
+    fn create_test_command() -> ResticCommand {
+        ResticCommand {
+            binary_path: PathBuf::from("restic"),
+            repository_url: "sftp:user@host:/path".to_string(),
+            repository_password: ResticPassword::Value("secret".to_string()),
+            cache_dir: None,
+            environment: HashMap::new(),
+            options: vec![],
+        }
+    }
+
+    fn command_args(cmd: &Command) -> Vec<String> {
+        cmd.as_std()
+            .get_args()
+            .map(|a| a.to_string_lossy().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_build_restore_command() {
+        let restic_cmd = create_test_command();
+        let options = RestoreOptions {
+            snapshot: crate::backup::restore::SnapshotSelector::Latest,
+            target: PathBuf::from("/tmp/restore"),
+            include: vec!["/home/user/Documents".to_string()],
+            exclude: vec!["*.tmp".to_string()],
+            overwrite: OverwritePolicy::IfNewer,
+        };
+
+        let cmd = restic_cmd.build_restore_command("abc123", &options);
+
+        assert_eq!(
+            command_args(&cmd),
+            vec![
+                "restore",
+                "abc123",
+                "--json",
+                "--target",
+                "/tmp/restore",
+                "--include",
+                "/home/user/Documents",
+                "--exclude",
+                "*.tmp",
+                "--overwrite",
+                "if-newer",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_build_forget_command() {
+        let restic_cmd = create_test_command();
+        let job_id = uuid::Uuid::new_v4();
+        let job = BackupJob {
+            id: job_id,
+            device_id: "test-device".to_string(),
+            name: "device1/home".to_string(),
+            description: None,
+            source_paths: vec!["/home".to_string()],
+            exclude_patterns: None,
+            tags: None,
+            restic_args: serde_json::json!([]),
+            enabled: true,
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+            metadata: serde_json::json!({}),
+            origin_name: None,
+            origin_id: None,
+            account_id: None,
+            retention: crate::db::models::RetentionPolicy {
+                keep_daily: Some(7),
+                keep_within: Some("1y".to_string()),
+                ..Default::default()
+            },
+        };
+
+        let cmd = restic_cmd.build_forget_command(&job);
+
+        assert_eq!(
+            command_args(&cmd),
+            vec![
+                "forget".to_string(),
+                "--json".to_string(),
+                "--tag".to_string(),
+                format!("backup:{}", job_id),
+                "--group-by".to_string(),
+                "".to_string(),
+                "--keep-daily".to_string(),
+                "7".to_string(),
+                "--keep-within".to_string(),
+                "1y".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_build_check_command() {
+        let restic_cmd = create_test_command();
+
+        let cmd = restic_cmd.build_check_command(None);
+        assert_eq!(command_args(&cmd), vec!["check".to_string()]);
+
+        let subset = ReadDataSubset { index: 2, total: 5 };
+        let cmd = restic_cmd.build_check_command(Some(&subset));
+        assert_eq!(
+            command_args(&cmd),
+            vec!["check".to_string(), "--read-data-subset=2/5".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_extended_options() {
+        let mut restic_cmd = create_test_command();
+        restic_cmd.options = vec!["sftp.args=-p 2222".to_string()];
+
+        let cmd = restic_cmd.build_unlock_command();
+        assert_eq!(
+            command_args(&cmd),
+            vec![
+                "-o".to_string(),
+                "sftp.args=-p 2222".to_string(),
+                "unlock".to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_build_unlock_command() {
+        let restic_cmd = create_test_command();
+
+        let cmd = restic_cmd.build_unlock_command();
+        assert_eq!(command_args(&cmd), vec!["unlock".to_string()]);
+    }
+
+    #[test]
+    fn test_build_restore_command_default_overwrite() {
+        let restic_cmd = create_test_command();
+        let options = RestoreOptions {
+            snapshot: crate::backup::restore::SnapshotSelector::Latest,
+            target: PathBuf::from("/tmp/restore"),
+            include: vec![],
+            exclude: vec![],
+            overwrite: OverwritePolicy::Always,
+        };
+
+        let args = command_args(&restic_cmd.build_restore_command("abc123", &options));
+        assert!(!args.contains(&"--overwrite".to_string()));
+    }
 }
@@ -0,0 +1,479 @@
+use crate::backup::output::{
+    parse_restore_json_output, parse_snapshots_json, RestoreStats, Snapshot,
+};
+use crate::backup::repository_label;
+use crate::backup::restic::ResticCommand;
+use crate::config::remote::RemoteConfig;
+use crate::db;
+use crate::db::models::{BackupJob, Repository};
+use crate::error::{BackupError, Result};
+use chrono::Utc;
+use sqlx::PgPool;
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+use tracing::{debug, error, info, warn};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SnapshotSelector {
+    Latest,
+    Id(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OverwritePolicy {
+    #[default]
+    Always,
+    IfChanged,
+    IfNewer,
+    Never,
+}
+
+impl OverwritePolicy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OverwritePolicy::Always => "always",
+            OverwritePolicy::IfChanged => "if-changed",
+            OverwritePolicy::IfNewer => "if-newer",
+            OverwritePolicy::Never => "never",
+        }
+    }
+}
+
+impl fmt::Display for OverwritePolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for OverwritePolicy {
+    type Err = BackupError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "always" => Ok(OverwritePolicy::Always),
+            "if-changed" => Ok(OverwritePolicy::IfChanged),
+            "if-newer" => Ok(OverwritePolicy::IfNewer),
+            "never" => Ok(OverwritePolicy::Never),
+            other => Err(BackupError::ConfigurationError(format!(
+                "Unknown overwrite policy '{}' (expected always, if-changed, if-newer or never)",
+                other
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RestoreOptions {
+    pub snapshot: SnapshotSelector,
+    pub target: PathBuf,
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+    pub overwrite: OverwritePolicy,
+}
+
+// Explicit IDs may be abbreviated, but must be unambiguous
+pub fn select_snapshot(
+    job: &BackupJob,
+    mut snapshots: Vec<Snapshot>,
+    selector: &SnapshotSelector,
+) -> Result<Snapshot> {
+    match selector {
+        SnapshotSelector::Latest => {
+            snapshots.sort_by_key(|s| s.time);
+            snapshots.pop().ok_or_else(|| {
+                BackupError::SnapshotNotFound(format!(
+                    "No snapshots found for job '{}' ({})",
+                    job.name, job.id
+                ))
+                .into()
+            })
+        }
+        SnapshotSelector::Id(id) => {
+            let mut matching: Vec<Snapshot> = snapshots
+                .into_iter()
+                .filter(|s| s.id.starts_with(id.as_str()))
+                .collect();
+
+            match matching.len() {
+                0 => Err(BackupError::SnapshotNotFound(format!(
+                    "Snapshot '{}' does not belong to job '{}' ({})",
+                    id, job.name, job.id
+                ))
+                .into()),
+                1 => Ok(matching.remove(0)),
+                n => Err(BackupError::SnapshotNotFound(format!(
+                    "Snapshot ID '{}' is ambiguous ({} matches)",
+                    id, n
+                ))
+                .into()),
+            }
+        }
+    }
+}
+
+async fn list_job_snapshots(
+    restic_cmd: &ResticCommand,
+    job: &BackupJob,
+    trace_id: &str,
+) -> Result<Vec<Snapshot>> {
+    let mut command = restic_cmd.build_snapshots_command(job);
+
+    debug!(
+        trace_id = trace_id,
+        "Listing snapshots for job '{}'", job.name
+    );
+
+    let output = command
+        .output()
+        .await
+        .map_err(|e| BackupError::ExecutionFailed(format!("Failed to execute restic: {}", e)))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(BackupError::ExecutionFailed(format!(
+            "Failed to list snapshots: {}",
+            stderr.trim()
+        ))
+        .into());
+    }
+
+    parse_snapshots_json(&String::from_utf8_lossy(&output.stdout))
+}
+
+pub async fn resolve_snapshot(
+    restic_cmd: &ResticCommand,
+    job: &BackupJob,
+    selector: &SnapshotSelector,
+    trace_id: &str,
+) -> Result<Snapshot> {
+    let snapshots = list_job_snapshots(restic_cmd, job, trace_id).await?;
+    debug!(
+        trace_id = trace_id,
+        count = snapshots.len(),
+        "Found snapshots for job"
+    );
+    select_snapshot(job, snapshots, selector)
+}
+
+async fn update_restore_with_failure(
+    pool: &PgPool,
+    restore_id: i32,
+    error_msg: String,
+    snapshot_id: Option<String>,
+    exit_code: Option<i32>,
+    stdout: Option<String>,
+    stderr: Option<String>,
+) -> Result<()> {
+    db::update_restore(
+        pool,
+        restore_id,
+        Utc::now(),
+        "failed".to_string(),
+        exit_code,
+        Some(error_msg),
+        snapshot_id,
+        None,
+        None,
+        None,
+        stdout,
+        stderr,
+    )
+    .await?;
+    Ok(())
+}
+
+async fn update_restore_with_success(
+    pool: &PgPool,
+    restore_id: i32,
+    snapshot_id: String,
+    exit_code: i32,
+    stats: &RestoreStats,
+    stdout: String,
+    stderr: Option<String>,
+) -> Result<()> {
+    db::update_restore(
+        pool,
+        restore_id,
+        Utc::now(),
+        "success".to_string(),
+        Some(exit_code),
+        None,
+        Some(snapshot_id),
+        Some(stats.files_restored),
+        Some(stats.files_skipped),
+        Some(stats.bytes_restored),
+        Some(stdout),
+        stderr,
+    )
+    .await?;
+    Ok(())
+}
+
+pub async fn execute_restore(
+    job: &BackupJob,
+    config: &RemoteConfig,
+    pool: &PgPool,
+    repository: Option<&Repository>,
+    options: &RestoreOptions,
+    trace_id: String,
+) -> Result<i32> {
+    info!(
+        trace_id = trace_id,
+        job_id = %job.id,
+        job_name = %job.name,
+        repository = repository_label(repository),
+        target = %options.target.display(),
+        "Starting restore execution"
+    );
+
+    let restore_id = db::create_restore(
+        pool,
+        job.id,
+        job.device_id.clone(),
+        repository.map(|r| r.id),
+        options.target.display().to_string(),
+        Some(options.include.clone()).filter(|p| !p.is_empty()),
+        Some(options.exclude.clone()).filter(|p| !p.is_empty()),
+        options.overwrite.as_str().to_string(),
+        "manual".to_string(),
+    )
+    .await?;
+    debug!(
+        trace_id = trace_id,
+        restore_id = restore_id,
+        "Created restore record"
+    );
+
+    let restic_cmd = match ResticCommand::new(config, repository) {
+        Ok(restic_cmd) => restic_cmd,
+        Err(e) => {
+            update_restore_with_failure(pool, restore_id, e.to_string(), None, None, None, None)
+                .await?;
+            return Err(e);
+        }
+    };
+
+    let snapshot = match resolve_snapshot(&restic_cmd, job, &options.snapshot, &trace_id).await {
+        Ok(snapshot) => snapshot,
+        Err(e) => {
+            update_restore_with_failure(pool, restore_id, e.to_string(), None, None, None, None)
+                .await?;
+            return Err(e);
+        }
+    };
+
+    info!(
+        trace_id = trace_id,
+        snapshot_id = %snapshot.id,
+        snapshot_time = %snapshot.time,
+        "Restoring snapshot"
+    );
+
+    let output = match restic_cmd
+        .build_restore_command(&snapshot.id, options)
+        .output()
+        .await
+    {
+        Ok(output) => output,
+        Err(e) => {
+            let error_msg = format!("Failed to execute restic: {}", e);
+            error!(trace_id = trace_id, "{}", error_msg);
+            update_restore_with_failure(
+                pool,
+                restore_id,
+                error_msg.clone(),
+                Some(snapshot.id),
+                None,
+                None,
+                None,
+            )
+            .await?;
+            return Err(BackupError::ExecutionFailed(error_msg).into());
+        }
+    };
+
+    let exit_code = output.status.code().unwrap_or(-1);
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+
+    if !output.status.success() {
+        let error_msg = if !stderr.is_empty() {
+            stderr.clone()
+        } else {
+            "Restore failed with no error message".to_string()
+        };
+        warn!(
+            trace_id = trace_id,
+            exit_code = exit_code,
+            "Restore failed: {}",
+            error_msg
+        );
+
+        update_restore_with_failure(
+            pool,
+            restore_id,
+            error_msg.clone(),
+            Some(snapshot.id),
+            Some(exit_code),
+            Some(stdout),
+            Some(stderr),
+        )
+        .await?;
+
+        return Err(BackupError::ExecutionFailed(error_msg).into());
+    }
+
+    let stats = match parse_restore_json_output(&stdout) {
+        Ok(stats) => stats,
+        Err(e) => {
+            let error_msg = format!("Failed to parse restic output: {}", e);
+            error!(trace_id = trace_id, "{}", error_msg);
+
+            update_restore_with_failure(
+                pool,
+                restore_id,
+                error_msg,
+                Some(snapshot.id),
+                Some(exit_code),
+                Some(stdout),
+                Some(stderr),
+            )
+            .await?;
+
+            return Err(e);
+        }
+    };
+
+    info!(
+        trace_id = trace_id,
+        snapshot_id = %snapshot.id,
+        files_restored = stats.files_restored,
+        files_skipped = stats.files_skipped,
+        restored_mb = stats.bytes_restored / 1024 / 1024,
+        "Restore completed successfully"
+    );
+
+    let stderr_opt = if !stderr.is_empty() {
+        Some(stderr)
+    } else {
+        None
+    };
+
+    update_restore_with_success(
+        pool,
+        restore_id,
+        snapshot.id,
+        exit_code,
+        &stats,
+        stdout,
+        stderr_opt,
+    )
+    .await?;
+
+    Ok(restore_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::models::RetentionPolicy;
+    use chrono::TimeZone;
+    use uuid::Uuid;
+
+    fn create_test_job() -> BackupJob {
+        BackupJob {
+            id: Uuid::new_v4(),
+            device_id: "test-device".to_string(),
+            name: "device1/home".to_string(),
+            description: None,
+            source_paths: vec!["/home".to_string()],
+            exclude_patterns: None,
+            tags: None,
+            restic_args: serde_json::json!([]),
+            enabled: true,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            metadata: serde_json::json!({}),
+            origin_name: None,
+            origin_id: None,
+            account_id: None,
+            retention: RetentionPolicy::default(),
+        }
+    }
+
+    fn create_snapshot(id: &str, hour: u32) -> Snapshot {
+        Snapshot {
+            id: id.to_string(),
+            short_id: Some(id.chars().take(8).collect()),
+            time: Utc.with_ymd_and_hms(2025, 1, 1, hour, 0, 0).unwrap(),
+            paths: vec!["/home".to_string()],
+            hostname: Some("device1".to_string()),
+            tags: vec![],
+        }
+    }
+
+    #[test]
+    fn test_select_latest_snapshot() {
+        let job = create_test_job();
+        let snapshots = vec![
+            create_snapshot("aaaa1111", 10),
+            create_snapshot("bbbb2222", 12),
+            create_snapshot("cccc3333", 11),
+        ];
+
+        let snapshot = select_snapshot(&job, snapshots, &SnapshotSelector::Latest)
+            .expect("Failed to select latest snapshot");
+        assert_eq!(snapshot.id, "bbbb2222");
+    }
+
+    #[test]
+    fn test_select_latest_snapshot_none() {
+        let job = create_test_job();
+        assert!(select_snapshot(&job, vec![], &SnapshotSelector::Latest).is_err());
+    }
+
+    #[test]
+    fn test_select_snapshot_by_id_prefix() {
+        let job = create_test_job();
+        let snapshots = vec![
+            create_snapshot("aaaa1111", 10),
+            create_snapshot("bbbb2222", 12),
+        ];
+
+        let snapshot = select_snapshot(&job, snapshots, &SnapshotSelector::Id("aaaa".to_string()))
+            .expect("Failed to select snapshot by id");
+        assert_eq!(snapshot.id, "aaaa1111");
+    }
+
+    #[test]
+    fn test_select_snapshot_by_id_not_in_job() {
+        let job = create_test_job();
+        let snapshots = vec![create_snapshot("aaaa1111", 10)];
+
+        let result = select_snapshot(&job, snapshots, &SnapshotSelector::Id("ffff".to_string()));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_select_snapshot_by_ambiguous_id() {
+        let job = create_test_job();
+        let snapshots = vec![
+            create_snapshot("aaaa1111", 10),
+            create_snapshot("aaaa2222", 12),
+        ];
+
+        let result = select_snapshot(&job, snapshots, &SnapshotSelector::Id("aaaa".to_string()));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_overwrite_policy_parsing() {
+        assert_eq!(
+            "if-newer".parse::<OverwritePolicy>().ok(),
+            Some(OverwritePolicy::IfNewer)
+        );
+        assert_eq!(OverwritePolicy::IfChanged.as_str(), "if-changed");
+        assert!("sometimes".parse::<OverwritePolicy>().is_err());
+    }
+}
@@ -0,0 +1,294 @@
+use crate::backup::output::{parse_forget_json_output, ForgetStats};
+use crate::backup::repository_label;
+use crate::backup::restic::ResticCommand;
+use crate::config::remote::RemoteConfig;
+use crate::db;
+use crate::db::models::{BackupJob, Repository};
+use crate::error::{BackupError, Result};
+use chrono::Utc;
+use sqlx::PgPool;
+use std::process::Output;
+use tokio::process::Command;
+use tracing::{debug, error, info, warn};
+
+pub(crate) async fn update_maintenance_with_failure(
+    pool: &PgPool,
+    maintenance_run_id: i32,
+    error_msg: String,
+    exit_code: Option<i32>,
+    stdout: Option<String>,
+    stderr: Option<String>,
+) -> Result<()> {
+    db::update_maintenance_run(
+        pool,
+        maintenance_run_id,
+        Utc::now(),
+        "failed".to_string(),
+        exit_code,
+        Some(error_msg),
+        None,
+        None,
+        stdout,
+        stderr,
+    )
+    .await?;
+    Ok(())
+}
+
+pub(crate) async fn update_maintenance_with_success(
+    pool: &PgPool,
+    maintenance_run_id: i32,
+    exit_code: i32,
+    stats: Option<&ForgetStats>,
+    stdout: String,
+    stderr: Option<String>,
+) -> Result<()> {
+    db::update_maintenance_run(
+        pool,
+        maintenance_run_id,
+        Utc::now(),
+        "success".to_string(),
+        Some(exit_code),
+        None,
+        stats.map(|s| s.snapshots_kept),
+        stats.map(|s| s.snapshots_removed),
+        Some(stdout),
+        stderr,
+    )
+    .await?;
+    Ok(())
+}
+
+pub(crate) async fn run_maintenance_command(
+    pool: &PgPool,
+    maintenance_run_id: i32,
+    mut command: Command,
+    task_type: &str,
+    trace_id: &str,
+) -> Result<Output> {
+    let output = match command.output().await {
+        Ok(output) => output,
+        Err(e) => {
+            let error_msg = format!("Failed to execute restic: {}", e);
+            error!(trace_id = trace_id, "{}", error_msg);
+            update_maintenance_with_failure(
+                pool,
+                maintenance_run_id,
+                error_msg.clone(),
+                None,
+                None,
+                None,
+            )
+            .await?;
+            return Err(BackupError::ExecutionFailed(error_msg).into());
+        }
+    };
+
+    if !output.status.success() {
+        let exit_code = output.status.code().unwrap_or(-1);
+        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+        let error_msg = if !stderr.is_empty() {
+            stderr.clone()
+        } else {
+            format!("restic {} failed with no error message", task_type)
+        };
+        warn!(
+            trace_id = trace_id,
+            exit_code = exit_code,
+            "restic {} failed: {}",
+            task_type,
+            error_msg
+        );
+
+        update_maintenance_with_failure(
+            pool,
+            maintenance_run_id,
+            error_msg.clone(),
+            Some(exit_code),
+            Some(String::from_utf8_lossy(&output.stdout).to_string()),
+            Some(stderr),
+        )
+        .await?;
+
+        return Err(BackupError::ExecutionFailed(error_msg).into());
+    }
+
+    Ok(output)
+}
+
+pub async fn execute_forget(
+    job: &BackupJob,
+    config: &RemoteConfig,
+    pool: &PgPool,
+    repository: Option<&Repository>,
+    run_id: Option<i32>,
+    trace_id: &str,
+) -> Result<Option<i32>> {
+    if job.retention.is_empty() {
+        debug!(
+            trace_id = trace_id,
+            job_id = %job.id,
+            "Job has no retention policy, skipping forget"
+        );
+        return Ok(None);
+    }
+
+    info!(
+        trace_id = trace_id,
+        job_id = %job.id,
+        job_name = %job.name,
+        repository = repository_label(repository),
+        "Applying retention policy"
+    );
+
+    let maintenance_run_id = db::create_maintenance_run(
+        pool,
+        job.device_id.clone(),
+        repository.map(|r| r.id),
+        Some(job.id),
+        run_id,
+        "forget".to_string(),
+        None,
+        "backup".to_string(),
+    )
+    .await?;
+
+    let restic_cmd = match ResticCommand::new(config, repository) {
+        Ok(restic_cmd) => restic_cmd,
+        Err(e) => {
+            update_maintenance_with_failure(
+                pool,
+                maintenance_run_id,
+                e.to_string(),
+                None,
+                None,
+                None,
+            )
+            .await?;
+            return Err(e);
+        }
+    };
+
+    let output = run_maintenance_command(
+        pool,
+        maintenance_run_id,
+        restic_cmd.build_forget_command(job),
+        "forget",
+        trace_id,
+    )
+    .await?;
+
+    let exit_code = output.status.code().unwrap_or(-1);
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+
+    let stats = match parse_forget_json_output(&stdout) {
+        Ok(stats) => stats,
+        Err(e) => {
+            update_maintenance_with_failure(
+                pool,
+                maintenance_run_id,
+                e.to_string(),
+                Some(exit_code),
+                Some(stdout),
+                Some(stderr),
+            )
+            .await?;
+            return Err(e);
+        }
+    };
+
+    info!(
+        trace_id = trace_id,
+        job_id = %job.id,
+        snapshots_kept = stats.snapshots_kept,
+        snapshots_removed = stats.snapshots_removed,
+        "Retention policy applied"
+    );
+
+    let stderr_opt = Some(stderr).filter(|s| !s.is_empty());
+    update_maintenance_with_success(
+        pool,
+        maintenance_run_id,
+        exit_code,
+        Some(&stats),
+        stdout,
+        stderr_opt,
+    )
+    .await?;
+
+    Ok(Some(maintenance_run_id))
+}
+
+pub async fn execute_prune(
+    config: &RemoteConfig,
+    pool: &PgPool,
+    device_id: String,
+    repository: Option<&Repository>,
+    trace_id: &str,
+) -> Result<i32> {
+    info!(
+        trace_id = trace_id,
+        repository = repository_label(repository),
+        "Starting repository prune"
+    );
+
+    let maintenance_run_id = db::create_maintenance_run(
+        pool,
+        device_id,
+        repository.map(|r| r.id),
+        None,
+        None,
+        "prune".to_string(),
+        None,
+        "schedule".to_string(),
+    )
+    .await?;
+
+    let restic_cmd = match ResticCommand::new(config, repository) {
+        Ok(restic_cmd) => restic_cmd,
+        Err(e) => {
+            update_maintenance_with_failure(
+                pool,
+                maintenance_run_id,
+                e.to_string(),
+                None,
+                None,
+                None,
+            )
+            .await?;
+            return Err(e);
+        }
+    };
+
+    let output = run_maintenance_command(
+        pool,
+        maintenance_run_id,
+        restic_cmd.build_prune_command(),
+        "prune",
+        trace_id,
+    )
+    .await?;
+
+    let exit_code = output.status.code().unwrap_or(-1);
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+
+    info!(
+        trace_id = trace_id,
+        "Repository prune completed successfully"
+    );
+
+    let stderr_opt = Some(stderr).filter(|s| !s.is_empty());
+    update_maintenance_with_success(
+        pool,
+        maintenance_run_id,
+        exit_code,
+        None,
+        stdout,
+        stderr_opt,
+    )
+    .await?;
+
+    Ok(maintenance_run_id)
+}
@@ -0,0 +1,110 @@
+use crate::config::remote::RemoteConfig;
+use crate::db::models::BackupJob;
+use std::time::Duration;
+
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30 * 60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    pub retries: u32,
+    // Doubled for every further retry
+    pub initial_delay: Duration,
+}
+
+impl RetryPolicy {
+    pub fn for_job(job: &BackupJob, config: &RemoteConfig) -> Self {
+        let job_value = |key: &str| job.metadata.get(key).and_then(|v| v.as_u64());
+
+        let retries =
+            job_value("retry_attempts").unwrap_or_else(|| config.backup_retry_attempts() as u64);
+        let backoff_seconds = job_value("retry_backoff_seconds")
+            .unwrap_or_else(|| config.backup_retry_backoff_seconds());
+
+        Self {
+            retries: u32::try_from(retries).unwrap_or(u32::MAX),
+            initial_delay: Duration::from_secs(backoff_seconds),
+        }
+    }
+
+    pub fn delay(&self, retry: u32) -> Duration {
+        let factor = 2u32.saturating_pow(retry.saturating_sub(1));
+        self.initial_delay
+            .saturating_mul(factor)
+            .min(MAX_RETRY_DELAY)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use std::collections::HashMap;
+
+    fn job(metadata: serde_json::Value) -> BackupJob {
+        BackupJob {
+            id: uuid::Uuid::new_v4(),
+            device_id: "test-device".to_string(),
+            name: "test-job".to_string(),
+            description: None,
+            source_paths: vec!["/data".to_string()],
+            exclude_patterns: None,
+            tags: None,
+            restic_args: serde_json::json!([]),
+            enabled: true,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            metadata,
+            origin_name: None,
+            origin_id: None,
+            account_id: None,
+            retention: Default::default(),
+        }
+    }
+
+    fn config(settings: &[(&str, &str)]) -> RemoteConfig {
+        RemoteConfig {
+            jobs: vec![],
+            schedules: vec![],
+            hooks: vec![],
+            repositories: vec![],
+            job_repositories: vec![],
+            settings: settings
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect::<HashMap<_, _>>(),
+        }
+    }
+
+    #[test]
+    fn test_retry_policy_for_job() {
+        let defaults = RetryPolicy::for_job(&job(serde_json::json!({})), &config(&[]));
+        assert_eq!(defaults.retries, 2);
+        assert_eq!(defaults.initial_delay, Duration::from_secs(60));
+
+        let settings = config(&[
+            ("backup_retry_attempts", "5"),
+            ("backup_retry_backoff_seconds", "10"),
+        ]);
+        let policy = RetryPolicy::for_job(&job(serde_json::json!({})), &settings);
+        assert_eq!(policy.retries, 5);
+        assert_eq!(policy.initial_delay, Duration::from_secs(10));
+
+        let policy =
+            RetryPolicy::for_job(&job(serde_json::json!({"retry_attempts": 0})), &settings);
+        assert_eq!(policy.retries, 0);
+    }
+
+    #[test]
+    fn test_retry_delay_doubles_up_to_maximum() {
+        let policy = RetryPolicy {
+            retries: 10,
+            initial_delay: Duration::from_secs(60),
+        };
+
+        assert_eq!(policy.delay(1), Duration::from_secs(60));
+        assert_eq!(policy.delay(2), Duration::from_secs(120));
+        assert_eq!(policy.delay(3), Duration::from_secs(240));
+        assert_eq!(policy.delay(10), MAX_RETRY_DELAY);
+        assert_eq!(policy.delay(100), MAX_RETRY_DELAY);
+    }
+}
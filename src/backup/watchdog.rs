@@ -0,0 +1,252 @@
+use crate::backup::output::BackupProgress;
+use crate::config::remote::RemoteConfig;
+use crate::db::models::BackupJob;
+use std::fmt;
+use tokio::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RunLimits {
+    pub max_runtime: Option<Duration>,
+    pub stall_timeout: Option<Duration>,
+}
+
+impl RunLimits {
+    // A value of 0 disables the limit
+    pub fn for_job(job: &BackupJob, config: &RemoteConfig) -> Self {
+        let job_minutes = |key: &str| job.metadata.get(key).and_then(|v| v.as_u64());
+
+        let max_runtime = job_minutes("max_runtime_minutes")
+            .unwrap_or_else(|| config.backup_max_runtime_minutes());
+        let stall_timeout = job_minutes("stall_timeout_minutes")
+            .unwrap_or_else(|| config.backup_stall_timeout_minutes());
+
+        Self {
+            max_runtime: minutes(max_runtime),
+            stall_timeout: minutes(stall_timeout),
+        }
+    }
+}
+
+fn minutes(value: u64) -> Option<Duration> {
+    if value == 0 {
+        None
+    } else {
+        Some(Duration::from_secs(value * 60))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchdogTrigger {
+    TimedOut(Duration),
+    Stalled(Duration),
+}
+
+impl WatchdogTrigger {
+    pub fn failure_reason(&self) -> &'static str {
+        match self {
+            WatchdogTrigger::TimedOut(_) => "timeout",
+            WatchdogTrigger::Stalled(_) => "stalled",
+        }
+    }
+}
+
+impl fmt::Display for WatchdogTrigger {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WatchdogTrigger::TimedOut(limit) => write!(
+                f,
+                "backup exceeded the maximum runtime of {} minutes",
+                limit.as_secs() / 60
+            ),
+            WatchdogTrigger::Stalled(limit) => write!(
+                f,
+                "restic reported no progress for {} minutes",
+                limit.as_secs() / 60
+            ),
+        }
+    }
+}
+
+type ProgressCounters = (i64, i64, i64, i64);
+
+// The elapsed time restic updates in every status message does not count as progress
+#[derive(Debug)]
+pub struct Watchdog {
+    limits: RunLimits,
+    started_at: Instant,
+    last_progress_at: Instant,
+    last_counters: Option<ProgressCounters>,
+}
+
+impl Watchdog {
+    pub fn new(limits: RunLimits, now: Instant) -> Self {
+        Self {
+            limits,
+            started_at: now,
+            last_progress_at: now,
+            last_counters: None,
+        }
+    }
+
+    pub fn observe(&mut self, progress: &BackupProgress, now: Instant) {
+        let counters = (
+            progress.files_done,
+            progress.bytes_done,
+            progress.total_files,
+            progress.total_bytes,
+        );
+
+        if self.last_counters != Some(counters) {
+            self.last_counters = Some(counters);
+            self.last_progress_at = now;
+        }
+    }
+
+    pub fn deadline(&self) -> Option<Instant> {
+        let runtime_deadline = self.limits.max_runtime.map(|limit| self.started_at + limit);
+        let stall_deadline = self
+            .limits
+            .stall_timeout
+            .map(|limit| self.last_progress_at + limit);
+
+        match (runtime_deadline, stall_deadline) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        }
+    }
+
+    pub fn check(&self, now: Instant) -> Option<WatchdogTrigger> {
+        if let Some(limit) = self.limits.max_runtime {
+            if now >= self.started_at + limit {
+                return Some(WatchdogTrigger::TimedOut(limit));
+            }
+        }
+
+        if let Some(limit) = self.limits.stall_timeout {
+            if now >= self.last_progress_at + limit {
+                return Some(WatchdogTrigger::Stalled(limit));
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use std::collections::HashMap;
+
+    fn limits(max_runtime_minutes: u64, stall_timeout_minutes: u64) -> RunLimits {
+        RunLimits {
+            max_runtime: minutes(max_runtime_minutes),
+            stall_timeout: minutes(stall_timeout_minutes),
+        }
+    }
+
+    fn progress(files_done: i64, bytes_done: i64) -> BackupProgress {
+        BackupProgress {
+            files_done,
+            bytes_done,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_run_limits_from_job_metadata_and_settings() {
+        let mut settings = HashMap::new();
+        settings.insert("backup_max_runtime_minutes".to_string(), "600".to_string());
+        settings.insert("backup_stall_timeout_minutes".to_string(), "30".to_string());
+        let config = RemoteConfig {
+            jobs: vec![],
+            schedules: vec![],
+            hooks: vec![],
+            repositories: vec![],
+            job_repositories: vec![],
+            settings,
+        };
+
+        let mut job = BackupJob {
+            id: uuid::Uuid::new_v4(),
+            device_id: "test-device".to_string(),
+            name: "test-job".to_string(),
+            description: None,
+            source_paths: vec!["/data".to_string()],
+            exclude_patterns: None,
+            tags: None,
+            restic_args: serde_json::json!([]),
+            enabled: true,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            metadata: serde_json::json!({}),
+            origin_name: None,
+            origin_id: None,
+            account_id: None,
+            retention: Default::default(),
+        };
+
+        assert_eq!(RunLimits::for_job(&job, &config), limits(600, 30));
+
+        job.metadata = serde_json::json!({"max_runtime_minutes": 120, "stall_timeout_minutes": 0});
+        assert_eq!(RunLimits::for_job(&job, &config), limits(120, 0));
+
+        let empty_config = RemoteConfig {
+            jobs: vec![],
+            schedules: vec![],
+            hooks: vec![],
+            repositories: vec![],
+            job_repositories: vec![],
+            settings: HashMap::new(),
+        };
+        job.metadata = serde_json::json!({});
+        assert_eq!(
+            RunLimits::for_job(&job, &empty_config),
+            RunLimits::default()
+        );
+    }
+
+    #[test]
+    fn test_watchdog_max_runtime() {
+        let start = Instant::now();
+        let mut watchdog = Watchdog::new(limits(60, 0), start);
+
+        watchdog.observe(&progress(1, 100), start + Duration::from_secs(59 * 60));
+        assert_eq!(watchdog.check(start + Duration::from_secs(59 * 60)), None);
+        assert_eq!(watchdog.deadline(), Some(start + Duration::from_secs(3600)));
+
+        assert_eq!(
+            watchdog.check(start + Duration::from_secs(3600)),
+            Some(WatchdogTrigger::TimedOut(Duration::from_secs(3600)))
+        );
+    }
+
+    #[test]
+    fn test_watchdog_stall_detection() {
+        let start = Instant::now();
+        let mut watchdog = Watchdog::new(limits(0, 10), start);
+        let minute = Duration::from_secs(60);
+
+        watchdog.observe(&progress(5, 500), start + minute * 5);
+        assert_eq!(watchdog.deadline(), Some(start + minute * 15));
+
+        // Repeated status messages without changed counters are not progress
+        watchdog.observe(&progress(5, 500), start + minute * 12);
+        assert_eq!(watchdog.check(start + minute * 14), None);
+
+        let trigger = watchdog
+            .check(start + minute * 15)
+            .expect("Backup should be stalled");
+        assert_eq!(trigger, WatchdogTrigger::Stalled(minute * 10));
+        assert_eq!(trigger.failure_reason(), "stalled");
+    }
+
+    #[test]
+    fn test_watchdog_without_limits() {
+        let start = Instant::now();
+        let watchdog = Watchdog::new(RunLimits::default(), start);
+
+        assert_eq!(watchdog.deadline(), None);
+        assert_eq!(watchdog.check(start + Duration::from_secs(86400 * 7)), None);
+    }
+}
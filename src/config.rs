@@ -1,5 +1,8 @@
+pub mod cache;
+pub mod diff;
 pub mod local;
 pub mod remote;
+pub mod secret;
 
 pub use local::LocalConfig;
 pub use remote::load_config_from_db;
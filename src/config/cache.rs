@@ -0,0 +1,258 @@
+use crate::config::remote::RemoteConfig;
+use crate::config::secret::DeviceKey;
+use crate::db::models::{
+    BackendCredentials, BackupHook, BackupJob, JobRepository, Repository, Schedule,
+};
+use crate::error::{ConfigError, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::types::Json;
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+pub struct ConfigCache {
+    path: PathBuf,
+    device_key: Option<DeviceKey>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedConfig {
+    saved_at: DateTime<Utc>,
+    jobs: Vec<BackupJob>,
+    schedules: Vec<Schedule>,
+    hooks: Vec<BackupHook>,
+    repositories: Vec<Repository>,
+    job_repositories: Vec<JobRepository>,
+    settings: HashMap<String, String>,
+    // Repository passwords and credentials are never serialized with the repositories
+    repository_secrets: Vec<RepositorySecrets>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct RepositorySecrets {
+    id: Uuid,
+    password: String,
+    credentials: Option<BackendCredentials>,
+}
+
+impl ConfigCache {
+    pub fn new(path: PathBuf, device_key: Option<DeviceKey>) -> Self {
+        Self { path, device_key }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn save(&self, config: &RemoteConfig) -> Result<()> {
+        let cached = CachedConfig {
+            saved_at: Utc::now(),
+            jobs: config.jobs.clone(),
+            schedules: config.schedules.clone(),
+            hooks: config.hooks.clone(),
+            repositories: config.repositories.clone(),
+            job_repositories: config.job_repositories.clone(),
+            settings: config.settings.clone(),
+            repository_secrets: config
+                .repositories
+                .iter()
+                .map(|repository| RepositorySecrets {
+                    id: repository.id,
+                    password: repository.password.clone(),
+                    credentials: repository.credentials.as_ref().map(|c| c.0.clone()),
+                })
+                .collect(),
+        };
+
+        let json = serde_json::to_string(&cached)
+            .map_err(|e| ConfigError::ParseFailed(format!("Failed to serialize cache: {}", e)))?;
+
+        let content = match &self.device_key {
+            Some(key) => key.encrypt(&json)?,
+            None => json,
+        };
+
+        self.write_private(content.as_bytes()).map_err(|e| {
+            ConfigError::LoadFailed(format!(
+                "Failed to write config cache {}: {}",
+                self.path.display(),
+                e
+            ))
+            .into()
+        })
+    }
+
+    // A crash never leaves a truncated cache behind
+    fn write_private(&self, content: &[u8]) -> std::io::Result<()> {
+        let temp_path = self.path.with_extension("tmp");
+
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+
+        let mut file = options.open(&temp_path)?;
+        file.write_all(content)?;
+        file.sync_all()?;
+        drop(file);
+
+        std::fs::rename(&temp_path, &self.path)
+    }
+
+    pub fn load(&self) -> Result<Option<(RemoteConfig, DateTime<Utc>)>> {
+        let content = match std::fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => {
+                return Err(ConfigError::LoadFailed(format!(
+                    "Failed to read config cache {}: {}",
+                    self.path.display(),
+                    e
+                ))
+                .into())
+            }
+        };
+
+        let json = match content.strip_prefix("enc:") {
+            Some(encrypted) => {
+                let key = self.device_key.as_ref().ok_or_else(|| {
+                    ConfigError::LoadFailed(
+                        "Config cache is encrypted, but device.secret_key is not configured"
+                            .to_string(),
+                    )
+                })?;
+                key.decrypt(encrypted)?
+            }
+            None => content,
+        };
+
+        let cached: CachedConfig = serde_json::from_str(&json).map_err(|e| {
+            ConfigError::ParseFailed(format!("Failed to parse config cache: {}", e))
+        })?;
+
+        let secrets: HashMap<Uuid, RepositorySecrets> = cached
+            .repository_secrets
+            .into_iter()
+            .map(|secrets| (secrets.id, secrets))
+            .collect();
+
+        let repositories = cached
+            .repositories
+            .into_iter()
+            .map(|mut repository| {
+                if let Some(secrets) = secrets.get(&repository.id) {
+                    repository.password = secrets.password.clone();
+                    repository.credentials = secrets.credentials.clone().map(Json);
+                }
+                repository
+            })
+            .collect();
+
+        let config = RemoteConfig {
+            jobs: cached.jobs,
+            schedules: cached.schedules,
+            hooks: cached.hooks,
+            repositories,
+            job_repositories: cached.job_repositories,
+            settings: cached.settings,
+        };
+
+        Ok(Some((config, cached.saved_at)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> RemoteConfig {
+        let mut settings = HashMap::new();
+        settings.insert("repository_url".to_string(), "/srv/restic".to_string());
+
+        RemoteConfig {
+            jobs: vec![],
+            schedules: vec![],
+            hooks: vec![],
+            repositories: vec![Repository {
+                id: Uuid::new_v4(),
+                name: "offsite".to_string(),
+                url: "b2:bucket:path".to_string(),
+                password: "repository-secret".to_string(),
+                cache_dir: None,
+                environment: serde_json::json!({}),
+                enabled: true,
+                created_at: Utc::now(),
+                updated_at: Utc::now(),
+                metadata: serde_json::json!({}),
+                credentials: Some(Json(BackendCredentials::B2 {
+                    account_id: "id".to_string(),
+                    account_key: "key".to_string(),
+                })),
+            }],
+            job_repositories: vec![],
+            settings,
+        }
+    }
+
+    #[test]
+    fn test_missing_cache() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let cache = ConfigCache::new(dir.path().join("config.cache"), None);
+
+        assert!(cache.load().expect("Failed to load cache").is_none());
+    }
+
+    #[test]
+    fn test_cache_round_trip() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let cache = ConfigCache::new(dir.path().join("config.cache"), None);
+
+        let config = config();
+        cache.save(&config).expect("Failed to save cache");
+        let (loaded, _) = cache
+            .load()
+            .expect("Failed to load cache")
+            .expect("Cache not found");
+
+        // Including the password and credentials, which are not serialized with repositories
+        assert_eq!(loaded.repositories, config.repositories);
+        assert_eq!(loaded.repository_url(), Some(&"/srv/restic".to_string()));
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(cache.path())
+                .expect("Failed to read cache metadata")
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+
+    #[test]
+    fn test_encrypted_cache() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let path = dir.path().join("config.cache");
+        let key = DeviceKey::generate().expect("Failed to generate key");
+        let device_key = || DeviceKey::from_base64(&key).expect("Failed to parse key");
+
+        ConfigCache::new(path.clone(), Some(device_key()))
+            .save(&config())
+            .expect("Failed to save cache");
+
+        let content = std::fs::read_to_string(&path).expect("Failed to read cache");
+        assert!(!content.contains("repository-secret"));
+
+        assert!(ConfigCache::new(path.clone(), None).load().is_err());
+        let (loaded, _) = ConfigCache::new(path, Some(device_key()))
+            .load()
+            .expect("Failed to load cache")
+            .expect("Cache not found");
+        assert_eq!(loaded.repositories[0].password, "repository-secret");
+    }
+}
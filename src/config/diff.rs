@@ -0,0 +1,317 @@
+use crate::config::remote::RemoteConfig;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::hash::Hash;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Changes {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<String>,
+}
+
+impl Changes {
+    fn between<T, K: Eq + Hash>(
+        old: &[T],
+        new: &[T],
+        key: impl Fn(&T) -> K,
+        label: impl Fn(&T) -> String,
+        same: impl Fn(&T, &T) -> bool,
+    ) -> Self {
+        let old_by_key: HashMap<K, &T> = old.iter().map(|item| (key(item), item)).collect();
+        let mut changes = Changes::default();
+
+        for item in new {
+            match old_by_key.get(&key(item)) {
+                None => changes.added.push(label(item)),
+                Some(previous) if !same(previous, item) => changes.changed.push(label(item)),
+                Some(_) => {}
+            }
+        }
+
+        let new_keys: HashSet<K> = new.iter().map(&key).collect();
+        for item in old {
+            if !new_keys.contains(&key(item)) {
+                changes.removed.push(label(item));
+            }
+        }
+
+        changes
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+impl fmt::Display for Changes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let parts: Vec<String> = [
+            ("added", &self.added),
+            ("removed", &self.removed),
+            ("changed", &self.changed),
+        ]
+        .iter()
+        .filter(|(_, items)| !items.is_empty())
+        .map(|(kind, items)| format!("{} {}", kind, items.join(", ")))
+        .collect();
+
+        write!(f, "{}", parts.join("; "))
+    }
+}
+
+// Settings are compared by key only, their values may be secrets
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConfigDiff {
+    pub jobs: Changes,
+    pub schedules: Changes,
+    pub hooks: Changes,
+    pub repositories: Changes,
+    pub job_repositories: Changes,
+    pub settings: Changes,
+}
+
+impl ConfigDiff {
+    pub fn between(old: &RemoteConfig, new: &RemoteConfig) -> Self {
+        let jobs = Changes::between(
+            &old.jobs,
+            &new.jobs,
+            |job| job.id,
+            |job| format!("{} ({})", job.name, job.id),
+            |a, b| a == b,
+        );
+
+        let schedules = Changes::between(
+            &old.schedules,
+            &new.schedules,
+            |schedule| schedule.id,
+            |schedule| format!("{} of job {}", schedule.id, schedule.job_id),
+            |a, b| a.same_definition(b),
+        );
+
+        let hooks = Changes::between(
+            &old.hooks,
+            &new.hooks,
+            |hook| hook.id,
+            |hook| format!("{} {} of job {}", hook.timing, hook.id, hook.job_id),
+            |a, b| a == b,
+        );
+
+        let repositories = Changes::between(
+            &old.repositories,
+            &new.repositories,
+            |repository| repository.id,
+            |repository| repository.name.clone(),
+            |a, b| a == b,
+        );
+
+        // The last successful backup of a destination is state, not configuration
+        let job_repositories = Changes::between(
+            &old.job_repositories,
+            &new.job_repositories,
+            |jr| (jr.job_id, jr.repository_id),
+            |jr| {
+                let repository = new
+                    .repository(jr.repository_id)
+                    .or_else(|| old.repository(jr.repository_id))
+                    .map(|r| r.name.clone())
+                    .unwrap_or_else(|| jr.repository_id.to_string());
+                format!("job {} -> {}", jr.job_id, repository)
+            },
+            |_, _| true,
+        );
+
+        let mut old_settings: Vec<(&String, &String)> = old.settings.iter().collect();
+        let mut new_settings: Vec<(&String, &String)> = new.settings.iter().collect();
+        old_settings.sort();
+        new_settings.sort();
+        let settings = Changes::between(
+            &old_settings,
+            &new_settings,
+            |(key, _)| (*key).clone(),
+            |(key, _)| (*key).clone(),
+            |a, b| a.1 == b.1,
+        );
+
+        Self {
+            jobs,
+            schedules,
+            hooks,
+            repositories,
+            job_repositories,
+            settings,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.categories()
+            .iter()
+            .all(|(_, changes)| changes.is_empty())
+    }
+
+    // The time zone setting applies to all schedules
+    pub fn affects_schedules(&self) -> bool {
+        !self.schedules.is_empty() || self.setting_changed("timezone")
+    }
+
+    pub fn setting_changed(&self, key: &str) -> bool {
+        let settings = &self.settings;
+        [&settings.added, &settings.removed, &settings.changed]
+            .iter()
+            .any(|keys| keys.iter().any(|k| k == key))
+    }
+
+    pub fn categories(&self) -> [(&'static str, &Changes); 6] {
+        [
+            ("jobs", &self.jobs),
+            ("schedules", &self.schedules),
+            ("hooks", &self.hooks),
+            ("repositories", &self.repositories),
+            ("job_repositories", &self.job_repositories),
+            ("settings", &self.settings),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::models::{BackupJob, RetentionPolicy, Schedule};
+    use chrono::Utc;
+    use uuid::Uuid;
+
+    fn job(name: &str) -> BackupJob {
+        BackupJob {
+            id: Uuid::new_v4(),
+            device_id: "test-device".to_string(),
+            name: name.to_string(),
+            description: None,
+            source_paths: vec!["/home".to_string()],
+            exclude_patterns: None,
+            tags: None,
+            restic_args: serde_json::json!([]),
+            enabled: true,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            metadata: serde_json::json!({}),
+            origin_name: None,
+            origin_id: None,
+            account_id: None,
+            retention: RetentionPolicy::default(),
+        }
+    }
+
+    fn schedule(id: i32, job_id: Uuid, cron: &str) -> Schedule {
+        Schedule {
+            id,
+            job_id,
+            schedule_type: "cron".to_string(),
+            cron_expression: Some(cron.to_string()),
+            interval_seconds: None,
+            enabled: true,
+            last_run_at: None,
+            next_run_at: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            metadata: serde_json::json!({}),
+            calendar: None,
+            timezone: None,
+        }
+    }
+
+    fn config(
+        jobs: Vec<BackupJob>,
+        schedules: Vec<Schedule>,
+        settings: &[(&str, &str)],
+    ) -> RemoteConfig {
+        RemoteConfig {
+            jobs,
+            schedules,
+            hooks: vec![],
+            repositories: vec![],
+            job_repositories: vec![],
+            settings: settings
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_unchanged_config() {
+        let home = job("home");
+        let old = config(
+            vec![home.clone()],
+            vec![schedule(1, home.id, "0 0 2 * * *")],
+            &[("timezone", "Europe/Prague")],
+        );
+
+        // Runs of the scheduler are not configuration changes
+        let mut new = old.clone();
+        new.schedules[0].last_run_at = Some(Utc::now());
+        new.schedules[0].next_run_at = Some(Utc::now());
+
+        let diff = ConfigDiff::between(&old, &new);
+        assert!(diff.is_empty());
+        assert!(!diff.affects_schedules());
+    }
+
+    #[test]
+    fn test_config_changes() {
+        let home = job("home");
+        let photos = job("photos");
+        let mut renamed = home.clone();
+        renamed.source_paths.push("/etc".to_string());
+        let documents = job("documents");
+
+        let old = config(
+            vec![home.clone(), photos.clone()],
+            vec![
+                schedule(1, home.id, "0 0 2 * * *"),
+                schedule(2, photos.id, "0 0 3 * * *"),
+            ],
+            &[
+                ("timezone", "Europe/Prague"),
+                ("prune_interval_hours", "24"),
+            ],
+        );
+        let new = config(
+            vec![renamed, documents.clone()],
+            vec![
+                schedule(1, home.id, "0 0 4 * * *"),
+                schedule(3, documents.id, "0 0 5 * * *"),
+            ],
+            &[("timezone", "UTC"), ("check_interval_hours", "168")],
+        );
+
+        let diff = ConfigDiff::between(&old, &new);
+
+        assert_eq!(
+            diff.jobs,
+            Changes {
+                added: vec![format!("documents ({})", documents.id)],
+                removed: vec![format!("photos ({})", photos.id)],
+                changed: vec![format!("home ({})", home.id)],
+            }
+        );
+        assert_eq!(diff.schedules.added.len(), 1);
+        assert_eq!(diff.schedules.removed.len(), 1);
+        assert_eq!(
+            diff.schedules.changed,
+            vec![format!("1 of job {}", home.id)]
+        );
+        assert_eq!(
+            diff.settings,
+            Changes {
+                added: vec!["check_interval_hours".to_string()],
+                removed: vec!["prune_interval_hours".to_string()],
+                changed: vec!["timezone".to_string()],
+            }
+        );
+        assert!(diff.affects_schedules());
+        assert_eq!(
+            diff.settings.to_string(),
+            "added check_interval_hours; removed prune_interval_hours; changed timezone"
+        );
+    }
+}
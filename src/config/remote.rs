@@ -1,12 +1,18 @@
-use crate::db::models::{BackupJob, Schedule};
-use crate::error::Result;
+use crate::db::models::{
+    BackendCredentials, BackupHook, BackupJob, JobRepository, Repository, Schedule,
+};
+use crate::error::{ConfigError, Result};
 use sqlx::PgPool;
 use std::collections::HashMap;
+use uuid::Uuid;
 
 #[derive(Debug, Clone)]
 pub struct RemoteConfig {
     pub jobs: Vec<BackupJob>,
     pub schedules: Vec<Schedule>,
+    pub hooks: Vec<BackupHook>,
+    pub repositories: Vec<Repository>,
+    pub job_repositories: Vec<JobRepository>,
     pub settings: HashMap<String, String>,
 }
 
@@ -15,6 +21,58 @@ impl RemoteConfig {
         self.settings.get(key)
     }
 
+    pub fn hooks_for_job(&self, job_id: Uuid, timing: &str) -> Vec<&BackupHook> {
+        let mut hooks: Vec<&BackupHook> = self
+            .hooks
+            .iter()
+            .filter(|hook| hook.job_id == job_id && hook.timing == timing && hook.enabled)
+            .collect();
+        hooks.sort_by_key(|hook| (hook.sort_order, hook.created_at));
+        hooks
+    }
+
+    pub fn repository(&self, repository_id: Uuid) -> Option<&Repository> {
+        self.repositories.iter().find(|r| r.id == repository_id)
+    }
+
+    // None stands for the repository configured in settings
+    pub fn destinations(&self, job_id: Uuid) -> Vec<Option<&Repository>> {
+        let mut destinations = Vec::new();
+        let mut has_repositories = false;
+
+        for job_repository in self
+            .job_repositories
+            .iter()
+            .filter(|jr| jr.job_id == job_id)
+        {
+            has_repositories = true;
+            if let Some(repository) = self.repository(job_repository.repository_id) {
+                destinations.push(Some(repository));
+            }
+        }
+
+        if !has_repositories {
+            destinations.push(None);
+        }
+        destinations
+    }
+
+    pub fn device_destinations(&self) -> Vec<Option<&Repository>> {
+        let mut destinations: Vec<Option<&Repository>> = Vec::new();
+
+        for job in &self.jobs {
+            for destination in self.destinations(job.id) {
+                if !destinations
+                    .iter()
+                    .any(|d| d.map(|r| r.id) == destination.map(|r| r.id))
+                {
+                    destinations.push(destination);
+                }
+            }
+        }
+        destinations
+    }
+
     #[allow(dead_code)]
     pub fn repository_url(&self) -> Option<&String> {
         self.get_setting("repository_url")
@@ -30,6 +88,115 @@ impl RemoteConfig {
         self.get_setting("repository_cache_dir")
     }
 
+    // Stored as JSON in the format of repositories.credentials
+    pub fn repository_credentials(&self) -> Result<Option<BackendCredentials>> {
+        match self
+            .get_setting("repository_credentials")
+            .filter(|s| !s.trim().is_empty())
+        {
+            Some(json) => serde_json::from_str(json).map(Some).map_err(|e| {
+                ConfigError::ParseFailed(format!("Invalid repository_credentials: {}", e)).into()
+            }),
+            None => Ok(None),
+        }
+    }
+
+    pub fn timezone(&self) -> Option<&str> {
+        self.get_setting("timezone")
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+    }
+
+    pub fn prune_interval_hours(&self) -> u64 {
+        self.get_setting("prune_interval_hours")
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(0)
+    }
+
+    pub fn check_interval_hours(&self) -> u64 {
+        self.get_setting("check_interval_hours")
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(0)
+    }
+
+    pub fn check_read_data_subsets(&self) -> u32 {
+        self.get_setting("check_read_data_subsets")
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(0)
+    }
+
+    // Maximum runtime of a repository check in minutes; 0 means no limit
+    pub fn check_max_runtime_minutes(&self) -> u64 {
+        self.get_setting("check_max_runtime_minutes")
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(0)
+    }
+
+    pub fn maintenance_window(&self) -> Option<&String> {
+        self.get_setting("maintenance_window")
+            .filter(|window| !window.is_empty())
+    }
+
+    pub fn backup_max_runtime_minutes(&self) -> u64 {
+        self.get_setting("backup_max_runtime_minutes")
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(0)
+    }
+
+    pub fn backup_stall_timeout_minutes(&self) -> u64 {
+        self.get_setting("backup_stall_timeout_minutes")
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(0)
+    }
+
+    pub fn backup_retry_attempts(&self) -> u32 {
+        self.get_setting("backup_retry_attempts")
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(2)
+    }
+
+    pub fn backup_retry_backoff_seconds(&self) -> u64 {
+        self.get_setting("backup_retry_backoff_seconds")
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(60)
+    }
+
+    pub fn max_concurrent_backups(&self) -> usize {
+        self.get_setting("max_concurrent_backups")
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(1)
+    }
+
+    // 0 means only max_concurrent_backups applies
+    pub fn max_concurrent_backups_per_repository(&self) -> usize {
+        self.get_setting("max_concurrent_backups_per_repository")
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(0)
+    }
+
+    pub fn stale_run_timeout_minutes(&self) -> u64 {
+        self.get_setting("stale_run_timeout_minutes")
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(60)
+    }
+
+    pub fn catchup_grace_minutes(&self) -> i64 {
+        self.get_setting("catchup_grace_minutes")
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(5)
+    }
+
+    pub fn catchup_policy(&self) -> Option<&String> {
+        self.get_setting("catchup_policy")
+            .filter(|policy| !policy.is_empty())
+    }
+
+    pub fn catchup_overdue_minutes(&self) -> u64 {
+        self.get_setting("catchup_overdue_minutes")
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(0)
+    }
+
     #[allow(dead_code)]
     pub fn sync_interval_seconds(&self) -> u64 {
         self.get_setting("sync_interval_seconds")
@@ -41,6 +208,10 @@ impl RemoteConfig {
 pub async fn load_config_from_db(pool: &PgPool, device_id: String) -> Result<RemoteConfig> {
     let jobs = crate::db::get_jobs_for_device(pool, device_id.clone()).await?;
     let schedules = crate::db::get_schedules_for_device(pool, device_id.clone()).await?;
+    let hooks = crate::db::get_hooks_for_device(pool, device_id.clone()).await?;
+    let repositories = crate::db::get_repositories_for_device(pool, device_id.clone()).await?;
+    let job_repositories =
+        crate::db::get_job_repositories_for_device(pool, device_id.clone()).await?;
     let settings_vec = crate::db::get_settings_for_device(pool, device_id).await?;
 
     let settings: HashMap<String, String> =
@@ -49,6 +220,9 @@ pub async fn load_config_from_db(pool: &PgPool, device_id: String) -> Result<Rem
     Ok(RemoteConfig {
         jobs,
         schedules,
+        hooks,
+        repositories,
+        job_repositories,
         settings,
     })
 }
@@ -70,6 +244,9 @@ mod tests {
         let config = RemoteConfig {
             jobs: vec![],
             schedules: vec![],
+            hooks: vec![],
+            repositories: vec![],
+            job_repositories: vec![],
             settings,
         };
 
@@ -86,10 +263,86 @@ mod tests {
         let config = RemoteConfig {
             jobs: vec![],
             schedules: vec![],
+            hooks: vec![],
+            repositories: vec![],
+            job_repositories: vec![],
             settings: HashMap::new(),
         };
 
         assert_eq!(config.repository_url(), None);
         assert_eq!(config.sync_interval_seconds(), 300);
+        assert_eq!(config.prune_interval_hours(), 0);
+        assert_eq!(config.maintenance_window(), None);
+        assert_eq!(config.check_interval_hours(), 0);
+        assert_eq!(config.check_read_data_subsets(), 0);
+        assert_eq!(config.check_max_runtime_minutes(), 0);
+        assert_eq!(config.backup_max_runtime_minutes(), 0);
+        assert_eq!(config.backup_stall_timeout_minutes(), 0);
+        assert_eq!(config.stale_run_timeout_minutes(), 60);
+    }
+
+    fn repository(name: &str) -> Repository {
+        Repository {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            url: format!("/srv/restic/{}", name),
+            password: "secret".to_string(),
+            cache_dir: None,
+            environment: serde_json::json!({}),
+            enabled: true,
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+            metadata: serde_json::json!({}),
+            credentials: None,
+        }
+    }
+
+    fn job_repository(job_id: Uuid, repository_id: Uuid) -> JobRepository {
+        JobRepository {
+            job_id,
+            repository_id,
+            last_success_at: None,
+            last_run_id: None,
+            last_snapshot_id: None,
+            created_at: chrono::Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_destinations() {
+        let nas = repository("nas");
+        let offsite = repository("offsite");
+        let disabled_id = Uuid::new_v4();
+
+        let mirrored_job = Uuid::new_v4();
+        let default_job = Uuid::new_v4();
+        let disabled_job = Uuid::new_v4();
+
+        let config = RemoteConfig {
+            jobs: vec![],
+            schedules: vec![],
+            hooks: vec![],
+            job_repositories: vec![
+                job_repository(mirrored_job, nas.id),
+                job_repository(mirrored_job, offsite.id),
+                job_repository(disabled_job, disabled_id),
+            ],
+            repositories: vec![nas, offsite],
+            settings: HashMap::new(),
+        };
+
+        let names: Vec<Option<&str>> = config
+            .destinations(mirrored_job)
+            .into_iter()
+            .map(|r| r.map(|r| r.name.as_str()))
+            .collect();
+        assert_eq!(names, vec![Some("nas"), Some("offsite")]);
+
+        let default_destinations = config.destinations(default_job);
+        assert_eq!(default_destinations.len(), 1);
+        assert!(default_destinations[0].is_none());
+
+        // Jobs whose repositories are all disabled do not fall back to the default repository
+        assert!(config.destinations(disabled_job).is_empty());
     }
 }
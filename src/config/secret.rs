@@ -0,0 +1,278 @@
+use crate::error::{ConfigError, Result};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+use std::fmt;
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::OnceLock;
+
+static DEVICE_KEY: OnceLock<DeviceKey> = OnceLock::new();
+
+// Values without a prefix are taken literally, plain: keeps values starting with a prefix
+#[derive(Clone, PartialEq, Eq)]
+pub enum Secret {
+    Plain(String),
+    Env(String),
+    File(PathBuf),
+    Command(String),
+    Encrypted(String),
+}
+
+impl Secret {
+    pub fn parse(value: &str) -> Self {
+        if let Some(var) = value.strip_prefix("env:") {
+            Secret::Env(var.to_string())
+        } else if let Some(path) = value.strip_prefix("file:") {
+            Secret::File(PathBuf::from(path))
+        } else if let Some(command) = value.strip_prefix("command:") {
+            Secret::Command(command.to_string())
+        } else if let Some(encrypted) = value.strip_prefix("enc:") {
+            Secret::Encrypted(encrypted.to_string())
+        } else if let Some(plain) = value.strip_prefix("plain:") {
+            Secret::Plain(plain.to_string())
+        } else {
+            Secret::Plain(value.to_string())
+        }
+    }
+
+    pub fn resolve(&self) -> Result<String> {
+        self.resolve_with(DEVICE_KEY.get())
+    }
+
+    pub fn resolve_with(&self, device_key: Option<&DeviceKey>) -> Result<String> {
+        match self {
+            Secret::Plain(value) => Ok(value.clone()),
+            Secret::Env(var) => std::env::var(var).map_err(|_| {
+                ConfigError::SecretFailed(format!("environment variable {} is not set", var)).into()
+            }),
+            Secret::File(path) => std::fs::read_to_string(path)
+                .map(|content| trim_line_end(&content).to_string())
+                .map_err(|e| {
+                    ConfigError::SecretFailed(format!("cannot read {}: {}", path.display(), e))
+                        .into()
+                }),
+            Secret::Command(command) => run_command(command),
+            Secret::Encrypted(encrypted) => {
+                let key = device_key.ok_or_else(|| {
+                    ConfigError::SecretFailed(
+                        "the secret is encrypted, but device.secret_key is not configured"
+                            .to_string(),
+                    )
+                })?;
+                key.decrypt(encrypted)
+            }
+        }
+    }
+}
+
+// Never prints the secret itself
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Secret::Plain(_) => write!(f, "Plain(***)"),
+            Secret::Env(var) => write!(f, "Env({})", var),
+            Secret::File(path) => write!(f, "File({})", path.display()),
+            Secret::Command(command) => write!(f, "Command({})", command),
+            Secret::Encrypted(_) => write!(f, "Encrypted(***)"),
+        }
+    }
+}
+
+fn trim_line_end(value: &str) -> &str {
+    value.trim_end_matches(['\r', '\n'])
+}
+
+fn run_command(command: &str) -> Result<String> {
+    let output = if cfg!(target_os = "windows") {
+        Command::new("cmd").arg("/C").arg(command).output()
+    } else {
+        Command::new("sh").arg("-c").arg(command).output()
+    }
+    .map_err(|e| ConfigError::SecretFailed(format!("cannot run '{}': {}", command, e)))?;
+
+    if !output.status.success() {
+        return Err(ConfigError::SecretFailed(format!(
+            "'{}' exited with {}: {}",
+            command,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+        .into());
+    }
+
+    let stdout = String::from_utf8(output.stdout)
+        .map_err(|_| ConfigError::SecretFailed(format!("'{}' printed invalid UTF-8", command)))?;
+    Ok(trim_line_end(&stdout).to_string())
+}
+
+// Only kept in the local configuration, so secrets in the database cannot be read from a dump
+pub struct DeviceKey(LessSafeKey);
+
+impl DeviceKey {
+    const LENGTH: usize = 32;
+
+    pub fn from_base64(encoded: &str) -> Result<Self> {
+        let bytes = STANDARD.decode(encoded.trim()).map_err(|e| {
+            ConfigError::ValidationFailed(format!("device.secret_key is not valid base64: {}", e))
+        })?;
+
+        if bytes.len() != Self::LENGTH {
+            return Err(ConfigError::ValidationFailed(format!(
+                "device.secret_key must be {} bytes long, got {}",
+                Self::LENGTH,
+                bytes.len()
+            ))
+            .into());
+        }
+
+        Ok(Self::from_bytes(&bytes))
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Self {
+        let key = UnboundKey::new(&AES_256_GCM, bytes).expect("key length checked by caller");
+        Self(LessSafeKey::new(key))
+    }
+
+    pub fn generate() -> Result<String> {
+        let mut bytes = [0u8; Self::LENGTH];
+        SystemRandom::new().fill(&mut bytes).map_err(|_| {
+            ConfigError::SecretFailed("no secure random number generator".to_string())
+        })?;
+        Ok(STANDARD.encode(bytes))
+    }
+
+    pub fn encrypt(&self, value: &str) -> Result<String> {
+        let mut nonce = [0u8; NONCE_LEN];
+        SystemRandom::new().fill(&mut nonce).map_err(|_| {
+            ConfigError::SecretFailed("no secure random number generator".to_string())
+        })?;
+
+        let mut data = value.as_bytes().to_vec();
+        self.0
+            .seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::empty(), &mut data)
+            .map_err(|_| ConfigError::SecretFailed("encryption failed".to_string()))?;
+
+        let mut encrypted = nonce.to_vec();
+        encrypted.extend_from_slice(&data);
+        Ok(format!("enc:{}", STANDARD.encode(encrypted)))
+    }
+
+    pub fn decrypt(&self, encrypted: &str) -> Result<String> {
+        let invalid = || {
+            ConfigError::SecretFailed(
+                "the secret was not encrypted with the key of this device".to_string(),
+            )
+        };
+
+        let mut data = STANDARD.decode(encrypted.trim()).map_err(|_| invalid())?;
+        if data.len() < NONCE_LEN {
+            return Err(invalid().into());
+        }
+
+        let nonce = Nonce::try_assume_unique_for_key(&data[..NONCE_LEN]).map_err(|_| invalid())?;
+        let plaintext = self
+            .0
+            .open_in_place(nonce, Aad::empty(), &mut data[NONCE_LEN..])
+            .map_err(|_| invalid())?;
+
+        String::from_utf8(plaintext.to_vec()).map_err(|_| invalid().into())
+    }
+}
+
+pub fn set_device_key(key: DeviceKey) {
+    let _ = DEVICE_KEY.set(key);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_key() -> DeviceKey {
+        DeviceKey::from_base64(&DeviceKey::generate().expect("Failed to generate key"))
+            .expect("Failed to parse key")
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Secret::parse("secret"), Secret::Plain("secret".to_string()));
+        assert_eq!(
+            Secret::parse("env:RESTIC_PASSWORD"),
+            Secret::Env("RESTIC_PASSWORD".to_string())
+        );
+        assert_eq!(
+            Secret::parse("file:/etc/rbackup2/password"),
+            Secret::File(PathBuf::from("/etc/rbackup2/password"))
+        );
+        assert_eq!(
+            Secret::parse("command:pass show backup"),
+            Secret::Command("pass show backup".to_string())
+        );
+        assert_eq!(
+            Secret::parse("plain:env:not-a-variable"),
+            Secret::Plain("env:not-a-variable".to_string())
+        );
+    }
+
+    #[test]
+    fn test_resolve_file_and_command() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let path = dir.path().join("password");
+        std::fs::write(&path, "from-file\n").expect("Failed to write password file");
+
+        assert_eq!(
+            Secret::File(path)
+                .resolve_with(None)
+                .expect("Failed to resolve"),
+            "from-file"
+        );
+
+        if cfg!(unix) {
+            assert_eq!(
+                Secret::Command("echo from-command".to_string())
+                    .resolve_with(None)
+                    .expect("Failed to resolve"),
+                "from-command"
+            );
+            assert!(Secret::Command("exit 1".to_string())
+                .resolve_with(None)
+                .is_err());
+        }
+
+        assert!(Secret::Env("RBACKUP2_TEST_UNSET_VARIABLE".to_string())
+            .resolve_with(None)
+            .is_err());
+    }
+
+    #[test]
+    fn test_encrypted_secret() {
+        let key = test_key();
+        let encrypted = key.encrypt("repository-key").expect("Failed to encrypt");
+        assert!(!encrypted.contains("repository-key"));
+
+        let secret = Secret::parse(&encrypted);
+        assert_eq!(
+            secret.resolve_with(Some(&key)).expect("Failed to decrypt"),
+            "repository-key"
+        );
+
+        // Other devices and devices without a key cannot decrypt it
+        assert!(secret.resolve_with(Some(&test_key())).is_err());
+        assert!(secret.resolve_with(None).is_err());
+    }
+
+    #[test]
+    fn test_invalid_device_key() {
+        assert!(DeviceKey::from_base64("not base64!").is_err());
+        assert!(DeviceKey::from_base64(&STANDARD.encode([0u8; 16])).is_err());
+    }
+
+    #[test]
+    fn test_debug_hides_values() {
+        assert_eq!(
+            format!("{:?}", Secret::parse("hunter2")),
+            "Plain(***)".to_string()
+        );
+    }
+}
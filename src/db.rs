@@ -4,7 +4,21 @@ pub mod queries;
 // Re-export functions for use in tests and future phases
 #[allow(unused_imports)]
 pub use queries::{
-    create_pool, create_run, get_device, get_global_setting, get_job_by_id, get_jobs_for_device,
-    get_recent_runs, get_schedules_for_device, get_settings_for_device, run_migrations,
-    update_device_heartbeat, update_run, update_schedule_last_run, upsert_device,
+    abandon_maintenance_runs, abandon_runs, add_job_repository, claim_next_queued_job,
+    create_device_if_missing, create_hook_execution, create_journaled_hook_execution,
+    create_lazy_pool, create_maintenance_run, create_pool, create_repository, create_restore,
+    create_retry_run, create_run, create_schedule, delete_imported_schedules, delete_queued_job,
+    enqueue_job, get_device, get_failed_checks, get_global_setting, get_hook_executions,
+    get_hooks_for_device, get_hooks_for_job, get_job_by_id, get_job_queue,
+    get_job_repositories_for_device, get_jobs_for_device, get_last_maintenance_run,
+    get_latest_runs, get_recent_maintenance_runs, get_recent_runs, get_repositories_for_device,
+    get_requested_cancellations, get_restore, get_run, get_run_id_by_journal_id,
+    get_running_maintenance_runs, get_running_runs, get_schedules_for_device,
+    get_schedules_for_job, get_settings_for_device, insert_journaled_run, list_runs,
+    record_imported_maintenance_run, record_repository_success, register_device,
+    request_run_cancellation, requeue_interrupted_jobs, requeue_queued_job, run_migrations,
+    set_global_setting_if_empty, set_maintenance_failure_reason, set_run_failed_paths,
+    set_run_failure_reason, update_device_heartbeat, update_maintenance_run, update_restore,
+    update_run, update_run_cancelled, update_run_progress, update_schedule_last_run,
+    update_schedule_next_run, upsert_device, upsert_imported_hook, upsert_imported_job,
 };
@@ -1,6 +1,8 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use sqlx::types::Json;
 use sqlx::FromRow;
+use std::sync::OnceLock;
 use uuid::Uuid;
 
 #[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
@@ -17,7 +19,7 @@ pub struct Device {
     pub metadata: serde_json::Value,
 }
 
-#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, FromRow, Serialize, Deserialize)]
 pub struct BackupJob {
     pub id: Uuid,
     pub device_id: String,
@@ -34,6 +36,20 @@ pub struct BackupJob {
     pub origin_name: Option<String>,
     pub origin_id: Option<Uuid>,
     pub account_id: Option<Uuid>,
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    pub retention: RetentionPolicy,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, FromRow, Serialize, Deserialize)]
+pub struct RetentionPolicy {
+    pub keep_last: Option<i32>,
+    pub keep_hourly: Option<i32>,
+    pub keep_daily: Option<i32>,
+    pub keep_weekly: Option<i32>,
+    pub keep_monthly: Option<i32>,
+    pub keep_yearly: Option<i32>,
+    pub keep_within: Option<String>,
 }
 
 #[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
@@ -49,6 +65,60 @@ pub struct Schedule {
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub metadata: serde_json::Value,
+    pub calendar: Option<Json<CalendarSchedule>>,
+    // None uses the timezone setting
+    pub timezone: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CalendarSchedule {
+    // HH:MM in the schedule's time zone
+    #[serde(default)]
+    pub times_of_day: Vec<String>,
+    // 0 = Sunday ... 6 = Saturday
+    #[serde(default)]
+    pub days_of_week: Vec<u32>,
+    #[serde(default)]
+    pub days_of_month: Vec<u32>,
+    #[serde(default)]
+    pub months_of_year: Vec<u32>,
+    // Run at the next opportunity when a scheduled time was missed
+    #[serde(default)]
+    pub flexible: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, FromRow, Serialize, Deserialize)]
+pub struct BackupHook {
+    pub id: Uuid,
+    pub job_id: Uuid,
+    pub description: Option<String>,
+    pub command: String,
+    pub timing: String,
+    pub on_error: String,
+    pub timeout_seconds: i32,
+    pub sort_order: i32,
+    pub enabled: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub metadata: serde_json::Value,
+}
+
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct HookExecution {
+    pub id: i32,
+    pub run_id: i32,
+    pub hook_id: Option<Uuid>,
+    pub timing: String,
+    pub command: String,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    pub status: String,
+    pub exit_code: Option<i32>,
+    pub error_message: Option<String>,
+    pub stdout: Option<String>,
+    pub stderr: Option<String>,
+    pub duration_seconds: Option<i32>,
+    pub created_at: DateTime<Utc>,
 }
 
 #[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
@@ -77,6 +147,104 @@ pub struct Run {
     pub triggered_by: String,
     pub created_at: DateTime<Utc>,
     pub metadata: serde_json::Value,
+    pub progress: Option<serde_json::Value>,
+    pub progress_updated_at: Option<DateTime<Utc>>,
+    pub cancel_requested_at: Option<DateTime<Utc>>,
+    pub cancel_requested_by: Option<String>,
+    pub cancel_reason: Option<String>,
+    pub failure_reason: Option<String>,
+    pub attempt: i32,
+    pub retry_of: Option<i32>,
+    pub failed_paths: Option<serde_json::Value>,
+    pub repository_id: Option<Uuid>,
+    // Set for runs replayed from the run journal
+    pub journal_id: Option<Uuid>,
+    pub owner_host: Option<String>,
+    pub owner_pid: Option<i32>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RunFilter {
+    pub job_id: Option<Uuid>,
+    pub status: Option<String>,
+    pub repository_id: Option<Uuid>,
+    pub triggered_by: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct Restore {
+    pub id: i32,
+    pub job_id: Uuid,
+    pub device_id: String,
+    pub snapshot_id: Option<String>,
+    pub target_path: String,
+    pub include_patterns: Option<Vec<String>>,
+    pub exclude_patterns: Option<Vec<String>>,
+    pub overwrite_policy: String,
+    pub start_time: DateTime<Utc>,
+    pub end_time: Option<DateTime<Utc>>,
+    pub status: String,
+    pub exit_code: Option<i32>,
+    pub error_message: Option<String>,
+    pub files_restored: Option<i32>,
+    pub files_skipped: Option<i32>,
+    pub bytes_restored: Option<i64>,
+    pub duration_seconds: Option<i32>,
+    pub restic_output: Option<String>,
+    pub restic_errors: Option<String>,
+    pub triggered_by: String,
+    pub created_at: DateTime<Utc>,
+    pub metadata: serde_json::Value,
+    pub repository_id: Option<Uuid>,
+}
+
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct MaintenanceRun {
+    pub id: i32,
+    pub device_id: String,
+    pub job_id: Option<Uuid>,
+    pub run_id: Option<i32>,
+    pub task_type: String,
+    pub start_time: DateTime<Utc>,
+    pub end_time: Option<DateTime<Utc>>,
+    pub status: String,
+    pub exit_code: Option<i32>,
+    pub error_message: Option<String>,
+    pub snapshots_kept: Option<i32>,
+    pub snapshots_removed: Option<i32>,
+    pub read_data_subset: Option<String>,
+    pub duration_seconds: Option<i32>,
+    pub restic_output: Option<String>,
+    pub restic_errors: Option<String>,
+    pub triggered_by: String,
+    pub created_at: DateTime<Utc>,
+    pub metadata: serde_json::Value,
+    pub repository_id: Option<Uuid>,
+    pub owner_host: Option<String>,
+    pub owner_pid: Option<i32>,
+    pub failure_reason: Option<String>,
+}
+
+// Process executing a run or maintenance task
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RunOwner {
+    pub host: String,
+    pub pid: i32,
+}
+
+impl RunOwner {
+    pub fn current() -> &'static RunOwner {
+        static OWNER: OnceLock<RunOwner> = OnceLock::new();
+        OWNER.get_or_init(|| RunOwner {
+            host: hostname::get()
+                .ok()
+                .and_then(|h| h.into_string().ok())
+                .unwrap_or_default(),
+            pid: std::process::id() as i32,
+        })
+    }
 }
 
 #[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
@@ -90,6 +258,82 @@ pub struct Setting {
     pub updated_at: DateTime<Utc>,
 }
 
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct QueuedJob {
+    pub id: i64,
+    pub job_id: Uuid,
+    pub device_id: String,
+    pub triggered_by: String,
+    pub priority: i32,
+    pub status: String,
+    pub enqueued_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, PartialEq, FromRow, Serialize, Deserialize)]
+pub struct Repository {
+    pub id: Uuid,
+    pub name: String,
+    pub url: String,
+    #[serde(skip_serializing, default)]
+    pub password: String,
+    pub cache_dir: Option<String>,
+    pub environment: serde_json::Value,
+    pub enabled: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub metadata: serde_json::Value,
+    #[serde(skip_serializing, default)]
+    pub credentials: Option<Json<BackendCredentials>>,
+}
+
+// The type has to match the scheme of the repository URL
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase", deny_unknown_fields)]
+pub enum BackendCredentials {
+    S3 {
+        access_key_id: String,
+        secret_access_key: String,
+        #[serde(default)]
+        session_token: Option<String>,
+        #[serde(default)]
+        region: Option<String>,
+    },
+    B2 {
+        account_id: String,
+        account_key: String,
+    },
+    Azure {
+        account_name: String,
+        #[serde(default)]
+        account_key: Option<String>,
+        #[serde(default)]
+        account_sas: Option<String>,
+        #[serde(default)]
+        endpoint_suffix: Option<String>,
+    },
+    Rest {
+        username: String,
+        password: String,
+    },
+    Sftp {
+        #[serde(default)]
+        command: Option<String>,
+        #[serde(default)]
+        args: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct JobRepository {
+    pub job_id: Uuid,
+    pub repository_id: Uuid,
+    pub last_success_at: Option<DateTime<Utc>>,
+    pub last_run_id: Option<i32>,
+    pub last_snapshot_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
 impl BackupJob {
     #[allow(dead_code)]
     pub fn get_restic_tags(&self) -> Vec<String> {
@@ -109,6 +353,39 @@ impl BackupJob {
     }
 }
 
+impl RetentionPolicy {
+    pub fn is_empty(&self) -> bool {
+        self.keep_arguments().is_empty()
+    }
+
+    pub fn keep_arguments(&self) -> Vec<String> {
+        let counts = [
+            ("--keep-last", self.keep_last),
+            ("--keep-hourly", self.keep_hourly),
+            ("--keep-daily", self.keep_daily),
+            ("--keep-weekly", self.keep_weekly),
+            ("--keep-monthly", self.keep_monthly),
+            ("--keep-yearly", self.keep_yearly),
+        ];
+
+        let mut args = Vec::new();
+
+        for (flag, value) in counts {
+            if let Some(n) = value.filter(|n| *n > 0) {
+                args.push(flag.to_string());
+                args.push(n.to_string());
+            }
+        }
+
+        if let Some(within) = self.keep_within.as_ref().filter(|w| !w.is_empty()) {
+            args.push("--keep-within".to_string());
+            args.push(within.clone());
+        }
+
+        args
+    }
+}
+
 impl Schedule {
     #[allow(dead_code)]
     pub fn is_cron(&self) -> bool {
@@ -119,6 +396,28 @@ impl Schedule {
     pub fn is_interval(&self) -> bool {
         self.schedule_type == "interval"
     }
+
+    pub fn is_calendar(&self) -> bool {
+        self.schedule_type == "calendar"
+    }
+
+    pub fn same_definition(&self, other: &Schedule) -> bool {
+        self.job_id == other.job_id
+            && self.schedule_type == other.schedule_type
+            && self.cron_expression == other.cron_expression
+            && self.interval_seconds == other.interval_seconds
+            && self.enabled == other.enabled
+            && self.calendar == other.calendar
+            && self.timezone == other.timezone
+            && self.metadata == other.metadata
+    }
+
+    pub fn is_flexible(&self) -> bool {
+        self.calendar
+            .as_ref()
+            .map(|calendar| calendar.flexible)
+            .unwrap_or(true)
+    }
 }
 
 impl Run {
@@ -132,12 +431,41 @@ impl Run {
         self.status == "success"
     }
 
+    #[allow(dead_code)]
+    pub fn is_partial(&self) -> bool {
+        self.status == "partial"
+    }
+
     #[allow(dead_code)]
     pub fn is_failed(&self) -> bool {
         self.status == "failed"
     }
 }
 
+impl Repository {
+    pub fn environment_variables(&self) -> Vec<(String, String)> {
+        self.environment
+            .as_object()
+            .map(|variables| {
+                variables
+                    .iter()
+                    .map(|(key, value)| match value {
+                        serde_json::Value::String(value) => (key.clone(), value.clone()),
+                        other => (key.clone(), other.to_string()),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+impl Restore {
+    #[allow(dead_code)]
+    pub fn is_success(&self) -> bool {
+        self.status == "success"
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -163,6 +491,7 @@ mod tests {
             origin_name: Some("device1".to_string()),
             origin_id: None,
             account_id: Some(account_id),
+            retention: RetentionPolicy::default(),
         };
 
         let tags = job.get_restic_tags();
@@ -174,6 +503,42 @@ mod tests {
         assert_eq!(tags[3], format!("account_id={}", account_id));
     }
 
+    #[test]
+    fn test_retention_policy_keep_arguments() {
+        let policy = RetentionPolicy {
+            keep_last: Some(3),
+            keep_daily: Some(7),
+            keep_weekly: Some(0),
+            keep_within: Some("30d".to_string()),
+            ..Default::default()
+        };
+
+        assert!(!policy.is_empty());
+        assert_eq!(
+            policy.keep_arguments(),
+            vec![
+                "--keep-last",
+                "3",
+                "--keep-daily",
+                "7",
+                "--keep-within",
+                "30d"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_retention_policy_empty() {
+        let policy = RetentionPolicy {
+            keep_hourly: Some(0),
+            keep_within: Some(String::new()),
+            ..Default::default()
+        };
+
+        assert!(policy.is_empty());
+        assert!(RetentionPolicy::default().is_empty());
+    }
+
     #[test]
     fn test_schedule_type_checks() {
         let cron_schedule = Schedule {
@@ -188,6 +553,8 @@ mod tests {
             created_at: Utc::now(),
             updated_at: Utc::now(),
             metadata: serde_json::json!({}),
+            calendar: None,
+            timezone: None,
         };
 
         assert!(cron_schedule.is_cron());
@@ -221,10 +588,24 @@ mod tests {
             triggered_by: "schedule".to_string(),
             created_at: Utc::now(),
             metadata: serde_json::json!({}),
+            progress: None,
+            progress_updated_at: None,
+            cancel_requested_at: None,
+            cancel_requested_by: None,
+            cancel_reason: None,
+            failure_reason: None,
+            attempt: 1,
+            retry_of: None,
+            failed_paths: None,
+            repository_id: None,
+            journal_id: None,
+            owner_host: None,
+            owner_pid: None,
         };
 
         assert!(run.is_running());
         assert!(!run.is_success());
+        assert!(!run.is_partial());
         assert!(!run.is_failed());
     }
 }
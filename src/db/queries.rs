@@ -1,26 +1,41 @@
-use crate::db::models::{BackupJob, Device, Run, Schedule, Setting};
+use crate::db::models::{
+    BackendCredentials, BackupHook, BackupJob, CalendarSchedule, Device, HookExecution,
+    JobRepository, MaintenanceRun, QueuedJob, Repository, Restore, Run, RunFilter, RunOwner,
+    Schedule, Setting,
+};
 use crate::error::{DatabaseError, Result};
 use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
-use sqlx::{ConnectOptions, PgPool};
+use sqlx::types::Json;
+use sqlx::{ConnectOptions, PgExecutor, PgPool};
 use std::time::Duration;
 use tracing::log::LevelFilter;
 use uuid::Uuid;
 
 pub async fn create_pool(connection_string: String) -> Result<PgPool> {
-    let mut connect_options: PgConnectOptions = connection_string
+    let pool = pool_options()
+        .connect_with(connect_options(&connection_string)?)
+        .await
+        .map_err(DatabaseError::ConnectionFailed)?;
+
+    Ok(pool)
+}
+
+pub fn create_lazy_pool(connection_string: String) -> Result<PgPool> {
+    Ok(pool_options().connect_lazy_with(connect_options(&connection_string)?))
+}
+
+fn connect_options(connection_string: &str) -> Result<PgConnectOptions> {
+    let connect_options: PgConnectOptions = connection_string
         .parse()
         .map_err(|e| DatabaseError::ConnectionFailed(sqlx::Error::Configuration(Box::new(e))))?;
 
-    connect_options = connect_options.log_statements(LevelFilter::Debug);
+    Ok(connect_options.log_statements(LevelFilter::Debug))
+}
 
-    let pool = PgPoolOptions::new()
+fn pool_options() -> PgPoolOptions {
+    PgPoolOptions::new()
         .max_connections(5)
         .acquire_timeout(Duration::from_secs(10))
-        .connect_with(connect_options)
-        .await
-        .map_err(DatabaseError::ConnectionFailed)?;
-
-    Ok(pool)
 }
 
 pub async fn run_migrations(pool: &PgPool) -> Result<()> {
@@ -69,6 +84,48 @@ pub async fn upsert_device(
     Ok(device)
 }
 
+pub async fn register_device(pool: &PgPool, device_id: String) -> Result<Device> {
+    let platform = if cfg!(target_os = "windows") {
+        "windows"
+    } else if cfg!(target_os = "linux") {
+        "linux"
+    } else {
+        "unknown"
+    };
+
+    let hostname = hostname::get().ok().and_then(|h| h.into_string().ok());
+
+    upsert_device(
+        pool,
+        device_id.clone(),
+        device_id,
+        platform.to_string(),
+        hostname,
+    )
+    .await
+}
+
+pub async fn create_device_if_missing(
+    executor: impl PgExecutor<'_>,
+    device_id: String,
+    name: String,
+    platform: String,
+) -> Result<bool> {
+    let result = sqlx::query(
+        r#"
+        INSERT INTO devices (id, name, platform, enabled)
+        VALUES ($1, $2, $3, true)
+        ON CONFLICT (id) DO NOTHING
+        "#,
+    )
+    .bind(device_id)
+    .bind(name)
+    .bind(platform)
+    .execute(executor)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
 #[allow(dead_code)]
 pub async fn update_device_heartbeat(
     pool: &PgPool,
@@ -113,6 +170,50 @@ pub async fn get_job_by_id(pool: &PgPool, job_id: Uuid) -> Result<Option<BackupJ
     Ok(job)
 }
 
+// Allow many arguments: this function mirrors the database schema columns for imported jobs
+#[allow(clippy::too_many_arguments)]
+pub async fn upsert_imported_job(
+    executor: impl PgExecutor<'_>,
+    job_id: Uuid,
+    device_id: String,
+    name: String,
+    source_paths: Vec<String>,
+    exclude_patterns: Option<Vec<String>>,
+    origin_name: Option<String>,
+    origin_id: Option<Uuid>,
+    account_id: Option<Uuid>,
+) -> Result<BackupJob> {
+    let job = sqlx::query_as::<_, BackupJob>(
+        r#"
+        INSERT INTO backup_jobs (
+            id, device_id, name, source_paths, exclude_patterns, origin_name, origin_id, account_id
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        ON CONFLICT (id) DO UPDATE
+        SET device_id = EXCLUDED.device_id,
+            name = EXCLUDED.name,
+            source_paths = EXCLUDED.source_paths,
+            exclude_patterns = EXCLUDED.exclude_patterns,
+            origin_name = EXCLUDED.origin_name,
+            origin_id = EXCLUDED.origin_id,
+            account_id = EXCLUDED.account_id,
+            updated_at = NOW()
+        RETURNING *
+        "#,
+    )
+    .bind(job_id)
+    .bind(device_id)
+    .bind(name)
+    .bind(source_paths)
+    .bind(exclude_patterns)
+    .bind(origin_name)
+    .bind(origin_id)
+    .bind(account_id)
+    .fetch_one(executor)
+    .await?;
+    Ok(job)
+}
+
 pub async fn get_schedules_for_device(pool: &PgPool, device_id: String) -> Result<Vec<Schedule>> {
     let schedules = sqlx::query_as::<_, Schedule>(
         r#"
@@ -130,6 +231,197 @@ pub async fn get_schedules_for_device(pool: &PgPool, device_id: String) -> Resul
     Ok(schedules)
 }
 
+pub async fn get_schedules_for_job(pool: &PgPool, job_id: Uuid) -> Result<Vec<Schedule>> {
+    let schedules =
+        sqlx::query_as::<_, Schedule>("SELECT * FROM schedules WHERE job_id = $1 ORDER BY id")
+            .bind(job_id)
+            .fetch_all(pool)
+            .await?;
+    Ok(schedules)
+}
+
+// Allow many arguments: this function mirrors the database schema columns for schedules
+#[allow(clippy::too_many_arguments)]
+pub async fn create_schedule(
+    executor: impl PgExecutor<'_>,
+    job_id: Uuid,
+    schedule_type: String,
+    cron_expression: Option<String>,
+    interval_seconds: Option<i32>,
+    calendar: Option<Json<CalendarSchedule>>,
+    enabled: bool,
+    last_run_at: Option<chrono::DateTime<chrono::Utc>>,
+    metadata: serde_json::Value,
+) -> Result<i32> {
+    let schedule_id: (i32,) = sqlx::query_as(
+        r#"
+        INSERT INTO schedules (
+            job_id, schedule_type, cron_expression, interval_seconds, calendar, enabled,
+            last_run_at, metadata
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        RETURNING id
+        "#,
+    )
+    .bind(job_id)
+    .bind(schedule_type)
+    .bind(cron_expression)
+    .bind(interval_seconds)
+    .bind(calendar)
+    .bind(enabled)
+    .bind(last_run_at)
+    .bind(metadata)
+    .fetch_one(executor)
+    .await?;
+    Ok(schedule_id.0)
+}
+
+pub async fn delete_imported_schedules(
+    executor: impl PgExecutor<'_>,
+    job_id: Uuid,
+    source: String,
+) -> Result<u64> {
+    let result = sqlx::query(
+        r#"
+        DELETE FROM schedules
+        WHERE job_id = $1 AND metadata->>'imported_from' = $2
+        "#,
+    )
+    .bind(job_id)
+    .bind(source)
+    .execute(executor)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+pub async fn get_hooks_for_device(pool: &PgPool, device_id: String) -> Result<Vec<BackupHook>> {
+    let hooks = sqlx::query_as::<_, BackupHook>(
+        r#"
+        SELECT h.*
+        FROM backup_hooks h
+        JOIN backup_jobs j ON h.job_id = j.id
+        WHERE j.device_id = $1
+          AND j.enabled = true
+          AND h.enabled = true
+        ORDER BY h.job_id, h.timing, h.sort_order, h.created_at
+        "#,
+    )
+    .bind(device_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(hooks)
+}
+
+pub async fn get_repositories_for_device(
+    pool: &PgPool,
+    device_id: String,
+) -> Result<Vec<Repository>> {
+    let repositories = sqlx::query_as::<_, Repository>(
+        r#"
+        SELECT DISTINCT r.*
+        FROM repositories r
+        JOIN job_repositories jr ON jr.repository_id = r.id
+        JOIN backup_jobs j ON jr.job_id = j.id
+        WHERE j.device_id = $1
+          AND r.enabled = true
+        ORDER BY r.name
+        "#,
+    )
+    .bind(device_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(repositories)
+}
+
+pub async fn get_job_repositories_for_device(
+    pool: &PgPool,
+    device_id: String,
+) -> Result<Vec<JobRepository>> {
+    let job_repositories = sqlx::query_as::<_, JobRepository>(
+        r#"
+        SELECT jr.*
+        FROM job_repositories jr
+        JOIN backup_jobs j ON jr.job_id = j.id
+        WHERE j.device_id = $1
+        ORDER BY jr.job_id, jr.created_at
+        "#,
+    )
+    .bind(device_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(job_repositories)
+}
+
+#[allow(dead_code)]
+pub async fn create_repository(
+    pool: &PgPool,
+    name: String,
+    url: String,
+    password: String,
+    cache_dir: Option<String>,
+    environment: serde_json::Value,
+    credentials: Option<BackendCredentials>,
+) -> Result<Repository> {
+    let repository = sqlx::query_as::<_, Repository>(
+        r#"
+        INSERT INTO repositories (name, url, password, cache_dir, environment, credentials)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING *
+        "#,
+    )
+    .bind(name)
+    .bind(url)
+    .bind(password)
+    .bind(cache_dir)
+    .bind(environment)
+    .bind(credentials.map(Json))
+    .fetch_one(pool)
+    .await?;
+    Ok(repository)
+}
+
+#[allow(dead_code)]
+pub async fn add_job_repository(pool: &PgPool, job_id: Uuid, repository_id: Uuid) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO job_repositories (job_id, repository_id)
+        VALUES ($1, $2)
+        ON CONFLICT (job_id, repository_id) DO NOTHING
+        "#,
+    )
+    .bind(job_id)
+    .bind(repository_id)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn record_repository_success(
+    pool: &PgPool,
+    job_id: Uuid,
+    repository_id: Uuid,
+    run_id: i32,
+    snapshot_id: String,
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        UPDATE job_repositories
+        SET last_success_at  = NOW(),
+            last_run_id      = $3,
+            last_snapshot_id = $4
+        WHERE job_id = $1
+          AND repository_id = $2
+        "#,
+    )
+    .bind(job_id)
+    .bind(repository_id)
+    .bind(run_id)
+    .bind(snapshot_id)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
 #[allow(dead_code)]
 pub async fn update_schedule_last_run(
     pool: &PgPool,
@@ -154,122 +446,1078 @@ pub async fn update_schedule_last_run(
     Ok(())
 }
 
-#[allow(dead_code)]
-pub async fn create_run(
+pub async fn update_schedule_next_run(
+    pool: &PgPool,
+    schedule_id: i32,
+    next_run_at: chrono::DateTime<chrono::Utc>,
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        UPDATE schedules
+        SET next_run_at = $2,
+            updated_at = NOW()
+        WHERE id = $1
+        "#,
+    )
+    .bind(schedule_id)
+    .bind(next_run_at)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+// A pending entry of the job keeps its place and takes over a higher priority
+pub async fn enqueue_job(
     pool: &PgPool,
     job_id: Uuid,
     device_id: String,
     triggered_by: String,
-) -> Result<i32> {
-    let run_id: (i32,) = sqlx::query_as(
+    priority: i32,
+) -> Result<QueuedJob> {
+    let entry = sqlx::query_as::<_, QueuedJob>(
         r#"
-        INSERT INTO runs (job_id, device_id, start_time, status, triggered_by)
-        VALUES ($1, $2, NOW(), 'running', $3)
-        RETURNING id
+        INSERT INTO job_queue (job_id, device_id, triggered_by, priority)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (job_id) WHERE status = 'pending' DO UPDATE
+        SET triggered_by = CASE
+                WHEN EXCLUDED.priority > job_queue.priority THEN EXCLUDED.triggered_by
+                ELSE job_queue.triggered_by
+            END,
+            priority = GREATEST(job_queue.priority, EXCLUDED.priority)
+        RETURNING *
         "#,
     )
     .bind(job_id)
     .bind(device_id)
     .bind(triggered_by)
+    .bind(priority)
     .fetch_one(pool)
     .await?;
-    Ok(run_id.0)
+    Ok(entry)
 }
 
-// Allow many arguments: this function mirrors the database schema columns for run updates
-#[allow(clippy::too_many_arguments)]
-#[allow(dead_code)]
-pub async fn update_run(
-    pool: &PgPool,
-    run_id: i32,
-    end_time: chrono::DateTime<chrono::Utc>,
-    status: String,
-    exit_code: Option<i32>,
-    error_message: Option<String>,
-    files_new: Option<i32>,
-    files_changed: Option<i32>,
-    files_unmodified: Option<i32>,
-    data_added_bytes: Option<i64>,
-    snapshot_id: Option<String>,
-    restic_output: Option<String>,
-    restic_errors: Option<String>,
-) -> Result<()> {
+// Entries of running jobs wait until the running entry is removed
+pub async fn claim_next_queued_job(pool: &PgPool, device_id: String) -> Result<Option<QueuedJob>> {
+    let entry = sqlx::query_as::<_, QueuedJob>(
+        r#"
+        UPDATE job_queue
+        SET status = 'running',
+            started_at = NOW()
+        WHERE id = (
+            SELECT q.id FROM job_queue q
+            WHERE q.device_id = $1
+              AND q.status = 'pending'
+              AND NOT EXISTS (
+                  SELECT 1 FROM job_queue r
+                  WHERE r.job_id = q.job_id AND r.status = 'running'
+              )
+            ORDER BY q.priority DESC, q.enqueued_at, q.id
+            LIMIT 1
+            FOR UPDATE SKIP LOCKED
+        )
+        RETURNING *
+        "#,
+    )
+    .bind(device_id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(entry)
+}
+
+pub async fn delete_queued_job(pool: &PgPool, id: i64) -> Result<()> {
     sqlx::query(
         r#"
-        UPDATE runs
-        SET end_time = $2,
-            status = $3,
-            exit_code = $4,
-            error_message = $5,
-            files_new = $6,
-            files_changed = $7,
-            files_unmodified = $8,
-            data_added_bytes = $9,
-            snapshot_id = $10,
-            restic_output = $11,
-            restic_errors = $12,
-            duration_seconds = EXTRACT(EPOCH FROM ($2 - start_time))::INTEGER
+        DELETE FROM job_queue
         WHERE id = $1
         "#,
     )
-    .bind(run_id)
-    .bind(end_time)
-    .bind(status)
-    .bind(exit_code)
-    .bind(error_message)
-    .bind(files_new)
-    .bind(files_changed)
-    .bind(files_unmodified)
-    .bind(data_added_bytes)
-    .bind(snapshot_id)
-    .bind(restic_output)
-    .bind(restic_errors)
+    .bind(id)
     .execute(pool)
     .await?;
     Ok(())
 }
 
-#[allow(dead_code)]
-pub async fn get_recent_runs(pool: &PgPool, device_id: String, limit: i64) -> Result<Vec<Run>> {
-    let runs = sqlx::query_as::<_, Run>(
+pub async fn requeue_queued_job(pool: &PgPool, id: i64) -> Result<()> {
+    let mut tx = pool.begin().await?;
+
+    let merged = sqlx::query(
         r#"
-        SELECT * FROM runs
-        WHERE device_id = $1
-        ORDER BY start_time DESC
-        LIMIT $2
+        UPDATE job_queue p
+        SET triggered_by = CASE WHEN r.priority > p.priority THEN r.triggered_by ELSE p.triggered_by END,
+            priority = GREATEST(p.priority, r.priority)
+        FROM job_queue r
+        WHERE r.id = $1 AND p.job_id = r.job_id AND p.status = 'pending'
         "#,
     )
-    .bind(device_id)
-    .bind(limit)
-    .fetch_all(pool)
+    .bind(id)
+    .execute(&mut *tx)
     .await?;
-    Ok(runs)
+
+    let query = if merged.rows_affected() > 0 {
+        "DELETE FROM job_queue WHERE id = $1"
+    } else {
+        "UPDATE job_queue SET status = 'pending', started_at = NULL WHERE id = $1"
+    };
+    sqlx::query(query).bind(id).execute(&mut *tx).await?;
+
+    tx.commit().await?;
+    Ok(())
 }
 
-pub async fn get_settings_for_device(pool: &PgPool, device_id: String) -> Result<Vec<Setting>> {
-    let settings = sqlx::query_as::<_, Setting>(
+pub async fn get_job_queue(pool: &PgPool, device_id: String) -> Result<Vec<QueuedJob>> {
+    let entries = sqlx::query_as::<_, QueuedJob>(
         r#"
-        SELECT * FROM settings
-        WHERE device_id IS NULL OR device_id = $1
-        ORDER BY device_id NULLS FIRST
+        SELECT * FROM job_queue
+        WHERE device_id = $1
+        ORDER BY status DESC, priority DESC, enqueued_at, id
         "#,
     )
     .bind(device_id)
     .fetch_all(pool)
     .await?;
-    Ok(settings)
+    Ok(entries)
 }
 
-#[allow(dead_code)]
-pub async fn get_global_setting(pool: &PgPool, key: String) -> Result<Option<String>> {
-    let setting: Option<(String,)> = sqlx::query_as(
+pub async fn requeue_interrupted_jobs(pool: &PgPool, device_id: String) -> Result<u64> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query(
         r#"
-        SELECT value FROM settings
-        WHERE device_id IS NULL AND key = $1
+        DELETE FROM job_queue r
+        WHERE r.device_id = $1
+          AND r.status = 'running'
+          AND EXISTS (
+              SELECT 1 FROM job_queue p
+              WHERE p.job_id = r.job_id AND p.status = 'pending'
+          )
         "#,
     )
-    .bind(key)
-    .fetch_optional(pool)
+    .bind(device_id.clone())
+    .execute(&mut *tx)
     .await?;
-    Ok(setting.map(|s| s.0))
+
+    let result = sqlx::query(
+        r#"
+        UPDATE job_queue
+        SET status = 'pending',
+            started_at = NULL
+        WHERE device_id = $1 AND status = 'running'
+        "#,
+    )
+    .bind(device_id)
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    Ok(result.rows_affected())
+}
+
+#[allow(dead_code)]
+pub async fn create_run(
+    pool: &PgPool,
+    job_id: Uuid,
+    device_id: String,
+    repository_id: Option<Uuid>,
+    triggered_by: String,
+) -> Result<i32> {
+    let owner = RunOwner::current();
+    let run_id: (i32,) = sqlx::query_as(
+        r#"
+        INSERT INTO runs (job_id, device_id, repository_id, start_time, status, triggered_by, owner_host, owner_pid)
+        VALUES ($1, $2, $3, NOW(), 'running', $4, $5, $6)
+        RETURNING id
+        "#,
+    )
+    .bind(job_id)
+    .bind(device_id)
+    .bind(repository_id)
+    .bind(triggered_by)
+    .bind(&owner.host)
+    .bind(owner.pid)
+    .fetch_one(pool)
+    .await?;
+    Ok(run_id.0)
+}
+
+pub async fn create_retry_run(
+    pool: &PgPool,
+    job_id: Uuid,
+    device_id: String,
+    repository_id: Option<Uuid>,
+    triggered_by: String,
+    retry_of: i32,
+    attempt: i32,
+) -> Result<i32> {
+    let owner = RunOwner::current();
+    let run_id: (i32,) = sqlx::query_as(
+        r#"
+        INSERT INTO runs (job_id, device_id, repository_id, start_time, status, triggered_by, retry_of, attempt, owner_host, owner_pid)
+        VALUES ($1, $2, $3, NOW(), 'running', $4, $5, $6, $7, $8)
+        RETURNING id
+        "#,
+    )
+    .bind(job_id)
+    .bind(device_id)
+    .bind(repository_id)
+    .bind(triggered_by)
+    .bind(retry_of)
+    .bind(attempt)
+    .bind(&owner.host)
+    .bind(owner.pid)
+    .fetch_one(pool)
+    .await?;
+    Ok(run_id.0)
+}
+
+// Allow many arguments: this function mirrors the database schema columns for new runs
+#[allow(clippy::too_many_arguments)]
+pub async fn insert_journaled_run(
+    pool: &PgPool,
+    journal_id: Uuid,
+    job_id: Uuid,
+    device_id: String,
+    repository_id: Option<Uuid>,
+    triggered_by: String,
+    start_time: chrono::DateTime<chrono::Utc>,
+    retry_of: Option<i32>,
+    attempt: i32,
+    owner: Option<&RunOwner>,
+) -> Result<i32> {
+    let run_id: (i32,) = sqlx::query_as(
+        r#"
+        INSERT INTO runs (journal_id, job_id, device_id, repository_id, start_time, status, triggered_by, retry_of, attempt, owner_host, owner_pid)
+        VALUES ($1, $2, $3, $4, $5, 'running', $6, $7, $8, $9, $10)
+        ON CONFLICT (journal_id) DO UPDATE SET journal_id = EXCLUDED.journal_id
+        RETURNING id
+        "#,
+    )
+    .bind(journal_id)
+    .bind(job_id)
+    .bind(device_id)
+    .bind(repository_id)
+    .bind(start_time)
+    .bind(triggered_by)
+    .bind(retry_of)
+    .bind(attempt)
+    .bind(owner.map(|owner| owner.host.clone()))
+    .bind(owner.map(|owner| owner.pid))
+    .fetch_one(pool)
+    .await?;
+    Ok(run_id.0)
+}
+
+pub async fn get_run_id_by_journal_id(pool: &PgPool, journal_id: Uuid) -> Result<Option<i32>> {
+    let run_id: Option<(i32,)> = sqlx::query_as("SELECT id FROM runs WHERE journal_id = $1")
+        .bind(journal_id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(run_id.map(|(id,)| id))
+}
+
+// Allow many arguments: this function mirrors the database schema columns for run updates
+#[allow(clippy::too_many_arguments)]
+#[allow(dead_code)]
+pub async fn update_run(
+    pool: &PgPool,
+    run_id: i32,
+    end_time: chrono::DateTime<chrono::Utc>,
+    status: String,
+    exit_code: Option<i32>,
+    error_message: Option<String>,
+    files_new: Option<i32>,
+    files_changed: Option<i32>,
+    files_unmodified: Option<i32>,
+    data_added_bytes: Option<i64>,
+    snapshot_id: Option<String>,
+    restic_output: Option<String>,
+    restic_errors: Option<String>,
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        UPDATE runs
+        SET end_time = $2,
+            status = $3,
+            exit_code = $4,
+            error_message = $5,
+            files_new = $6,
+            files_changed = $7,
+            files_unmodified = $8,
+            data_added_bytes = $9,
+            snapshot_id = $10,
+            restic_output = $11,
+            restic_errors = $12,
+            duration_seconds = EXTRACT(EPOCH FROM ($2 - start_time))::INTEGER
+        WHERE id = $1
+        "#,
+    )
+    .bind(run_id)
+    .bind(end_time)
+    .bind(status)
+    .bind(exit_code)
+    .bind(error_message)
+    .bind(files_new)
+    .bind(files_changed)
+    .bind(files_unmodified)
+    .bind(data_added_bytes)
+    .bind(snapshot_id)
+    .bind(restic_output)
+    .bind(restic_errors)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+#[allow(dead_code)]
+pub async fn update_run_progress(
+    pool: &PgPool,
+    run_id: i32,
+    progress: serde_json::Value,
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        UPDATE runs
+        SET progress = $2,
+            progress_updated_at = NOW()
+        WHERE id = $1 AND status = 'running'
+        "#,
+    )
+    .bind(run_id)
+    .bind(progress)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+#[allow(dead_code)]
+pub async fn set_run_failure_reason(
+    pool: &PgPool,
+    run_id: i32,
+    failure_reason: String,
+) -> Result<()> {
+    sqlx::query("UPDATE runs SET failure_reason = $2 WHERE id = $1")
+        .bind(run_id)
+        .bind(failure_reason)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+pub async fn set_run_failed_paths(
+    pool: &PgPool,
+    run_id: i32,
+    failed_paths: serde_json::Value,
+) -> Result<()> {
+    sqlx::query("UPDATE runs SET failed_paths = $2 WHERE id = $1")
+        .bind(run_id)
+        .bind(failed_paths)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+// The first request is kept
+pub async fn request_run_cancellation(
+    pool: &PgPool,
+    run_id: i32,
+    device_id: String,
+    requested_by: String,
+    reason: String,
+) -> Result<bool> {
+    let updated: Option<(i32,)> = sqlx::query_as(
+        r#"
+        UPDATE runs
+        SET cancel_requested_at = COALESCE(cancel_requested_at, NOW()),
+            cancel_requested_by = COALESCE(cancel_requested_by, $3),
+            cancel_reason = COALESCE(cancel_reason, $4)
+        WHERE id = $1 AND device_id = $2 AND status = 'running'
+        RETURNING id
+        "#,
+    )
+    .bind(run_id)
+    .bind(device_id)
+    .bind(requested_by)
+    .bind(reason)
+    .fetch_optional(pool)
+    .await?;
+    Ok(updated.is_some())
+}
+
+pub async fn get_running_runs(pool: &PgPool, device_id: String) -> Result<Vec<Run>> {
+    let runs = sqlx::query_as::<_, Run>(
+        "SELECT * FROM runs WHERE device_id = $1 AND status = 'running' ORDER BY id",
+    )
+    .bind(device_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(runs)
+}
+
+// The last progress is taken as the end of the run
+pub async fn abandon_runs(
+    pool: &PgPool,
+    run_ids: Vec<i32>,
+    error_message: String,
+) -> Result<Vec<Run>> {
+    let runs = sqlx::query_as::<_, Run>(
+        r#"
+        UPDATE runs
+        SET status = 'failed',
+            end_time = COALESCE(progress_updated_at, start_time),
+            error_message = $2,
+            failure_reason = 'abandoned',
+            duration_seconds = EXTRACT(EPOCH FROM (COALESCE(progress_updated_at, start_time) - start_time))::INTEGER
+        WHERE id = ANY($1) AND status = 'running'
+        RETURNING *
+        "#,
+    )
+    .bind(run_ids)
+    .bind(error_message)
+    .fetch_all(pool)
+    .await?;
+    Ok(runs)
+}
+
+#[allow(dead_code)]
+pub async fn get_requested_cancellations(pool: &PgPool, run_ids: Vec<i32>) -> Result<Vec<Run>> {
+    let runs = sqlx::query_as::<_, Run>(
+        r#"
+        SELECT * FROM runs
+        WHERE id = ANY($1) AND status = 'running' AND cancel_requested_at IS NOT NULL
+        "#,
+    )
+    .bind(run_ids)
+    .fetch_all(pool)
+    .await?;
+    Ok(runs)
+}
+
+// Allow many arguments: this function mirrors the database schema columns for cancelled runs
+#[allow(clippy::too_many_arguments)]
+#[allow(dead_code)]
+pub async fn update_run_cancelled(
+    pool: &PgPool,
+    run_id: i32,
+    end_time: chrono::DateTime<chrono::Utc>,
+    exit_code: Option<i32>,
+    requested_by: String,
+    reason: String,
+    restic_output: Option<String>,
+    restic_errors: Option<String>,
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        UPDATE runs
+        SET end_time = $2,
+            status = 'cancelled',
+            exit_code = $3,
+            error_message = 'Cancelled by ' || $4 || ': ' || $5,
+            cancel_requested_at = COALESCE(cancel_requested_at, $2),
+            cancel_requested_by = $4,
+            cancel_reason = $5,
+            restic_output = $6,
+            restic_errors = $7,
+            duration_seconds = EXTRACT(EPOCH FROM ($2 - start_time))::INTEGER
+        WHERE id = $1
+        "#,
+    )
+    .bind(run_id)
+    .bind(end_time)
+    .bind(exit_code)
+    .bind(requested_by)
+    .bind(reason)
+    .bind(restic_output)
+    .bind(restic_errors)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn get_hooks_for_job(pool: &PgPool, job_id: Uuid) -> Result<Vec<BackupHook>> {
+    let hooks = sqlx::query_as::<_, BackupHook>(
+        "SELECT * FROM backup_hooks WHERE job_id = $1 ORDER BY timing, sort_order, created_at",
+    )
+    .bind(job_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(hooks)
+}
+
+// Allow many arguments: this function mirrors the database schema columns for imported hooks
+#[allow(clippy::too_many_arguments)]
+pub async fn upsert_imported_hook(
+    executor: impl PgExecutor<'_>,
+    hook_id: Uuid,
+    job_id: Uuid,
+    description: Option<String>,
+    command: String,
+    timing: String,
+    on_error: String,
+    sort_order: i32,
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO backup_hooks (id, job_id, description, command, timing, on_error, sort_order)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        ON CONFLICT (id) DO UPDATE
+        SET job_id = EXCLUDED.job_id,
+            description = EXCLUDED.description,
+            command = EXCLUDED.command,
+            timing = EXCLUDED.timing,
+            on_error = EXCLUDED.on_error,
+            sort_order = EXCLUDED.sort_order,
+            updated_at = NOW()
+        "#,
+    )
+    .bind(hook_id)
+    .bind(job_id)
+    .bind(description)
+    .bind(command)
+    .bind(timing)
+    .bind(on_error)
+    .bind(sort_order)
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+pub async fn get_run(pool: &PgPool, run_id: i32) -> Result<Option<Run>> {
+    let run = sqlx::query_as::<_, Run>("SELECT * FROM runs WHERE id = $1")
+        .bind(run_id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(run)
+}
+
+#[allow(dead_code)]
+pub async fn get_recent_runs(pool: &PgPool, device_id: String, limit: i64) -> Result<Vec<Run>> {
+    let runs = sqlx::query_as::<_, Run>(
+        r#"
+        SELECT * FROM runs
+        WHERE device_id = $1
+        ORDER BY start_time DESC
+        LIMIT $2
+        "#,
+    )
+    .bind(device_id)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+    Ok(runs)
+}
+
+pub async fn get_latest_runs(pool: &PgPool, device_id: String) -> Result<Vec<Run>> {
+    let runs = sqlx::query_as::<_, Run>(
+        r#"
+        SELECT DISTINCT ON (job_id) * FROM runs
+        WHERE device_id = $1
+        ORDER BY job_id, start_time DESC, id DESC
+        "#,
+    )
+    .bind(device_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(runs)
+}
+
+pub async fn list_runs(
+    pool: &PgPool,
+    device_id: String,
+    filter: &RunFilter,
+    limit: i64,
+    offset: i64,
+) -> Result<(Vec<Run>, i64)> {
+    const FILTER: &str = r#"
+        WHERE device_id = $1
+          AND ($2::uuid IS NULL OR job_id = $2)
+          AND ($3::text IS NULL OR status = $3)
+          AND ($4::uuid IS NULL OR repository_id = $4)
+          AND ($5::text IS NULL OR triggered_by = $5)
+          AND ($6::timestamptz IS NULL OR start_time >= $6)
+          AND ($7::timestamptz IS NULL OR start_time < $7)
+    "#;
+
+    let (total,): (i64,) = sqlx::query_as(&format!("SELECT COUNT(*) FROM runs {}", FILTER))
+        .bind(&device_id)
+        .bind(filter.job_id)
+        .bind(&filter.status)
+        .bind(filter.repository_id)
+        .bind(&filter.triggered_by)
+        .bind(filter.since)
+        .bind(filter.until)
+        .fetch_one(pool)
+        .await?;
+
+    let runs = sqlx::query_as::<_, Run>(&format!(
+        "SELECT * FROM runs {} ORDER BY start_time DESC, id DESC LIMIT $8 OFFSET $9",
+        FILTER
+    ))
+    .bind(&device_id)
+    .bind(filter.job_id)
+    .bind(&filter.status)
+    .bind(filter.repository_id)
+    .bind(&filter.triggered_by)
+    .bind(filter.since)
+    .bind(filter.until)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(pool)
+    .await?;
+
+    Ok((runs, total))
+}
+
+// Allow many arguments: this function mirrors the database schema columns for hook executions
+#[allow(clippy::too_many_arguments)]
+pub async fn create_hook_execution(
+    pool: &PgPool,
+    run_id: i32,
+    hook_id: Option<Uuid>,
+    timing: String,
+    command: String,
+    start_time: chrono::DateTime<chrono::Utc>,
+    end_time: chrono::DateTime<chrono::Utc>,
+    status: String,
+    exit_code: Option<i32>,
+    error_message: Option<String>,
+    stdout: Option<String>,
+    stderr: Option<String>,
+) -> Result<i32> {
+    let hook_execution_id: (i32,) = sqlx::query_as(
+        r#"
+        INSERT INTO hook_executions (
+            run_id, hook_id, timing, command, start_time, end_time, status, exit_code,
+            error_message, stdout, stderr, duration_seconds
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, EXTRACT(EPOCH FROM ($6 - $5))::INTEGER)
+        RETURNING id
+        "#,
+    )
+    .bind(run_id)
+    .bind(hook_id)
+    .bind(timing)
+    .bind(command)
+    .bind(start_time)
+    .bind(end_time)
+    .bind(status)
+    .bind(exit_code)
+    .bind(error_message)
+    .bind(stdout)
+    .bind(stderr)
+    .fetch_one(pool)
+    .await?;
+    Ok(hook_execution_id.0)
+}
+
+// Allow many arguments: this function mirrors the database schema columns for hook executions
+#[allow(clippy::too_many_arguments)]
+pub async fn create_journaled_hook_execution(
+    pool: &PgPool,
+    journal_id: Uuid,
+    run_id: i32,
+    hook_id: Option<Uuid>,
+    timing: String,
+    command: String,
+    start_time: chrono::DateTime<chrono::Utc>,
+    end_time: chrono::DateTime<chrono::Utc>,
+    status: String,
+    exit_code: Option<i32>,
+    error_message: Option<String>,
+    stdout: Option<String>,
+    stderr: Option<String>,
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO hook_executions (
+            journal_id, run_id, hook_id, timing, command, start_time, end_time, status, exit_code,
+            error_message, stdout, stderr, duration_seconds
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, EXTRACT(EPOCH FROM ($7 - $6))::INTEGER)
+        ON CONFLICT (journal_id) DO NOTHING
+        "#,
+    )
+    .bind(journal_id)
+    .bind(run_id)
+    .bind(hook_id)
+    .bind(timing)
+    .bind(command)
+    .bind(start_time)
+    .bind(end_time)
+    .bind(status)
+    .bind(exit_code)
+    .bind(error_message)
+    .bind(stdout)
+    .bind(stderr)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+#[allow(dead_code)]
+pub async fn get_hook_executions(pool: &PgPool, run_id: i32) -> Result<Vec<HookExecution>> {
+    let executions = sqlx::query_as::<_, HookExecution>(
+        "SELECT * FROM hook_executions WHERE run_id = $1 ORDER BY start_time, id",
+    )
+    .bind(run_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(executions)
+}
+
+// Allow many arguments: this function mirrors the database schema columns for restore records
+#[allow(clippy::too_many_arguments)]
+#[allow(dead_code)]
+pub async fn create_restore(
+    pool: &PgPool,
+    job_id: Uuid,
+    device_id: String,
+    repository_id: Option<Uuid>,
+    target_path: String,
+    include_patterns: Option<Vec<String>>,
+    exclude_patterns: Option<Vec<String>>,
+    overwrite_policy: String,
+    triggered_by: String,
+) -> Result<i32> {
+    let restore_id: (i32,) = sqlx::query_as(
+        r#"
+        INSERT INTO restores (job_id, device_id, repository_id, target_path, include_patterns,
+                              exclude_patterns, overwrite_policy, start_time, status, triggered_by)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, NOW(), 'running', $8)
+        RETURNING id
+        "#,
+    )
+    .bind(job_id)
+    .bind(device_id)
+    .bind(repository_id)
+    .bind(target_path)
+    .bind(include_patterns)
+    .bind(exclude_patterns)
+    .bind(overwrite_policy)
+    .bind(triggered_by)
+    .fetch_one(pool)
+    .await?;
+    Ok(restore_id.0)
+}
+
+// Allow many arguments: this function mirrors the database schema columns for restore updates
+#[allow(clippy::too_many_arguments)]
+#[allow(dead_code)]
+pub async fn update_restore(
+    pool: &PgPool,
+    restore_id: i32,
+    end_time: chrono::DateTime<chrono::Utc>,
+    status: String,
+    exit_code: Option<i32>,
+    error_message: Option<String>,
+    snapshot_id: Option<String>,
+    files_restored: Option<i32>,
+    files_skipped: Option<i32>,
+    bytes_restored: Option<i64>,
+    restic_output: Option<String>,
+    restic_errors: Option<String>,
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        UPDATE restores
+        SET end_time = $2,
+            status = $3,
+            exit_code = $4,
+            error_message = $5,
+            snapshot_id = COALESCE($6, snapshot_id),
+            files_restored = $7,
+            files_skipped = $8,
+            bytes_restored = $9,
+            restic_output = $10,
+            restic_errors = $11,
+            duration_seconds = EXTRACT(EPOCH FROM ($2 - start_time))::INTEGER
+        WHERE id = $1
+        "#,
+    )
+    .bind(restore_id)
+    .bind(end_time)
+    .bind(status)
+    .bind(exit_code)
+    .bind(error_message)
+    .bind(snapshot_id)
+    .bind(files_restored)
+    .bind(files_skipped)
+    .bind(bytes_restored)
+    .bind(restic_output)
+    .bind(restic_errors)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+#[allow(dead_code)]
+pub async fn get_restore(pool: &PgPool, restore_id: i32) -> Result<Option<Restore>> {
+    let restore = sqlx::query_as::<_, Restore>("SELECT * FROM restores WHERE id = $1")
+        .bind(restore_id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(restore)
+}
+
+#[allow(dead_code)]
+// Allow many arguments: this function mirrors the database schema columns of maintenance runs
+#[allow(clippy::too_many_arguments)]
+pub async fn create_maintenance_run(
+    pool: &PgPool,
+    device_id: String,
+    repository_id: Option<Uuid>,
+    job_id: Option<Uuid>,
+    run_id: Option<i32>,
+    task_type: String,
+    read_data_subset: Option<String>,
+    triggered_by: String,
+) -> Result<i32> {
+    let owner = RunOwner::current();
+    let maintenance_run_id: (i32,) = sqlx::query_as(
+        r#"
+        INSERT INTO maintenance_runs (device_id, repository_id, job_id, run_id, task_type, read_data_subset, start_time, status, triggered_by, owner_host, owner_pid)
+        VALUES ($1, $2, $3, $4, $5, $6, NOW(), 'running', $7, $8, $9)
+        RETURNING id
+        "#,
+    )
+    .bind(device_id)
+    .bind(repository_id)
+    .bind(job_id)
+    .bind(run_id)
+    .bind(task_type)
+    .bind(read_data_subset)
+    .bind(triggered_by)
+    .bind(&owner.host)
+    .bind(owner.pid)
+    .fetch_one(pool)
+    .await?;
+    Ok(maintenance_run_id.0)
+}
+
+pub async fn get_running_maintenance_runs(
+    pool: &PgPool,
+    device_id: String,
+) -> Result<Vec<MaintenanceRun>> {
+    let maintenance_runs = sqlx::query_as::<_, MaintenanceRun>(
+        "SELECT * FROM maintenance_runs WHERE device_id = $1 AND status = 'running' ORDER BY id",
+    )
+    .bind(device_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(maintenance_runs)
+}
+
+pub async fn abandon_maintenance_runs(
+    pool: &PgPool,
+    maintenance_run_ids: Vec<i32>,
+    error_message: String,
+) -> Result<Vec<MaintenanceRun>> {
+    let maintenance_runs = sqlx::query_as::<_, MaintenanceRun>(
+        r#"
+        UPDATE maintenance_runs
+        SET status = 'failed',
+            end_time = NOW(),
+            error_message = $2,
+            failure_reason = 'abandoned',
+            duration_seconds = EXTRACT(EPOCH FROM (NOW() - start_time))::INTEGER
+        WHERE id = ANY($1) AND status = 'running'
+        RETURNING *
+        "#,
+    )
+    .bind(maintenance_run_ids)
+    .bind(error_message)
+    .fetch_all(pool)
+    .await?;
+    Ok(maintenance_runs)
+}
+
+// Nothing is recorded if a task of the same type started at or after performed_at is known
+#[allow(dead_code)]
+pub async fn record_imported_maintenance_run(
+    executor: impl PgExecutor<'_>,
+    device_id: String,
+    task_type: String,
+    performed_at: chrono::DateTime<chrono::Utc>,
+) -> Result<Option<i32>> {
+    let maintenance_run_id: Option<(i32,)> = sqlx::query_as(
+        r#"
+        INSERT INTO maintenance_runs (device_id, task_type, start_time, end_time, status, triggered_by)
+        SELECT $1, $2, $3, $3, 'success', 'import'
+        WHERE NOT EXISTS (
+            SELECT 1 FROM maintenance_runs
+            WHERE task_type = $2 AND job_id IS NULL AND start_time >= $3
+        )
+        RETURNING id
+        "#,
+    )
+    .bind(device_id)
+    .bind(task_type)
+    .bind(performed_at)
+    .fetch_optional(executor)
+    .await?;
+    Ok(maintenance_run_id.map(|id| id.0))
+}
+
+// Allow many arguments: this function mirrors the database schema columns for maintenance updates
+#[allow(clippy::too_many_arguments)]
+#[allow(dead_code)]
+pub async fn update_maintenance_run(
+    pool: &PgPool,
+    maintenance_run_id: i32,
+    end_time: chrono::DateTime<chrono::Utc>,
+    status: String,
+    exit_code: Option<i32>,
+    error_message: Option<String>,
+    snapshots_kept: Option<i32>,
+    snapshots_removed: Option<i32>,
+    restic_output: Option<String>,
+    restic_errors: Option<String>,
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        UPDATE maintenance_runs
+        SET end_time = $2,
+            status = $3,
+            exit_code = $4,
+            error_message = $5,
+            snapshots_kept = $6,
+            snapshots_removed = $7,
+            restic_output = $8,
+            restic_errors = $9,
+            duration_seconds = EXTRACT(EPOCH FROM ($2 - start_time))::INTEGER
+        WHERE id = $1
+        "#,
+    )
+    .bind(maintenance_run_id)
+    .bind(end_time)
+    .bind(status)
+    .bind(exit_code)
+    .bind(error_message)
+    .bind(snapshots_kept)
+    .bind(snapshots_removed)
+    .bind(restic_output)
+    .bind(restic_errors)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+// Across all devices, since repository-wide tasks are coordinated between them
+#[allow(dead_code)]
+pub async fn get_last_maintenance_run(
+    pool: &PgPool,
+    task_type: String,
+    repository_id: Option<Uuid>,
+) -> Result<Option<MaintenanceRun>> {
+    let maintenance_run = sqlx::query_as::<_, MaintenanceRun>(
+        r#"
+        SELECT * FROM maintenance_runs
+        WHERE task_type = $1
+          AND repository_id IS NOT DISTINCT FROM $2
+        ORDER BY start_time DESC
+        LIMIT 1
+        "#,
+    )
+    .bind(task_type)
+    .bind(repository_id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(maintenance_run)
+}
+
+#[allow(dead_code)]
+pub async fn get_recent_maintenance_runs(
+    pool: &PgPool,
+    device_id: String,
+    limit: i64,
+) -> Result<Vec<MaintenanceRun>> {
+    let maintenance_runs = sqlx::query_as::<_, MaintenanceRun>(
+        r#"
+        SELECT * FROM maintenance_runs
+        WHERE device_id = $1
+        ORDER BY start_time DESC
+        LIMIT $2
+        "#,
+    )
+    .bind(device_id)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+    Ok(maintenance_runs)
+}
+
+pub async fn set_maintenance_failure_reason(
+    pool: &PgPool,
+    maintenance_run_id: i32,
+    failure_reason: String,
+) -> Result<()> {
+    sqlx::query("UPDATE maintenance_runs SET failure_reason = $2 WHERE id = $1")
+        .bind(maintenance_run_id)
+        .bind(failure_reason)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+// Latest check of each repository if it failed, whichever device ran it
+pub async fn get_failed_checks(pool: &PgPool) -> Result<Vec<MaintenanceRun>> {
+    let maintenance_runs = sqlx::query_as::<_, MaintenanceRun>(
+        r#"
+        SELECT * FROM maintenance_runs
+        WHERE id IN (
+            SELECT id FROM latest_maintenance_runs
+            WHERE task_type = 'check' AND status = 'failed'
+        )
+        ORDER BY start_time DESC
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(maintenance_runs)
+}
+
+pub async fn get_settings_for_device(pool: &PgPool, device_id: String) -> Result<Vec<Setting>> {
+    let settings = sqlx::query_as::<_, Setting>(
+        r#"
+        SELECT * FROM settings
+        WHERE device_id IS NULL OR device_id = $1
+        ORDER BY device_id NULLS FIRST
+        "#,
+    )
+    .bind(device_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(settings)
+}
+
+#[allow(dead_code)]
+pub async fn get_global_setting(pool: &PgPool, key: String) -> Result<Option<String>> {
+    let setting: Option<(String,)> = sqlx::query_as(
+        r#"
+        SELECT value FROM settings
+        WHERE device_id IS NULL AND key = $1
+        "#,
+    )
+    .bind(key)
+    .fetch_optional(pool)
+    .await?;
+    Ok(setting.map(|s| s.0))
+}
+
+// Configured values are never overwritten
+pub async fn set_global_setting_if_empty(
+    executor: impl PgExecutor<'_>,
+    key: String,
+    value: String,
+) -> Result<bool> {
+    let result = sqlx::query(
+        r#"
+        UPDATE settings
+        SET value = $2, updated_at = NOW()
+        WHERE device_id IS NULL AND key = $1 AND value = ''
+        "#,
+    )
+    .bind(key)
+    .bind(value)
+    .execute(executor)
+    .await?;
+    Ok(result.rows_affected() > 0)
 }
@@ -1,4 +1,9 @@
+use crate::backup::failure::ResticFailure;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
 use std::fmt;
+use tracing::error;
 
 #[derive(Debug)]
 pub enum AppError {
@@ -15,6 +20,7 @@ pub enum ConfigError {
     LoadFailed(String),
     ParseFailed(String),
     ValidationFailed(String),
+    SecretFailed(String),
 }
 
 #[derive(Debug)]
@@ -24,6 +30,7 @@ pub enum DatabaseError {
     ConnectionFailed(sqlx::Error),
     QueryFailed(sqlx::Error),
     MigrationFailed(sqlx::Error),
+    JournalFailed(String),
 }
 
 #[derive(Debug)]
@@ -31,8 +38,13 @@ pub enum DatabaseError {
 pub enum BackupError {
     ResticNotFound(String),
     ExecutionFailed(String),
+    ResticFailed(ResticFailure, String),
     OutputParseFailed(String),
     ConfigurationError(String),
+    SnapshotNotFound(String),
+    Cancelled(String),
+    TimedOut(String),
+    HookFailed(String),
 }
 
 #[derive(Debug)]
@@ -40,6 +52,9 @@ pub enum BackupError {
 pub enum SchedulerError {
     InvalidCronExpression(String),
     InvalidInterval(String),
+    InvalidMaintenanceWindow(String),
+    InvalidCalendar(String),
+    InvalidTimezone(String),
     JobNotFound(String),
 }
 
@@ -48,9 +63,30 @@ pub enum SchedulerError {
 pub enum ApiError {
     InvalidRequest(String),
     NotFound(String),
+    Unauthorized(String),
+    Forbidden(String),
     InternalError(String),
 }
 
+impl AppError {
+    // The database could not be reached, as opposed to rejecting a query
+    pub fn is_database_unavailable(&self) -> bool {
+        match self {
+            AppError::Database(DatabaseError::ConnectionFailed(_)) => true,
+            AppError::Database(DatabaseError::QueryFailed(e)) => matches!(
+                e,
+                sqlx::Error::Io(_)
+                    | sqlx::Error::Tls(_)
+                    | sqlx::Error::Protocol(_)
+                    | sqlx::Error::PoolTimedOut
+                    | sqlx::Error::PoolClosed
+                    | sqlx::Error::WorkerCrashed
+            ),
+            _ => false,
+        }
+    }
+}
+
 impl fmt::Display for AppError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -71,6 +107,7 @@ impl fmt::Display for ConfigError {
             ConfigError::ValidationFailed(msg) => {
                 write!(f, "Configuration validation failed: {}", msg)
             }
+            ConfigError::SecretFailed(msg) => write!(f, "Failed to resolve secret: {}", msg),
         }
     }
 }
@@ -81,6 +118,7 @@ impl fmt::Display for DatabaseError {
             DatabaseError::ConnectionFailed(e) => write!(f, "Database connection failed: {}", e),
             DatabaseError::QueryFailed(e) => write!(f, "Database query failed: {}", e),
             DatabaseError::MigrationFailed(e) => write!(f, "Database migration failed: {}", e),
+            DatabaseError::JournalFailed(msg) => write!(f, "Run journal failed: {}", msg),
         }
     }
 }
@@ -90,12 +128,19 @@ impl fmt::Display for BackupError {
         match self {
             BackupError::ResticNotFound(msg) => write!(f, "restic binary not found: {}", msg),
             BackupError::ExecutionFailed(msg) => write!(f, "Backup execution failed: {}", msg),
+            BackupError::ResticFailed(failure, msg) => {
+                write!(f, "Backup failed, {}: {}", failure, msg)
+            }
             BackupError::OutputParseFailed(msg) => {
                 write!(f, "Failed to parse backup output: {}", msg)
             }
             BackupError::ConfigurationError(msg) => {
                 write!(f, "Backup configuration error: {}", msg)
             }
+            BackupError::SnapshotNotFound(msg) => write!(f, "Snapshot not found: {}", msg),
+            BackupError::Cancelled(msg) => write!(f, "Backup cancelled: {}", msg),
+            BackupError::TimedOut(msg) => write!(f, "Backup timed out: {}", msg),
+            BackupError::HookFailed(msg) => write!(f, "Backup hook failed: {}", msg),
         }
     }
 }
@@ -107,6 +152,11 @@ impl fmt::Display for SchedulerError {
                 write!(f, "Invalid cron expression: {}", msg)
             }
             SchedulerError::InvalidInterval(msg) => write!(f, "Invalid interval: {}", msg),
+            SchedulerError::InvalidMaintenanceWindow(msg) => {
+                write!(f, "Invalid maintenance window: {}", msg)
+            }
+            SchedulerError::InvalidCalendar(msg) => write!(f, "Invalid calendar schedule: {}", msg),
+            SchedulerError::InvalidTimezone(msg) => write!(f, "Invalid time zone: {}", msg),
             SchedulerError::JobNotFound(msg) => write!(f, "Job not found: {}", msg),
         }
     }
@@ -117,6 +167,8 @@ impl fmt::Display for ApiError {
         match self {
             ApiError::InvalidRequest(msg) => write!(f, "Invalid request: {}", msg),
             ApiError::NotFound(msg) => write!(f, "Not found: {}", msg),
+            ApiError::Unauthorized(msg) => write!(f, "Unauthorized: {}", msg),
+            ApiError::Forbidden(msg) => write!(f, "Forbidden: {}", msg),
             ApiError::InternalError(msg) => write!(f, "Internal error: {}", msg),
         }
     }
@@ -183,4 +235,27 @@ impl From<serde_yaml::Error> for ConfigError {
     }
 }
 
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        let status = match &self {
+            AppError::Api(ApiError::InvalidRequest(_)) => StatusCode::BAD_REQUEST,
+            AppError::Api(ApiError::NotFound(_)) => StatusCode::NOT_FOUND,
+            AppError::Api(ApiError::Unauthorized(_)) => StatusCode::UNAUTHORIZED,
+            AppError::Api(ApiError::Forbidden(_)) => StatusCode::FORBIDDEN,
+            e if e.is_database_unavailable() => StatusCode::SERVICE_UNAVAILABLE,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+
+        if status.is_server_error() {
+            error!("API request failed: {}", self);
+        }
+
+        (
+            status,
+            Json(serde_json::json!({ "error": self.to_string() })),
+        )
+            .into_response()
+    }
+}
+
 pub type Result<T> = std::result::Result<T, AppError>;
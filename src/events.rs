@@ -0,0 +1,165 @@
+use crate::backup::output::BackupProgress;
+use crate::db::models::Run;
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+// Older events are dropped for a subscriber that falls behind
+const EVENT_BUFFER_SIZE: usize = 256;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    JobQueued {
+        queue_id: i64,
+        job_id: Uuid,
+        triggered_by: String,
+        priority: i32,
+    },
+    RunStarted {
+        run_id: i32,
+        job_id: Uuid,
+        repository_id: Option<Uuid>,
+        triggered_by: String,
+        attempt: i32,
+    },
+    RunProgress {
+        run_id: i32,
+        job_id: Uuid,
+        progress: BackupProgress,
+    },
+    RunFinished {
+        run_id: i32,
+        job_id: Uuid,
+        status: String,
+        stats: RunStats,
+    },
+    MaintenanceFinished {
+        maintenance_run_id: i32,
+        task_type: String,
+        repository_id: Option<Uuid>,
+        status: String,
+        error_message: Option<String>,
+        failure_reason: Option<String>,
+    },
+    ConfigReloaded {
+        changed: Vec<String>,
+    },
+    DatabaseConnectivityChanged {
+        connected: bool,
+    },
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RunStats {
+    pub duration_seconds: Option<i32>,
+    pub files_new: Option<i32>,
+    pub files_changed: Option<i32>,
+    pub files_unmodified: Option<i32>,
+    pub data_added_bytes: Option<i64>,
+    pub snapshot_id: Option<String>,
+    pub error_message: Option<String>,
+    pub failure_reason: Option<String>,
+}
+
+impl Event {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Event::JobQueued { .. } => "job_queued",
+            Event::RunStarted { .. } => "run_started",
+            Event::RunProgress { .. } => "run_progress",
+            Event::RunFinished { .. } => "run_finished",
+            Event::MaintenanceFinished { .. } => "maintenance_finished",
+            Event::ConfigReloaded { .. } => "config_reloaded",
+            Event::DatabaseConnectivityChanged { .. } => "database_connectivity_changed",
+        }
+    }
+
+    pub fn run_finished(run: &Run) -> Self {
+        Event::RunFinished {
+            run_id: run.id,
+            job_id: run.job_id,
+            status: run.status.clone(),
+            stats: RunStats {
+                duration_seconds: run.duration_seconds,
+                files_new: run.files_new,
+                files_changed: run.files_changed,
+                files_unmodified: run.files_unmodified,
+                data_added_bytes: run.data_added_bytes,
+                snapshot_id: run.snapshot_id.clone(),
+                error_message: run.error_message.clone(),
+                failure_reason: run.failure_reason.clone(),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<Event>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(EVENT_BUFFER_SIZE);
+        Self { sender }
+    }
+
+    pub fn publish(&self, event: Event) {
+        // Fails only when nobody is subscribed
+        let _ = self.sender.send(event);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<Event> {
+        self.sender.subscribe()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_publish_and_subscribe() {
+        let bus = EventBus::new();
+
+        // Published without subscribers, never received
+        bus.publish(Event::DatabaseConnectivityChanged { connected: false });
+
+        let mut rx = bus.subscribe();
+        let event = Event::ConfigReloaded {
+            changed: vec!["jobs".to_string()],
+        };
+        bus.clone().publish(event.clone());
+
+        assert_eq!(rx.recv().await.unwrap(), event);
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_event_serialization() {
+        let job_id = Uuid::new_v4();
+        let event = Event::JobQueued {
+            queue_id: 7,
+            job_id,
+            triggered_by: "manual".to_string(),
+            priority: 30,
+        };
+
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["type"], event.name());
+        assert_eq!(json["queue_id"], 7);
+        assert_eq!(json["job_id"], job_id.to_string());
+
+        let json =
+            serde_json::to_value(Event::DatabaseConnectivityChanged { connected: true }).unwrap();
+        assert_eq!(json["type"], "database_connectivity_changed");
+        assert_eq!(json["connected"], true);
+    }
+}
@@ -0,0 +1,903 @@
+use crate::db;
+use crate::db::models::{BackupHook, BackupJob, CalendarSchedule, Schedule};
+use crate::error::{ConfigError, Result};
+use chrono::{DateTime, Datelike, Utc};
+use serde::{Deserialize, Deserializer, Serialize};
+use sqlx::types::Json;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::path::Path;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+pub const IMPORT_SOURCE: &str = "relica";
+
+// Go serializes empty slices as null
+fn null_as_default<'de, D, T>(deserializer: D) -> std::result::Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Default + Deserialize<'de>,
+{
+    Ok(Option::<T>::deserialize(deserializer)?.unwrap_or_default())
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AccountInfo {
+    pub read_only: ReadOnly,
+    #[serde(default)]
+    pub local: Local,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ReadOnly {
+    #[serde(default, deserialize_with = "null_as_default")]
+    pub origins: Vec<Origin>,
+    #[serde(default, deserialize_with = "null_as_default")]
+    pub backups: Vec<Backup>,
+    #[serde(default, deserialize_with = "null_as_default")]
+    pub destinations: Vec<Destination>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Local {
+    #[serde(default, deserialize_with = "null_as_default")]
+    pub last_backups_done: HashMap<Uuid, HashMap<Uuid, DateTime<Utc>>>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Origin {
+    pub id: Uuid,
+    pub name: String,
+    pub account_id: Option<Uuid>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Backup {
+    pub id: Uuid,
+    pub name: String,
+    pub origin_id: Uuid,
+    pub account_id: Option<Uuid>,
+    #[serde(default, deserialize_with = "null_as_default")]
+    pub include: Vec<String>,
+    #[serde(default, deserialize_with = "null_as_default")]
+    pub exclude: Vec<String>,
+    #[serde(default, deserialize_with = "null_as_default")]
+    pub destinations: Vec<Uuid>,
+    pub schedule: Option<RelicaSchedule>,
+    #[serde(default, deserialize_with = "null_as_default")]
+    pub hooks: Vec<Hook>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RelicaSchedule {
+    #[serde(default)]
+    pub scheduled: bool,
+    #[serde(default)]
+    pub flexible: bool,
+    #[serde(default, deserialize_with = "null_as_default")]
+    pub times_of_day: Vec<String>,
+    // 0 = Sunday, as Go's time.Weekday
+    #[serde(default, deserialize_with = "null_as_default")]
+    pub days_of_week: Vec<u32>,
+    #[serde(default, deserialize_with = "null_as_default")]
+    pub days_of_month: Vec<u32>,
+    #[serde(default, deserialize_with = "null_as_default")]
+    pub months_of_year: Vec<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Hook {
+    pub id: Uuid,
+    pub command: String,
+    pub description: Option<String>,
+    pub timing: String,
+    pub on_error: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Destination {
+    pub id: Uuid,
+    pub name: String,
+    pub last_check: Option<DateTime<Utc>>,
+    pub last_prune: Option<DateTime<Utc>>,
+    pub cloud_account: Option<CloudAccount>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CloudAccount {
+    pub remote_type: String,
+    #[serde(default)]
+    pub remote_base_path: String,
+    #[serde(default, deserialize_with = "null_as_default")]
+    pub credentials: Vec<Credential>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Credential {
+    pub key: String,
+    pub value: String,
+}
+
+impl CloudAccount {
+    fn credential(&self, key: &str) -> Option<&str> {
+        self.credentials
+            .iter()
+            .find(|c| c.key == key)
+            .map(|c| c.value.as_str())
+            .filter(|v| !v.is_empty())
+    }
+
+    fn repository_url(&self) -> Option<String> {
+        if self.remote_type != "sftp" {
+            return None;
+        }
+
+        let host = self.credential("HOST")?;
+        let user = self
+            .credential("USER")
+            .map(|user| format!("{}@", user))
+            .unwrap_or_default();
+        let port = self.credential("PORT").unwrap_or("22");
+
+        Some(format!(
+            "sftp://{}{}:{}/{}",
+            user, host, port, self.remote_base_path
+        ))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportedSchedule {
+    pub calendar: CalendarSchedule,
+    pub enabled: bool,
+    pub metadata: serde_json::Value,
+}
+
+#[derive(Debug, Clone)]
+pub struct ImportedJob {
+    pub id: Uuid,
+    pub name: String,
+    pub source_paths: Vec<String>,
+    pub exclude_patterns: Option<Vec<String>>,
+    pub origin_id: Uuid,
+    pub account_id: Option<Uuid>,
+    pub schedule: Option<ImportedSchedule>,
+    pub last_run_at: Option<DateTime<Utc>>,
+    pub hooks: Vec<Hook>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ImportPlan {
+    pub device_id: String,
+    pub platform: String,
+    pub origin: Origin,
+    pub jobs: Vec<ImportedJob>,
+    pub repository_url: Option<String>,
+    pub last_check: Option<DateTime<Utc>>,
+    pub last_prune: Option<DateTime<Utc>>,
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ExistingState {
+    pub device_exists: bool,
+    pub jobs: HashMap<Uuid, BackupJob>,
+    pub schedules: HashMap<Uuid, Vec<Schedule>>,
+    pub hooks: HashMap<Uuid, BackupHook>,
+    pub repository_url: Option<String>,
+}
+
+impl ExistingState {
+    fn imported_schedules(&self, job_id: Uuid) -> Vec<&Schedule> {
+        self.schedules
+            .get(&job_id)
+            .map(|schedules| {
+                schedules
+                    .iter()
+                    .filter(|s| {
+                        s.metadata.get("imported_from").and_then(|v| v.as_str())
+                            == Some(IMPORT_SOURCE)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn imported_last_run(&self, job_id: Uuid) -> Option<DateTime<Utc>> {
+        self.imported_schedules(job_id)
+            .iter()
+            .filter_map(|s| s.last_run_at)
+            .max()
+    }
+}
+
+pub fn load_account_info(path: &Path) -> Result<AccountInfo> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| ConfigError::LoadFailed(format!("Cannot read {}: {}", path.display(), e)))?;
+    parse_account_info(&content)
+}
+
+pub fn parse_account_info(content: &str) -> Result<AccountInfo> {
+    serde_json::from_str(content)
+        .map_err(|e| ConfigError::ParseFailed(format!("Invalid Relica account info: {}", e)).into())
+}
+
+// Go's zero time marks values that were never set
+fn non_zero(time: Option<DateTime<Utc>>) -> Option<DateTime<Utc>> {
+    time.filter(|t| t.year() > 1)
+}
+
+fn parse_time_of_day(value: &str) -> Result<(u32, u32)> {
+    let invalid = || ConfigError::ParseFailed(format!("Invalid Relica time of day: {}", value));
+
+    let (hour, minute) = value.split_once(':').ok_or_else(invalid)?;
+    let hour: u32 = hour.trim().parse().map_err(|_| invalid())?;
+    let minute: u32 = minute.trim().parse().map_err(|_| invalid())?;
+
+    if hour > 23 || minute > 59 {
+        return Err(invalid().into());
+    }
+
+    Ok((hour, minute))
+}
+
+fn sorted(values: &[u32]) -> Vec<u32> {
+    let mut values = values.to_vec();
+    values.sort_unstable();
+    values.dedup();
+    values
+}
+
+// Sunday given as 7 becomes 0
+pub fn convert_schedule(schedule: &RelicaSchedule) -> Result<Option<CalendarSchedule>> {
+    let mut times = Vec::new();
+    for time in &schedule.times_of_day {
+        times.push(parse_time_of_day(time)?);
+    }
+    times.sort_unstable();
+    times.dedup();
+
+    if times.is_empty() {
+        return Ok(None);
+    }
+
+    if let Some(invalid) = schedule.days_of_week.iter().find(|d| **d > 7) {
+        return Err(
+            ConfigError::ParseFailed(format!("Invalid Relica day of week: {}", invalid)).into(),
+        );
+    }
+    let days_of_week: Vec<u32> = schedule.days_of_week.iter().map(|d| d % 7).collect();
+
+    let calendar = CalendarSchedule {
+        times_of_day: times
+            .iter()
+            .map(|(hour, minute)| format!("{:02}:{:02}", hour, minute))
+            .collect(),
+        days_of_week: sorted(&days_of_week),
+        days_of_month: sorted(&schedule.days_of_month),
+        months_of_year: sorted(&schedule.months_of_year),
+        flexible: schedule.flexible,
+    };
+
+    calendar
+        .validate()
+        .map_err(|e| ConfigError::ParseFailed(format!("Invalid Relica schedule: {}", e)))?;
+
+    Ok(Some(calendar))
+}
+
+fn guess_platform(jobs: &[ImportedJob]) -> &'static str {
+    let windows_path = |path: &String| path.as_bytes().get(1..3) == Some(b":\\");
+
+    if jobs
+        .iter()
+        .any(|job| job.source_paths.iter().any(windows_path))
+    {
+        "windows"
+    } else {
+        "linux"
+    }
+}
+
+pub fn plan_import(info: &AccountInfo, origin_name: &str, device_id: &str) -> Result<ImportPlan> {
+    let origins: Vec<&Origin> = info
+        .read_only
+        .origins
+        .iter()
+        .filter(|o| o.name == origin_name)
+        .collect();
+
+    let origin = match origins.as_slice() {
+        [origin] => (*origin).clone(),
+        [] => {
+            let names: Vec<&str> = info
+                .read_only
+                .origins
+                .iter()
+                .map(|o| o.name.as_str())
+                .collect();
+            return Err(ConfigError::ValidationFailed(format!(
+                "Relica origin '{}' not found, available origins: {}",
+                origin_name,
+                names.join(", ")
+            ))
+            .into());
+        }
+        _ => {
+            return Err(ConfigError::ValidationFailed(format!(
+                "Relica origin name '{}' is not unique",
+                origin_name
+            ))
+            .into())
+        }
+    };
+
+    let mut warnings = Vec::new();
+    let mut jobs = Vec::new();
+    let mut destination_ids: Vec<Uuid> = Vec::new();
+
+    for backup in info
+        .read_only
+        .backups
+        .iter()
+        .filter(|b| b.origin_id == origin.id)
+    {
+        let relica_schedule = backup.schedule.clone().unwrap_or_default();
+        let schedule = convert_schedule(&relica_schedule)?.map(|calendar| ImportedSchedule {
+            calendar,
+            enabled: relica_schedule.scheduled,
+            metadata: serde_json::json!({ "imported_from": IMPORT_SOURCE }),
+        });
+
+        let last_run_at = info
+            .local
+            .last_backups_done
+            .get(&backup.id)
+            .and_then(|by_destination| non_zero(by_destination.values().copied().max()));
+
+        let mut hooks = Vec::new();
+        for hook in &backup.hooks {
+            if matches!(hook.timing.as_str(), "start" | "end" | "failure")
+                && matches!(hook.on_error.as_str(), "stop" | "continue")
+            {
+                hooks.push(hook.clone());
+            } else {
+                warnings.push(format!(
+                    "Skipping hook {} of {}: unsupported timing '{}' or on_error '{}'",
+                    hook.id, backup.name, hook.timing, hook.on_error
+                ));
+            }
+        }
+
+        for destination_id in &backup.destinations {
+            if !destination_ids.contains(destination_id) {
+                destination_ids.push(*destination_id);
+            }
+        }
+
+        jobs.push(ImportedJob {
+            id: backup.id,
+            name: backup.name.clone(),
+            source_paths: backup.include.clone(),
+            exclude_patterns: Some(backup.exclude.clone()).filter(|e| !e.is_empty()),
+            origin_id: origin.id,
+            account_id: backup.account_id.or(origin.account_id),
+            schedule,
+            last_run_at,
+            hooks,
+        });
+    }
+
+    if destination_ids.len() > 1 {
+        warnings.push(format!(
+            "Backups use {} destinations, only the first one is used for the repository",
+            destination_ids.len()
+        ));
+    }
+
+    let destination = destination_ids
+        .first()
+        .and_then(|id| info.read_only.destinations.iter().find(|d| d.id == *id));
+
+    let repository_url = destination
+        .and_then(|d| d.cloud_account.as_ref())
+        .and_then(CloudAccount::repository_url);
+
+    if let Some(destination) = destination {
+        if repository_url.is_none() {
+            warnings.push(format!(
+                "Repository URL of destination '{}' cannot be derived, configure repository_url manually",
+                destination.name
+            ));
+        }
+    }
+
+    Ok(ImportPlan {
+        device_id: device_id.to_string(),
+        platform: guess_platform(&jobs).to_string(),
+        origin,
+        jobs,
+        repository_url,
+        last_check: non_zero(destination.and_then(|d| d.last_check)),
+        last_prune: non_zero(destination.and_then(|d| d.last_prune)),
+        warnings,
+    })
+}
+
+pub async fn load_existing_state(pool: &PgPool, plan: &ImportPlan) -> Result<ExistingState> {
+    let mut state = ExistingState {
+        device_exists: db::get_device(pool, plan.device_id.clone())
+            .await?
+            .is_some(),
+        repository_url: db::get_global_setting(pool, "repository_url".to_string())
+            .await?
+            .filter(|url| !url.is_empty()),
+        ..Default::default()
+    };
+
+    for job in &plan.jobs {
+        if let Some(existing) = db::get_job_by_id(pool, job.id).await? {
+            state.jobs.insert(job.id, existing);
+        }
+
+        state
+            .schedules
+            .insert(job.id, db::get_schedules_for_job(pool, job.id).await?);
+
+        for hook in db::get_hooks_for_job(pool, job.id).await? {
+            state.hooks.insert(hook.id, hook);
+        }
+    }
+
+    Ok(state)
+}
+
+fn changed_job_fields(
+    plan: &ImportPlan,
+    job: &ImportedJob,
+    existing: &BackupJob,
+) -> Vec<&'static str> {
+    let mut fields = Vec::new();
+
+    if existing.device_id != plan.device_id {
+        fields.push("device_id");
+    }
+    if existing.name != job.name {
+        fields.push("name");
+    }
+    if existing.source_paths != job.source_paths {
+        fields.push("source_paths");
+    }
+    if existing.exclude_patterns != job.exclude_patterns {
+        fields.push("exclude_patterns");
+    }
+    if existing.origin_name.as_deref() != Some(plan.origin.name.as_str()) {
+        fields.push("origin_name");
+    }
+    if existing.origin_id != Some(job.origin_id) {
+        fields.push("origin_id");
+    }
+    if existing.account_id != job.account_id {
+        fields.push("account_id");
+    }
+
+    fields
+}
+
+fn describe_schedule(schedule: &Option<ImportedSchedule>) -> String {
+    match schedule {
+        None => "none".to_string(),
+        Some(s) if s.enabled => format!("'{}'", s.calendar),
+        Some(s) => format!("'{}' (disabled)", s.calendar),
+    }
+}
+
+// + for new records, ~ for updated ones and = for unchanged jobs
+pub fn describe_changes(plan: &ImportPlan, existing: &ExistingState) -> Vec<String> {
+    let mut lines = Vec::new();
+
+    if !existing.device_exists {
+        lines.push(format!("+ device {} ({})", plan.device_id, plan.platform));
+    }
+
+    for job in &plan.jobs {
+        match existing.jobs.get(&job.id) {
+            None => lines.push(format!("+ job {} '{}'", job.id, job.name)),
+            Some(existing_job) => {
+                let fields = changed_job_fields(plan, job, existing_job);
+                if fields.is_empty() {
+                    lines.push(format!("= job {} '{}'", job.id, job.name));
+                } else {
+                    lines.push(format!(
+                        "~ job {} '{}': {}",
+                        job.id,
+                        job.name,
+                        fields.join(", ")
+                    ));
+                }
+            }
+        }
+
+        // Schedules imported as cron expressions by earlier versions are replaced as well
+        let current: Vec<(Option<&CalendarSchedule>, bool)> = existing
+            .imported_schedules(job.id)
+            .iter()
+            .map(|s| (s.calendar.as_ref().map(|c| &c.0), s.enabled))
+            .collect();
+        let planned: Vec<(Option<&CalendarSchedule>, bool)> = job
+            .schedule
+            .iter()
+            .map(|s| (Some(&s.calendar), s.enabled))
+            .collect();
+
+        if current != planned {
+            let marker = if current.is_empty() { "+" } else { "~" };
+            lines.push(format!(
+                "{}   schedule: {}",
+                marker,
+                describe_schedule(&job.schedule)
+            ));
+        }
+
+        let seeded = job.last_run_at.max(existing.imported_last_run(job.id));
+        if seeded != existing.imported_last_run(job.id) && job.schedule.is_some() {
+            if let Some(last_run_at) = seeded {
+                lines.push(format!("~   last run: {}", last_run_at.to_rfc3339()));
+            }
+        }
+
+        for hook in &job.hooks {
+            let marker = match existing.hooks.get(&hook.id) {
+                None => "+",
+                Some(existing_hook)
+                    if existing_hook.job_id == job.id
+                        && existing_hook.command == hook.command
+                        && existing_hook.timing == hook.timing
+                        && existing_hook.on_error == hook.on_error
+                        && existing_hook.description == hook.description =>
+                {
+                    continue
+                }
+                Some(_) => "~",
+            };
+
+            lines.push(format!(
+                "{}   {} hook {} (on_error: {}): {}",
+                marker, hook.timing, hook.id, hook.on_error, hook.command
+            ));
+        }
+    }
+
+    if let Some(url) = &plan.repository_url {
+        match &existing.repository_url {
+            None => lines.push(format!("+ setting repository_url = {}", url)),
+            Some(current) if current != url => lines.push(format!(
+                "! setting repository_url is already '{}', keeping it (Relica: {})",
+                current, url
+            )),
+            Some(_) => {}
+        }
+    }
+
+    if let Some(last_check) = plan.last_check {
+        lines.push(format!(
+            "+ last repository check: {}",
+            last_check.to_rfc3339()
+        ));
+    }
+    if let Some(last_prune) = plan.last_prune {
+        lines.push(format!(
+            "+ last repository prune: {}",
+            last_prune.to_rfc3339()
+        ));
+    }
+
+    lines
+}
+
+// Jobs and hooks keep their Relica UUIDs, so importing again updates them
+pub async fn apply_import(
+    pool: &PgPool,
+    plan: &ImportPlan,
+    existing: &ExistingState,
+) -> Result<()> {
+    let mut tx = pool.begin().await?;
+
+    if db::create_device_if_missing(
+        &mut *tx,
+        plan.device_id.clone(),
+        plan.device_id.clone(),
+        plan.platform.clone(),
+    )
+    .await?
+    {
+        info!(device_id = %plan.device_id, "Created device");
+    }
+
+    for job in &plan.jobs {
+        db::upsert_imported_job(
+            &mut *tx,
+            job.id,
+            plan.device_id.clone(),
+            job.name.clone(),
+            job.source_paths.clone(),
+            job.exclude_patterns.clone(),
+            Some(plan.origin.name.clone()),
+            Some(job.origin_id),
+            job.account_id,
+        )
+        .await?;
+
+        let last_run_at = job.last_run_at.max(existing.imported_last_run(job.id));
+
+        db::delete_imported_schedules(&mut *tx, job.id, IMPORT_SOURCE.to_string()).await?;
+        if let Some(schedule) = &job.schedule {
+            db::create_schedule(
+                &mut *tx,
+                job.id,
+                "calendar".to_string(),
+                None,
+                None,
+                Some(Json(schedule.calendar.clone())),
+                schedule.enabled,
+                last_run_at,
+                schedule.metadata.clone(),
+            )
+            .await?;
+        }
+
+        for (sort_order, hook) in job.hooks.iter().enumerate() {
+            db::upsert_imported_hook(
+                &mut *tx,
+                hook.id,
+                job.id,
+                hook.description.clone().filter(|d| !d.is_empty()),
+                hook.command.clone(),
+                hook.timing.clone(),
+                hook.on_error.clone(),
+                sort_order as i32,
+            )
+            .await?;
+        }
+
+        info!(
+            job_id = %job.id,
+            job_name = %job.name,
+            scheduled = job.schedule.is_some(),
+            hooks = job.hooks.len(),
+            "Imported backup job"
+        );
+    }
+
+    if let Some(url) = &plan.repository_url {
+        if !db::set_global_setting_if_empty(&mut *tx, "repository_url".to_string(), url.clone())
+            .await?
+        {
+            warn!("repository_url is already configured, keeping it");
+        }
+    }
+
+    if let Some(last_check) = plan.last_check {
+        db::record_imported_maintenance_run(
+            &mut *tx,
+            plan.device_id.clone(),
+            "check".to_string(),
+            last_check,
+        )
+        .await?;
+    }
+
+    if let Some(last_prune) = plan.last_prune {
+        db::record_imported_maintenance_run(
+            &mut *tx,
+            plan.device_id.clone(),
+            "prune".to_string(),
+            last_prune,
+        )
+        .await?;
+    }
+
+    tx.commit().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scheduler::timezone::ScheduleTimezone;
+
+    const FIXTURE: &str = include_str!("../../doc/relica-account-info.json");
+
+    fn fixture() -> AccountInfo {
+        parse_account_info(FIXTURE).expect("Fixture should parse")
+    }
+
+    fn schedule(times: &[&str], days_of_week: &[u32]) -> RelicaSchedule {
+        RelicaSchedule {
+            scheduled: true,
+            flexible: true,
+            times_of_day: times.iter().map(|t| t.to_string()).collect(),
+            days_of_week: days_of_week.to_vec(),
+            ..Default::default()
+        }
+    }
+
+    fn times(calendar: &CalendarSchedule) -> Vec<&str> {
+        calendar.times_of_day.iter().map(|t| t.as_str()).collect()
+    }
+
+    #[test]
+    fn test_convert_schedule() {
+        let daily = convert_schedule(&schedule(&["23:00", "13:00", "9:05"], &[]))
+            .unwrap()
+            .unwrap();
+        assert_eq!(times(&daily), vec!["09:05", "13:00", "23:00"]);
+        assert!(daily.days_of_week.is_empty());
+        assert!(daily.flexible);
+
+        let weekly = convert_schedule(&schedule(&["01:30"], &[6, 1, 7, 4, 2]))
+            .unwrap()
+            .unwrap();
+        assert_eq!(weekly.days_of_week, vec![0, 1, 2, 4, 6]);
+
+        let monthly = RelicaSchedule {
+            days_of_month: vec![15, 1],
+            months_of_year: vec![6],
+            flexible: false,
+            ..schedule(&["12:05"], &[])
+        };
+        let monthly = convert_schedule(&monthly).unwrap().unwrap();
+        assert_eq!(monthly.days_of_month, vec![1, 15]);
+        assert_eq!(monthly.months_of_year, vec![6]);
+        assert!(!monthly.flexible);
+
+        assert_eq!(convert_schedule(&schedule(&[], &[])).unwrap(), None);
+        assert!(convert_schedule(&schedule(&["25:00"], &[])).is_err());
+        assert!(convert_schedule(&schedule(&["noon"], &[])).is_err());
+        assert!(convert_schedule(&schedule(&["12:00"], &[9])).is_err());
+
+        let invalid_month = RelicaSchedule {
+            months_of_year: vec![13],
+            ..schedule(&["12:00"], &[])
+        };
+        assert!(convert_schedule(&invalid_month).is_err());
+    }
+
+    #[test]
+    fn test_converted_schedules_have_next_run() {
+        let now = Utc::now();
+        for backup in fixture().read_only.backups {
+            let schedule = backup.schedule.unwrap_or_default();
+            if let Some(calendar) = convert_schedule(&schedule).unwrap() {
+                let next = calendar
+                    .next_after(now, &ScheduleTimezone::Local)
+                    .unwrap_or_else(|e| panic!("No next run for '{}': {}", calendar, e));
+                assert!(next > now);
+            }
+        }
+    }
+
+    #[test]
+    fn test_plan_import_from_fixture() {
+        let plan = plan_import(&fixture(), "device1", "device1").unwrap();
+
+        assert_eq!(
+            plan.origin.id.to_string(),
+            "f213f03b-c8e1-47a5-8bf5-f7c8dd76217d"
+        );
+        assert_eq!(plan.platform, "linux");
+        assert!(plan.jobs.iter().all(|j| j.name.starts_with("device1/")));
+        assert!(plan.warnings.is_empty());
+
+        let job = plan
+            .jobs
+            .iter()
+            .find(|j| j.name == "device1/Dokumenty")
+            .expect("device1/Dokumenty should be imported");
+        assert_eq!(job.id.to_string(), "cb3104d6-5d04-4cd6-9a8e-73c6c4b61fe9");
+        assert_eq!(
+            job.account_id.map(|id| id.to_string()).as_deref(),
+            Some("003dbcee-4f0a-4a55-a7bb-a6511b263558")
+        );
+        assert_eq!(job.source_paths, vec!["/home/user/Dokumenty"]);
+        assert_eq!(job.exclude_patterns, None);
+        let schedule = job.schedule.as_ref().expect("Job should be scheduled");
+        assert_eq!(times(&schedule.calendar), vec!["13:00", "23:00"]);
+        assert!(schedule.calendar.flexible);
+        assert!(schedule.enabled);
+        assert_eq!(schedule.metadata["imported_from"], "relica");
+        assert_eq!(job.hooks.len(), 1);
+        assert_eq!(job.hooks[0].timing, "end");
+        assert_eq!(job.hooks[0].on_error, "stop");
+
+        let unscheduled = plan
+            .jobs
+            .iter()
+            .find(|j| j.name == "device1/test")
+            .expect("device1/test should be imported");
+        assert_eq!(unscheduled.schedule, None);
+
+        assert_eq!(
+            plan.repository_url.as_deref(),
+            Some("sftp://backup_user@backup.example.com:29//data/backups/")
+        );
+        assert_eq!(
+            plan.last_check.map(|t| t.to_rfc3339()).as_deref(),
+            Some("2025-12-12T17:13:12.032954+00:00")
+        );
+        assert_eq!(plan.last_prune, None);
+    }
+
+    #[test]
+    fn test_plan_import_last_run_and_platform() {
+        let info = fixture();
+
+        let plan = plan_import(&info, "prcek", "prcek").unwrap();
+        assert_eq!(plan.platform, "windows");
+
+        let seeded: Vec<&ImportedJob> = plan
+            .jobs
+            .iter()
+            .filter(|j| j.last_run_at.is_some())
+            .collect();
+        for job in seeded {
+            let expected = info.local.last_backups_done[&job.id]
+                .values()
+                .max()
+                .copied();
+            assert_eq!(job.last_run_at, expected);
+        }
+
+        assert!(plan_import(&info, "unknown-origin", "x").is_err());
+    }
+
+    #[test]
+    fn test_describe_changes() {
+        let plan = plan_import(&fixture(), "device1", "device1").unwrap();
+
+        let lines = describe_changes(&plan, &ExistingState::default());
+        assert!(lines.contains(&"+ device device1 (linux)".to_string()));
+        assert!(lines.contains(
+            &"+ job cb3104d6-5d04-4cd6-9a8e-73c6c4b61fe9 'device1/Dokumenty'".to_string()
+        ));
+        assert!(lines.contains(&"+   schedule: 'at 13:00,23:00 (flexible)'".to_string()));
+        assert!(lines
+            .iter()
+            .any(|l| l.starts_with("+ setting repository_url = sftp://")));
+
+        let job = &plan.jobs[0];
+        let mut existing = ExistingState {
+            device_exists: true,
+            repository_url: Some("/srv/restic".to_string()),
+            ..Default::default()
+        };
+        existing.jobs.insert(
+            job.id,
+            BackupJob {
+                id: job.id,
+                device_id: "device1".to_string(),
+                name: job.name.clone(),
+                description: None,
+                source_paths: vec!["/old".to_string()],
+                exclude_patterns: job.exclude_patterns.clone(),
+                tags: None,
+                restic_args: serde_json::json!([]),
+                enabled: true,
+                created_at: Utc::now(),
+                updated_at: Utc::now(),
+                metadata: serde_json::json!({}),
+                origin_name: Some("device1".to_string()),
+                origin_id: Some(job.origin_id),
+                account_id: job.account_id,
+                retention: Default::default(),
+            },
+        );
+
+        let lines = describe_changes(&plan, &existing);
+        assert!(!lines.iter().any(|l| l.starts_with("+ device")));
+        assert!(lines.contains(&format!("~ job {} '{}': source_paths", job.id, job.name)));
+        assert!(lines
+            .iter()
+            .any(|l| l.starts_with("! setting repository_url is already '/srv/restic'")));
+    }
+}
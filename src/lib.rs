@@ -1,5 +1,9 @@
+pub mod api;
 pub mod backup;
 pub mod config;
 pub mod db;
 pub mod error;
+pub mod events;
+pub mod import;
 pub mod scheduler;
+pub mod ui;
@@ -1,17 +1,32 @@
+mod api;
 mod backup;
 mod config;
 mod db;
 mod error;
+mod events;
+mod import;
 mod scheduler;
-
-use clap::Parser;
+mod ui;
+
+use api::auth::ApiAuth;
+use api::server::AppState;
+use backup::journal::RunJournal;
+use backup::output::FailedPath;
+use backup::registry::{CancelRequest, RunRegistry};
+use backup::restore::{OverwritePolicy, RestoreOptions, SnapshotSelector};
+use clap::{Parser, Subcommand};
+use config::cache::ConfigCache;
+use config::secret::{self, DeviceKey};
 use config::{load_config_from_db, LocalConfig};
+use events::EventBus;
+use scheduler::config_sync::ConfigSync;
 use scheduler::executor::JobExecutor;
+use scheduler::queue::JobQueue;
 use scheduler::Scheduler;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tokio::sync::Mutex;
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
 use tracing_subscriber::{fmt, prelude::*, EnvFilter};
 use uuid::Uuid;
 
@@ -24,6 +39,117 @@ struct Args {
 
     #[arg(long, value_name = "JOB_ID")]
     test_backup: Option<Uuid>,
+
+    #[arg(
+        long,
+        value_name = "JOB_ID",
+        requires = "restore_target",
+        help = "Restore a snapshot of the given job and exit"
+    )]
+    restore: Option<Uuid>,
+
+    #[arg(
+        long,
+        value_name = "DIR",
+        requires = "restore",
+        help = "Directory to restore into"
+    )]
+    restore_target: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_name = "SNAPSHOT_ID",
+        requires = "restore",
+        help = "Snapshot to restore (defaults to the latest snapshot of the job)"
+    )]
+    restore_snapshot: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "PATTERN",
+        requires = "restore",
+        help = "Only restore paths matching the pattern (may be repeated)"
+    )]
+    restore_include: Vec<String>,
+
+    #[arg(
+        long,
+        value_name = "PATTERN",
+        requires = "restore",
+        help = "Skip paths matching the pattern (may be repeated)"
+    )]
+    restore_exclude: Vec<String>,
+
+    #[arg(
+        long,
+        value_name = "NAME",
+        requires = "restore",
+        help = "Name of the repository to restore from (defaults to the first repository of the job)"
+    )]
+    restore_repository: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "POLICY",
+        default_value = "always",
+        help = "How to handle existing files: always, if-changed, if-newer, never"
+    )]
+    restore_overwrite: OverwritePolicy,
+
+    #[arg(long, value_name = "RUN_ID", help = "Cancel a running backup and exit")]
+    cancel: Option<i32>,
+
+    #[arg(
+        long,
+        value_name = "REASON",
+        requires = "cancel",
+        default_value = "Cancelled from command line",
+        help = "Reason recorded with the cancellation"
+    )]
+    cancel_reason: String,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    #[command(about = "Import the backups of a device from a Relica account-info file and exit")]
+    ImportRelica {
+        #[arg(value_name = "FILE", help = "Relica account-info JSON file")]
+        file: PathBuf,
+
+        #[arg(
+            long,
+            value_name = "DEVICE_ID",
+            help = "Device to import the backups to (defaults to the device of the configuration file)"
+        )]
+        device: Option<String>,
+
+        #[arg(
+            long,
+            value_name = "NAME",
+            help = "Relica origin whose backups are imported (defaults to the device ID)"
+        )]
+        origin: Option<String>,
+
+        #[arg(long, help = "Print the changes without writing them to the database")]
+        dry_run: bool,
+    },
+
+    #[command(about = "Print the running and queued backups of the device and exit")]
+    Status,
+
+    #[command(about = "Print a new random key for `device.secret_key` and exit")]
+    GenerateDeviceKey,
+
+    #[command(
+        about = "Encrypt a secret with the key of the device and print it as `enc:` value for the database or configuration file"
+    )]
+    EncryptSecret {
+        #[arg(value_name = "VALUE")]
+        value: String,
+    },
 }
 
 #[tokio::main]
@@ -39,6 +165,28 @@ async fn main() {
 async fn run(args: Args) -> error::Result<()> {
     let config = LocalConfig::from_file(&args.config)?;
 
+    match &args.command {
+        Some(Command::GenerateDeviceKey) => {
+            println!("{}", DeviceKey::generate()?);
+            return Ok(());
+        }
+        Some(Command::EncryptSecret { value }) => {
+            let key = config.device_key()?.ok_or_else(|| {
+                error::ConfigError::ValidationFailed(
+                    "device.secret_key is not configured, generate one with generate-device-key"
+                        .to_string(),
+                )
+            })?;
+            println!("{}", key.encrypt(value)?);
+            return Ok(());
+        }
+        _ => {}
+    }
+
+    if let Some(key) = config.device_key()? {
+        secret::set_device_key(key);
+    }
+
     setup_logging(&config)?;
 
     info!("========================================");
@@ -65,34 +213,52 @@ async fn run(args: Args) -> error::Result<()> {
     }
     info!("========================================");
 
+    let cache = ConfigCache::new(config.config_cache_path(&args.config), config.device_key()?);
+    debug!("Configuration cache: {}", cache.path().display());
+
+    let journal = RunJournal::new(config.run_journal_path(&args.config));
+    debug!("Run journal: {}", journal.path().display());
+
+    let api_token_path = config.api_token_path(&args.config);
+
+    // Only the client itself can run without the database, command line actions need it
+    let client_mode = args.command.is_none()
+        && args.cancel.is_none()
+        && args.test_backup.is_none()
+        && args.restore.is_none();
+
     info!("Connecting to database...");
-    let database_url = config.database_url();
-    let pool = db::create_pool(database_url).await?;
+    let database_url = config.database_url()?;
+    let pool = match db::create_pool(database_url.clone()).await {
+        Ok(pool) => pool,
+        Err(e) if client_mode => {
+            return run_offline(&config, &api_token_path, cache, journal, database_url, e).await
+        }
+        Err(e) => return Err(e),
+    };
     debug!("Database connection established");
 
     info!("Running database migrations...");
     db::run_migrations(&pool).await?;
     debug!("Database migrations completed");
 
-    info!("Registering device...");
-    let platform = if cfg!(target_os = "windows") {
-        "windows"
-    } else if cfg!(target_os = "linux") {
-        "linux"
-    } else {
-        "unknown"
-    };
-
-    let hostname = hostname::get().ok().and_then(|h| h.into_string().ok());
+    match args.command {
+        Some(Command::ImportRelica {
+            file,
+            device,
+            origin,
+            dry_run,
+        }) => {
+            let device_id = device.unwrap_or_else(|| config.device.id.clone());
+            let origin = origin.unwrap_or_else(|| device_id.clone());
+            return run_import_relica(&pool, &file, &origin, device_id, dry_run).await;
+        }
+        Some(Command::Status) => return run_status(&pool, config.device.id.clone()).await,
+        Some(Command::GenerateDeviceKey | Command::EncryptSecret { .. }) | None => {}
+    }
 
-    let device = db::upsert_device(
-        &pool,
-        config.device.id.clone(),
-        config.device.id.clone(),
-        platform.to_string(),
-        hostname.clone(),
-    )
-    .await?;
+    info!("Registering device...");
+    let device = db::register_device(&pool, config.device.id.clone()).await?;
     debug!("Device registered: {} ({})", device.name, device.platform);
 
     info!("Loading remote configuration from database...");
@@ -101,15 +267,18 @@ async fn run(args: Args) -> error::Result<()> {
     debug!("Loaded {} schedules", remote_config.schedules.len());
     debug!("Loaded {} settings", remote_config.settings.len());
 
-    let repo_url = remote_config
-        .repository_url()
-        .filter(|url| !url.is_empty())
-        .ok_or_else(|| {
-            error::ConfigError::ValidationFailed(
-                "Repository URL is not configured in database settings".to_string(),
-            )
-        })?;
-    debug!("Repository URL: {}", repo_url);
+    if let Some(run_id) = args.cancel {
+        return run_cancel(
+            &pool,
+            &journal,
+            &config.device.id,
+            run_id,
+            args.cancel_reason,
+        )
+        .await;
+    }
+
+    check_destinations(&remote_config)?;
 
     if let Some(job_id) = args.test_backup {
         info!("========================================");
@@ -128,41 +297,145 @@ async fn run(args: Args) -> error::Result<()> {
 
         let trace_id = uuid::Uuid::new_v4().to_string();
 
-        match backup::execute_backup(&job, &remote_config, &pool, trace_id).await {
-            Ok(run_id) => {
-                info!("Backup completed successfully");
-                info!("Run ID: {}", run_id);
-
-                let run = db::get_recent_runs(&pool, job.device_id, 1)
-                    .await?
-                    .into_iter()
-                    .next()
-                    .ok_or_else(|| error::DatabaseError::QueryFailed(sqlx::Error::RowNotFound))?;
-
-                info!("========================================");
-                info!("Backup Results");
-                info!("========================================");
-                info!("Status: {}", run.status);
-                info!("Duration: {} seconds", run.duration_seconds.unwrap_or(0));
-                info!("Files new: {}", run.files_new.unwrap_or(0));
-                info!("Files changed: {}", run.files_changed.unwrap_or(0));
-                info!("Files unmodified: {}", run.files_unmodified.unwrap_or(0));
-                info!(
-                    "Data added: {} MB",
-                    run.data_added_bytes.unwrap_or(0) / 1024 / 1024
-                );
-                info!("Snapshot ID: {}", run.snapshot_id.unwrap_or_default());
-                info!("========================================");
-
-                return Ok(());
-            }
-            Err(e) => {
-                eprintln!("Backup failed: {}", e);
-                return Err(e);
+        let registry = RunRegistry::new();
+        let mut result = Ok(());
+
+        for repository in remote_config.destinations(job.id) {
+            info!("Repository: {}", backup::repository_label(repository));
+
+            match backup::execute_backup(
+                &job,
+                &remote_config,
+                &pool,
+                &journal,
+                &registry,
+                repository,
+                "manual",
+                trace_id.clone(),
+            )
+            .await
+            {
+                Ok(run_id) => {
+                    info!("Backup completed");
+                    info!("Run ID: {}", run_id);
+                    print_backup_results(&pool, &journal, run_id).await?;
+                }
+                Err(e) => {
+                    eprintln!("Backup failed: {}", e);
+                    if result.is_ok() {
+                        result = Err(e);
+                    }
+                }
             }
         }
+
+        return result;
+    }
+
+    if let (Some(job_id), Some(target)) = (args.restore, args.restore_target) {
+        let options = RestoreOptions {
+            snapshot: args
+                .restore_snapshot
+                .map(SnapshotSelector::Id)
+                .unwrap_or(SnapshotSelector::Latest),
+            target,
+            include: args.restore_include,
+            exclude: args.restore_exclude,
+            overwrite: args.restore_overwrite,
+        };
+
+        return run_restore(
+            &pool,
+            &remote_config,
+            job_id,
+            args.restore_repository,
+            options,
+        )
+        .await;
+    }
+
+    if let Err(e) = cache.save(&remote_config) {
+        warn!("Failed to write configuration cache: {}", e);
+    }
+
+    run_client(
+        pool,
+        &config,
+        &api_token_path,
+        remote_config,
+        cache,
+        journal,
+        true,
+    )
+    .await
+}
+
+fn check_destinations(remote_config: &config::remote::RemoteConfig) -> error::Result<()> {
+    // Jobs without repositories back up to the repository configured in settings
+    let destinations = remote_config.device_destinations();
+    if destinations.iter().any(Option::is_none) {
+        let repo_url = remote_config
+            .repository_url()
+            .filter(|url| !url.is_empty())
+            .ok_or_else(|| {
+                error::ConfigError::ValidationFailed(
+                    "Repository URL is not configured in database settings".to_string(),
+                )
+            })?;
+        debug!("Repository URL: {}", repo_url);
+    }
+    for repository in destinations {
+        // Fails on credentials that do not match the backend of the repository
+        backup::backend::BackendSettings::resolve(remote_config, repository)?;
+        if let Some(repository) = repository {
+            debug!("Repository {}: {}", repository.name, repository.url);
+        }
     }
 
+    Ok(())
+}
+
+async fn run_offline(
+    config: &LocalConfig,
+    api_token_path: &Path,
+    cache: ConfigCache,
+    journal: RunJournal,
+    database_url: String,
+    connect_error: error::AppError,
+) -> error::Result<()> {
+    let (remote_config, saved_at) = match cache.load()? {
+        Some(cached) => cached,
+        None => return Err(connect_error),
+    };
+
+    warn!(
+        "Database is unreachable ({}), starting with the configuration cached at {}",
+        connect_error, saved_at
+    );
+    check_destinations(&remote_config)?;
+
+    let pool = db::create_lazy_pool(database_url)?;
+    run_client(
+        pool,
+        config,
+        api_token_path,
+        remote_config,
+        cache,
+        journal,
+        false,
+    )
+    .await
+}
+
+async fn run_client(
+    pool: sqlx::PgPool,
+    config: &LocalConfig,
+    api_token_path: &Path,
+    remote_config: config::remote::RemoteConfig,
+    cache: ConfigCache,
+    journal: RunJournal,
+    connected: bool,
+) -> error::Result<()> {
     info!("========================================");
     info!("Starting scheduler and job executor");
     info!("========================================");
@@ -170,21 +443,56 @@ async fn run(args: Args) -> error::Result<()> {
     let pool_arc = Arc::new(pool);
     let config_arc = Arc::new(Mutex::new(remote_config));
 
-    let max_concurrent = config_arc
-        .lock()
-        .await
-        .get_setting("max_concurrent_backups")
-        .and_then(|s| s.parse().ok())
-        .unwrap_or(1);
+    let max_concurrent = config_arc.lock().await.max_concurrent_backups();
 
-    let (scheduler, job_queue_rx) = Scheduler::new(
+    let events = EventBus::new();
+
+    let job_queue = JobQueue::new(pool_arc.clone(), config.device.id.clone(), events.clone());
+
+    let scheduler = Scheduler::new(
         pool_arc.clone(),
         config_arc.clone(),
         config.device.id.clone(),
+        job_queue.clone(),
+        events.clone(),
     );
     let scheduler_arc = Arc::new(scheduler);
 
-    let executor = Arc::new(JobExecutor::new(pool_arc, config_arc, max_concurrent));
+    let run_registry = RunRegistry::with_events(events.clone());
+
+    let config_sync = ConfigSync::new(
+        pool_arc.clone(),
+        config_arc.clone(),
+        scheduler_arc.clone(),
+        config.device.id.clone(),
+        cache,
+        connected,
+        events.clone(),
+    );
+
+    let api_auth = ApiAuth::from_config(config, api_token_path)?;
+    info!("HTTP API token: {}", api_token_path.display());
+
+    let api_state = AppState {
+        pool: pool_arc.clone(),
+        config: config_arc.clone(),
+        scheduler: scheduler_arc.clone(),
+        registry: run_registry.clone(),
+        journal: journal.clone(),
+        events,
+        auth: Arc::new(api_auth),
+        device_id: config.device.id.clone(),
+    };
+
+    let executor = Arc::new(JobExecutor::new(
+        pool_arc,
+        config_arc,
+        config.device.id.clone(),
+        run_registry,
+        journal,
+        job_queue,
+        max_concurrent,
+    ));
 
     let scheduler_handle = {
         let scheduler = scheduler_arc.clone();
@@ -196,13 +504,23 @@ async fn run(args: Args) -> error::Result<()> {
     };
 
     let executor_handle = tokio::spawn(async move {
-        if let Err(e) = executor.start(job_queue_rx).await {
+        if let Err(e) = executor.start().await {
             error!("Executor error: {}", e);
         }
     });
 
+    let config_sync_handle = tokio::spawn(config_sync.start());
+
+    // The backups keep running when the API cannot be served, e.g. because the port is taken
+    let http_bind = config.client.http_bind.clone();
+    tokio::spawn(async move {
+        if let Err(e) = api::server::serve(&http_bind, api_state).await {
+            error!("HTTP API error: {}", e);
+        }
+    });
+
     info!("========================================");
-    info!("Phase 4 complete - scheduler running");
+    info!("Phase 5 complete - scheduler and HTTP API running");
     info!("========================================");
 
     tokio::select! {
@@ -212,6 +530,9 @@ async fn run(args: Args) -> error::Result<()> {
         _ = executor_handle => {
             info!("Executor task completed");
         }
+        _ = config_sync_handle => {
+            info!("Configuration sync task completed");
+        }
         _ = tokio::signal::ctrl_c() => {
             info!("Received shutdown signal");
         }
@@ -221,6 +542,241 @@ async fn run(args: Args) -> error::Result<()> {
     Ok(())
 }
 
+async fn print_backup_results(
+    pool: &sqlx::PgPool,
+    journal: &RunJournal,
+    run_id: i32,
+) -> error::Result<()> {
+    let run = journal
+        .get_run(pool, run_id)
+        .await?
+        .ok_or_else(|| error::DatabaseError::QueryFailed(sqlx::Error::RowNotFound))?;
+
+    info!("========================================");
+    info!("Backup Results");
+    info!("========================================");
+    info!("Status: {}", run.status);
+    info!("Duration: {} seconds", run.duration_seconds.unwrap_or(0));
+    info!("Files new: {}", run.files_new.unwrap_or(0));
+    info!("Files changed: {}", run.files_changed.unwrap_or(0));
+    info!("Files unmodified: {}", run.files_unmodified.unwrap_or(0));
+    info!(
+        "Data added: {} MB",
+        run.data_added_bytes.unwrap_or(0) / 1024 / 1024
+    );
+    info!(
+        "Snapshot ID: {}",
+        run.snapshot_id.as_deref().unwrap_or_default()
+    );
+    if run.is_partial() {
+        let failed_paths: Vec<FailedPath> = run
+            .failed_paths
+            .and_then(|paths| serde_json::from_value(paths).ok())
+            .unwrap_or_default();
+        warn!("Unreadable files: {}", failed_paths.len());
+        for failed in failed_paths {
+            warn!("  {}: {}", failed.path, failed.error);
+        }
+    }
+    info!("========================================");
+
+    Ok(())
+}
+
+async fn run_restore(
+    pool: &sqlx::PgPool,
+    remote_config: &config::remote::RemoteConfig,
+    job_id: Uuid,
+    repository_name: Option<String>,
+    options: RestoreOptions,
+) -> error::Result<()> {
+    info!("========================================");
+    info!("Restore Mode");
+    info!("========================================");
+
+    let job = db::get_job_by_id(pool, job_id).await?.ok_or_else(|| {
+        error::AppError::Backup(error::BackupError::ConfigurationError(format!(
+            "Job with ID {} not found",
+            job_id
+        )))
+    })?;
+
+    let destinations = remote_config.destinations(job.id);
+    let repository = match &repository_name {
+        None => destinations.first().copied().flatten(),
+        Some(name) => {
+            let repository = destinations
+                .iter()
+                .copied()
+                .flatten()
+                .find(|r| &r.name == name)
+                .ok_or_else(|| {
+                    error::BackupError::ConfigurationError(format!(
+                        "Repository '{}' is not a destination of job {}",
+                        name, job.id
+                    ))
+                })?;
+            Some(repository)
+        }
+    };
+
+    info!("Job: {} ({})", job.name, job.id);
+    info!("Repository: {}", backup::repository_label(repository));
+    info!("Target: {}", options.target.display());
+    info!("Overwrite policy: {}", options.overwrite);
+
+    let trace_id = Uuid::new_v4().to_string();
+
+    match backup::restore::execute_restore(
+        &job,
+        remote_config,
+        pool,
+        repository,
+        &options,
+        trace_id,
+    )
+    .await
+    {
+        Ok(restore_id) => {
+            let restore = db::get_restore(pool, restore_id)
+                .await?
+                .ok_or_else(|| error::DatabaseError::QueryFailed(sqlx::Error::RowNotFound))?;
+
+            info!("========================================");
+            info!("Restore Results");
+            info!("========================================");
+            info!("Restore ID: {}", restore.id);
+            info!("Status: {}", restore.status);
+            info!("Snapshot ID: {}", restore.snapshot_id.unwrap_or_default());
+            info!(
+                "Duration: {} seconds",
+                restore.duration_seconds.unwrap_or(0)
+            );
+            info!("Files restored: {}", restore.files_restored.unwrap_or(0));
+            info!("Files skipped: {}", restore.files_skipped.unwrap_or(0));
+            info!(
+                "Data restored: {} MB",
+                restore.bytes_restored.unwrap_or(0) / 1024 / 1024
+            );
+            info!("========================================");
+            Ok(())
+        }
+        Err(e) => {
+            eprintln!("Restore failed: {}", e);
+            Err(e)
+        }
+    }
+}
+
+async fn run_import_relica(
+    pool: &sqlx::PgPool,
+    file: &std::path::Path,
+    origin: &str,
+    device_id: String,
+    dry_run: bool,
+) -> error::Result<()> {
+    info!("========================================");
+    info!("Relica Import{}", if dry_run { " (dry run)" } else { "" });
+    info!("========================================");
+
+    let account_info = import::relica::load_account_info(file)?;
+    let plan = import::relica::plan_import(&account_info, origin, &device_id)?;
+
+    info!("Origin: {} ({})", plan.origin.name, plan.origin.id);
+    info!("Device: {}", plan.device_id);
+    info!("Backup jobs: {}", plan.jobs.len());
+
+    for warning in &plan.warnings {
+        warn!("{}", warning);
+    }
+
+    let existing = import::relica::load_existing_state(pool, &plan).await?;
+    for line in import::relica::describe_changes(&plan, &existing) {
+        info!("{}", line);
+    }
+
+    if dry_run {
+        info!("Dry run, no changes written");
+        return Ok(());
+    }
+
+    import::relica::apply_import(pool, &plan, &existing).await?;
+
+    info!("========================================");
+    info!("Import completed");
+    info!("========================================");
+    Ok(())
+}
+
+async fn run_status(pool: &sqlx::PgPool, device_id: String) -> error::Result<()> {
+    let jobs: std::collections::HashMap<Uuid, String> =
+        db::get_jobs_for_device(pool, device_id.clone())
+            .await?
+            .into_iter()
+            .map(|job| (job.id, job.name))
+            .collect();
+    let queue = db::get_job_queue(pool, device_id.clone()).await?;
+
+    info!("========================================");
+    info!("Status of {}", device_id);
+    info!("========================================");
+
+    if queue.is_empty() {
+        info!("No backups running or queued");
+        return Ok(());
+    }
+
+    for entry in &queue {
+        let job_name = jobs.get(&entry.job_id).map(String::as_str).unwrap_or("?");
+        info!(
+            "{:<8} {} ({}), triggered by {}, priority {}, queued {}",
+            entry.status,
+            job_name,
+            entry.job_id,
+            entry.triggered_by,
+            entry.priority,
+            entry.enqueued_at
+        );
+    }
+
+    let pending = queue.iter().filter(|e| e.status == "pending").count();
+    info!("========================================");
+    info!("{} running, {} pending", queue.len() - pending, pending);
+    Ok(())
+}
+
+async fn run_cancel(
+    pool: &sqlx::PgPool,
+    journal: &RunJournal,
+    device_id: &str,
+    run_id: i32,
+    reason: String,
+) -> error::Result<()> {
+    let requested_by = match std::env::var("USER").or_else(|_| std::env::var("USERNAME")) {
+        Ok(user) => format!("cli ({})", user),
+        Err(_) => "cli".to_string(),
+    };
+    let request = CancelRequest {
+        reason,
+        requested_by,
+    };
+
+    // The run is executed by the client, not by this process
+    let registry = RunRegistry::new();
+    if !backup::cancel_backup(pool, journal, &registry, device_id, run_id, request).await? {
+        return Err(error::AppError::Backup(
+            error::BackupError::ConfigurationError(format!("Run {} is not running", run_id)),
+        ));
+    }
+
+    info!(
+        "Cancellation of run {} requested, the client executing it will stop restic",
+        run_id
+    );
+
+    Ok(())
+}
+
 fn setup_logging(config: &LocalConfig) -> error::Result<()> {
     let file_appender = tracing_appender::rolling::daily(
         std::path::Path::new(&config.client.log_file)
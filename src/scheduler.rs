@@ -1,19 +1,30 @@
+pub mod calendar;
+pub mod config_sync;
 pub mod executor;
+pub mod maintenance;
 pub mod missed_runs;
+pub mod queue;
 pub mod schedule_calc;
+pub mod timezone;
 
+use crate::backup::{check, repository_label, retention};
 use crate::config::remote::RemoteConfig;
 use crate::db;
-use crate::db::models::Schedule;
+use crate::db::models::{QueuedJob, Schedule};
 use crate::error::Result;
+use crate::events::EventBus;
 use chrono::Utc;
-use executor::JobExecution;
+use maintenance::{is_maintenance_due, MaintenanceTask, MaintenanceWindow};
+use missed_runs::{count_missed_interval_runs, due_action, CatchupPolicy, DueAction};
+use queue::JobQueue;
 use schedule_calc::{calculate_next_run, is_due};
 use sqlx::PgPool;
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use tokio::sync::{mpsc, Mutex};
-use tokio::time::{interval, Duration};
+use timezone::ScheduleTimezone;
+use tokio::sync::Mutex;
+use tokio::time::{interval, Duration, MissedTickBehavior};
 use tracing::{debug, error, info, warn};
 use uuid::Uuid;
 
@@ -21,11 +32,13 @@ const SCHEDULER_CHECK_INTERVAL_SECONDS: u64 = 60;
 
 pub struct Scheduler {
     pool: Arc<PgPool>,
-    #[allow(dead_code)]
     config: Arc<Mutex<RemoteConfig>>,
     device_id: String,
     schedules: Arc<Mutex<HashMap<i32, Schedule>>>,
-    job_queue_tx: mpsc::Sender<JobExecution>,
+    loaded_timezone: Mutex<Option<String>>,
+    job_queue: JobQueue,
+    maintenance_running: Arc<AtomicBool>,
+    events: EventBus,
 }
 
 impl Scheduler {
@@ -33,18 +46,19 @@ impl Scheduler {
         pool: Arc<PgPool>,
         config: Arc<Mutex<RemoteConfig>>,
         device_id: String,
-    ) -> (Self, mpsc::Receiver<JobExecution>) {
-        let (tx, rx) = mpsc::channel(100);
-
-        let scheduler = Self {
+        job_queue: JobQueue,
+        events: EventBus,
+    ) -> Self {
+        Self {
             pool,
             config,
             device_id,
             schedules: Arc::new(Mutex::new(HashMap::new())),
-            job_queue_tx: tx,
-        };
-
-        (scheduler, rx)
+            loaded_timezone: Mutex::new(None),
+            job_queue,
+            maintenance_running: Arc::new(AtomicBool::new(false)),
+            events,
+        }
     }
 
     pub async fn start(self: Arc<Self>) -> Result<()> {
@@ -53,38 +67,88 @@ impl Scheduler {
         self.reload_schedules().await?;
 
         let mut check_interval = interval(Duration::from_secs(SCHEDULER_CHECK_INTERVAL_SECONDS));
+        check_interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let mut last_check = Utc::now();
 
         loop {
             check_interval.tick().await;
 
+            // The wall clock moves on while the system sleeps, the interval does not
+            let now = Utc::now();
+            let paused = (now - last_check).num_seconds();
+            if paused > 2 * SCHEDULER_CHECK_INTERVAL_SECONDS as i64 {
+                info!(
+                    paused_seconds = paused,
+                    "Scheduler resumed after a pause, catching up missed runs"
+                );
+            }
+            last_check = now;
+
             if let Err(e) = self.check_schedules().await {
                 error!("Error checking schedules: {}", e);
             }
+
+            if let Err(e) = self.check_maintenance().await {
+                error!("Error checking repository maintenance: {}", e);
+            }
         }
     }
 
+    // Unchanged schedules keep their state, so a reload never delays or repeats their next run
     pub async fn reload_schedules(&self) -> Result<()> {
-        info!("Reloading schedules from database");
+        let (config_schedules, device_timezone) = {
+            let config = self.config.lock().await;
+            (
+                config.schedules.clone(),
+                config.timezone().map(str::to_string),
+            )
+        };
 
-        let db_schedules = db::get_schedules_for_device(&self.pool, self.device_id.clone()).await?;
+        let mut loaded_timezone = self.loaded_timezone.lock().await;
+        let timezone_changed = *loaded_timezone != device_timezone;
 
         let mut schedules = self.schedules.lock().await;
-        schedules.clear();
+        let mut reloaded = HashMap::new();
+        let (mut added, mut changed) = (0, 0);
 
-        for mut schedule in db_schedules {
+        for mut schedule in config_schedules {
             let now = Utc::now();
+            let tz = ScheduleTimezone::for_schedule(&schedule, device_timezone.as_deref())?;
+
+            let recalculate = match schedules.get(&schedule.id) {
+                Some(current) if current.same_definition(&schedule) => {
+                    if timezone_changed && schedule.timezone.is_none() {
+                        schedule.last_run_at = current.last_run_at;
+                        changed += 1;
+                        true
+                    } else {
+                        reloaded.insert(schedule.id, current.clone());
+                        continue;
+                    }
+                }
+                Some(current) => {
+                    info!(
+                        schedule_id = schedule.id,
+                        job_id = %schedule.job_id,
+                        "Schedule changed"
+                    );
+                    schedule.last_run_at = current.last_run_at;
+                    changed += 1;
+                    true
+                }
+                None => {
+                    added += 1;
+                    // Missed runs keep their past next_run_at for the catch-up policy
+                    schedule.next_run_at.is_none()
+                }
+            };
 
-            if schedule.next_run_at.is_none() {
-                let next_run = calculate_next_run(&schedule, schedule.last_run_at, now)?;
+            if recalculate {
+                let next_run = calculate_next_run(&schedule, schedule.last_run_at, now, &tz)?;
                 schedule.next_run_at = Some(next_run);
 
-                if let Err(e) = db::update_schedule_last_run(
-                    &self.pool,
-                    schedule.job_id,
-                    schedule.last_run_at.unwrap_or(now),
-                    Some(next_run),
-                )
-                .await
+                if let Err(e) =
+                    db::update_schedule_next_run(&self.pool, schedule.id, next_run).await
                 {
                     warn!(
                         schedule_id = schedule.id,
@@ -97,14 +161,37 @@ impl Scheduler {
                 schedule_id = schedule.id,
                 job_id = %schedule.job_id,
                 schedule_type = %schedule.schedule_type,
+                timezone = %tz,
                 next_run = ?schedule.next_run_at,
+                next_run_local = ?schedule.next_run_at.map(|t| tz.format(t)),
                 "Loaded schedule"
             );
 
-            schedules.insert(schedule.id, schedule);
+            reloaded.insert(schedule.id, schedule);
         }
 
-        info!("Loaded {} schedules", schedules.len());
+        let mut removed = 0;
+        for schedule in schedules.values() {
+            if !reloaded.contains_key(&schedule.id) {
+                info!(
+                    schedule_id = schedule.id,
+                    job_id = %schedule.job_id,
+                    "Schedule removed"
+                );
+                removed += 1;
+            }
+        }
+
+        *schedules = reloaded;
+        *loaded_timezone = device_timezone;
+
+        info!(
+            schedules = schedules.len(),
+            added = added,
+            changed = changed,
+            removed = removed,
+            "Loaded schedules"
+        );
 
         Ok(())
     }
@@ -112,81 +199,250 @@ impl Scheduler {
     async fn check_schedules(&self) -> Result<()> {
         let now = Utc::now();
         let schedules = self.schedules.lock().await.clone();
+        let config = self.config.lock().await.clone();
 
         debug!("Checking {} schedules", schedules.len());
 
         for schedule in schedules.values() {
-            if !schedule.enabled {
+            if !schedule.enabled || !is_due(schedule, now) {
                 continue;
             }
 
-            if is_due(schedule, now) {
-                info!(
+            let policy = CatchupPolicy::for_schedule(schedule, &config);
+            let result =
+                match due_action(schedule, policy, now, Some(config.catchup_grace_minutes())) {
+                    DueAction::Run => {
+                        info!(
+                            schedule_id = schedule.id,
+                            job_id = %schedule.job_id,
+                            "Schedule is due, queueing job"
+                        );
+                        self.queue_job(schedule, "schedule").await
+                    }
+                    DueAction::CatchUp => {
+                        info!(
+                            schedule_id = schedule.id,
+                            job_id = %schedule.job_id,
+                            missed_run = ?schedule.next_run_at,
+                            missed_interval_runs =
+                                count_missed_interval_runs(schedule, schedule.last_run_at, now),
+                            policy = ?policy,
+                            "Catching up missed run, queueing job"
+                        );
+                        self.queue_job(schedule, "catchup").await
+                    }
+                    DueAction::Skip => {
+                        info!(
+                            schedule_id = schedule.id,
+                            job_id = %schedule.job_id,
+                            missed_run = ?schedule.next_run_at,
+                            policy = ?policy,
+                            "Skipping missed run"
+                        );
+                        self.skip_missed_run(schedule, &config).await
+                    }
+                };
+
+            if let Err(e) = result {
+                error!(
                     schedule_id = schedule.id,
                     job_id = %schedule.job_id,
-                    "Schedule is due, queueing job"
+                    "Failed to process due schedule: {}",
+                    e
                 );
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn skip_missed_run(&self, schedule: &Schedule, config: &RemoteConfig) -> Result<()> {
+        let tz = ScheduleTimezone::for_schedule(schedule, config.timezone())?;
+        let next_run = calculate_next_run(schedule, None, Utc::now(), &tz)?;
 
-                if let Err(e) = self.queue_job(schedule).await {
+        db::update_schedule_next_run(&self.pool, schedule.id, next_run).await?;
+
+        if let Some(s) = self.schedules.lock().await.get_mut(&schedule.id) {
+            s.next_run_at = Some(next_run);
+        }
+
+        info!(
+            schedule_id = schedule.id,
+            job_id = %schedule.job_id,
+            next_run = %next_run,
+            next_run_local = %tz.format(next_run),
+            "Next run scheduled"
+        );
+
+        Ok(())
+    }
+
+    // Tasks never run concurrently, as prune needs an exclusive lock on the repository
+    async fn check_maintenance(&self) -> Result<()> {
+        let config = self.config.lock().await.clone();
+
+        let intervals = [
+            (MaintenanceTask::Prune, config.prune_interval_hours()),
+            (MaintenanceTask::Check, config.check_interval_hours()),
+        ];
+        if intervals
+            .iter()
+            .all(|(_, interval_hours)| *interval_hours == 0)
+        {
+            return Ok(());
+        }
+
+        // The window is in the time zone of the device's schedules, the local time without one
+        if let Some(window) = config.maintenance_window() {
+            let window = MaintenanceWindow::parse(window)?;
+            let timezone = match config.timezone() {
+                Some(name) => ScheduleTimezone::parse(name)?,
+                None => ScheduleTimezone::Local,
+            };
+            if !window.is_open(Utc::now(), timezone) {
+                return Ok(());
+            }
+        }
+
+        if self.maintenance_running.load(Ordering::SeqCst) {
+            debug!("Repository maintenance already in progress");
+            return Ok(());
+        }
+
+        let mut due_task = None;
+        'repositories: for repository in config.device_destinations() {
+            for (task, interval_hours) in intervals {
+                let last_run = db::get_last_maintenance_run(
+                    &self.pool,
+                    task.as_str().to_string(),
+                    repository.map(|r| r.id),
+                )
+                .await?;
+                if is_maintenance_due(last_run.map(|r| r.start_time), interval_hours, Utc::now()) {
+                    due_task = Some((task, repository.cloned()));
+                    break 'repositories;
+                }
+            }
+        }
+
+        let (task, repository) = match due_task {
+            Some(due_task) => due_task,
+            None => return Ok(()),
+        };
+
+        if self.maintenance_running.swap(true, Ordering::SeqCst) {
+            return Ok(());
+        }
+
+        info!(
+            task = task.as_str(),
+            repository = repository_label(repository.as_ref()),
+            "Repository maintenance is due, starting task"
+        );
+
+        let pool = self.pool.clone();
+        let device_id = self.device_id.clone();
+        let maintenance_running = self.maintenance_running.clone();
+        let events = self.events.clone();
+
+        tokio::spawn(async move {
+            let trace_id = Uuid::new_v4().to_string();
+
+            let result = match task {
+                MaintenanceTask::Prune => {
+                    retention::execute_prune(
+                        &config,
+                        &pool,
+                        device_id,
+                        repository.as_ref(),
+                        &trace_id,
+                    )
+                    .await
+                }
+                MaintenanceTask::Check => {
+                    check::execute_check(
+                        &config,
+                        &pool,
+                        &events,
+                        device_id,
+                        repository.as_ref(),
+                        &trace_id,
+                    )
+                    .await
+                }
+            };
+
+            match result {
+                Ok(maintenance_run_id) => {
+                    info!(
+                        trace_id = trace_id,
+                        task = task.as_str(),
+                        repository = repository_label(repository.as_ref()),
+                        maintenance_run_id = maintenance_run_id,
+                        "Repository maintenance completed"
+                    );
+                }
+                Err(e) => {
                     error!(
-                        schedule_id = schedule.id,
-                        job_id = %schedule.job_id,
-                        "Failed to queue job: {}",
+                        trace_id = trace_id,
+                        task = task.as_str(),
+                        repository = repository_label(repository.as_ref()),
+                        "Repository maintenance failed: {}",
                         e
                     );
                 }
             }
-        }
+
+            maintenance_running.store(false, Ordering::SeqCst);
+        });
 
         Ok(())
     }
 
-    async fn queue_job(&self, schedule: &Schedule) -> Result<()> {
-        let execution = JobExecution {
-            job_id: schedule.job_id,
-            triggered_by: "schedule".to_string(),
-        };
+    async fn queue_job(&self, schedule: &Schedule, triggered_by: &str) -> Result<()> {
+        self.job_queue.push(schedule.job_id, triggered_by).await?;
 
-        self.job_queue_tx
-            .send(execution)
-            .await
-            .map_err(|e| crate::error::SchedulerError::JobNotFound(e.to_string()))?;
+        let device_timezone = self.config.lock().await.timezone().map(str::to_string);
+        let tz = ScheduleTimezone::for_schedule(schedule, device_timezone.as_deref())?;
 
         let now = Utc::now();
-        let next_run = calculate_next_run(schedule, Some(now), now)?;
-
-        db::update_schedule_last_run(&self.pool, schedule.job_id, now, Some(next_run)).await?;
+        let next_run = calculate_next_run(schedule, Some(now), now, &tz)?;
 
-        let mut schedules = self.schedules.lock().await;
-        if let Some(s) = schedules.get_mut(&schedule.id) {
+        // Updated first, so a job queued while the database is unavailable is not queued again
+        if let Some(s) = self.schedules.lock().await.get_mut(&schedule.id) {
             s.last_run_at = Some(now);
             s.next_run_at = Some(next_run);
         }
 
-        debug!(
+        if let Err(e) =
+            db::update_schedule_last_run(&self.pool, schedule.job_id, now, Some(next_run)).await
+        {
+            warn!(
+                schedule_id = schedule.id,
+                "Failed to update schedule last_run_at: {}", e
+            );
+        }
+
+        info!(
             schedule_id = schedule.id,
             job_id = %schedule.job_id,
             next_run = %next_run,
+            next_run_local = %tz.format(next_run),
             "Updated schedule after queueing"
         );
 
         Ok(())
     }
 
-    #[allow(dead_code)]
-    pub async fn trigger_manual_backup(&self, job_id: Uuid) -> Result<()> {
-        info!(job_id = %job_id, "Triggering manual backup");
-
-        let execution = JobExecution {
-            job_id,
-            triggered_by: "manual".to_string(),
-        };
+    pub async fn schedules(&self) -> Vec<Schedule> {
+        let mut schedules: Vec<Schedule> = self.schedules.lock().await.values().cloned().collect();
+        schedules.sort_by_key(|schedule| schedule.id);
+        schedules
+    }
 
-        self.job_queue_tx
-            .send(execution)
-            .await
-            .map_err(|e| crate::error::SchedulerError::JobNotFound(e.to_string()))?;
+    pub async fn trigger_manual_backup(&self, job_id: Uuid) -> Result<QueuedJob> {
+        info!(job_id = %job_id, "Triggering manual backup");
 
-        Ok(())
+        self.job_queue.push(job_id, "manual").await
     }
 }
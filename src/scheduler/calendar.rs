@@ -0,0 +1,212 @@
+use crate::db::models::CalendarSchedule;
+use crate::error::{Result, SchedulerError};
+use crate::scheduler::timezone::ScheduleTimezone;
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveTime, Utc};
+use std::fmt;
+
+const DAY_NAMES: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+
+// Covers a leap day in the worst case
+const SEARCH_DAYS: i64 = 8 * 366;
+
+fn invalid(msg: String) -> SchedulerError {
+    SchedulerError::InvalidCalendar(msg)
+}
+
+impl CalendarSchedule {
+    pub fn times(&self) -> Result<Vec<NaiveTime>> {
+        let mut times = Vec::new();
+
+        for value in &self.times_of_day {
+            let time = NaiveTime::parse_from_str(value.trim(), "%H:%M")
+                .map_err(|_| invalid(format!("Invalid time of day: {}", value)))?;
+            times.push(time);
+        }
+
+        times.sort_unstable();
+        times.dedup();
+        Ok(times)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.times()?.is_empty() {
+            return Err(invalid("No times of day".to_string()).into());
+        }
+
+        let checks: [(&[u32], std::ops::RangeInclusive<u32>, &str); 3] = [
+            (&self.days_of_week, 0..=6, "day of week"),
+            (&self.days_of_month, 1..=31, "day of month"),
+            (&self.months_of_year, 1..=12, "month"),
+        ];
+
+        for (values, valid, name) in checks {
+            if let Some(value) = values.iter().find(|v| !valid.contains(v)) {
+                return Err(invalid(format!("Invalid {}: {}", name, value)).into());
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn matches_date(&self, date: NaiveDate) -> bool {
+        let matches = |values: &[u32], value: u32| values.is_empty() || values.contains(&value);
+
+        matches(&self.days_of_week, date.weekday().num_days_from_sunday())
+            && matches(&self.days_of_month, date.day())
+            && matches(&self.months_of_year, date.month())
+    }
+
+    pub fn next_after(&self, after: DateTime<Utc>, tz: &ScheduleTimezone) -> Result<DateTime<Utc>> {
+        self.validate()?;
+        let times = self.times()?;
+
+        let start = tz.local_time(after).date();
+
+        for offset in 0..SEARCH_DAYS {
+            let date = start + Duration::days(offset);
+            if !self.matches_date(date) {
+                continue;
+            }
+
+            for time in &times {
+                if let Some(candidate) = tz.resolve_local(date.and_time(*time)) {
+                    if candidate > after {
+                        return Ok(candidate);
+                    }
+                }
+            }
+        }
+
+        Err(invalid(format!("No date matches the calendar {}", self)).into())
+    }
+}
+
+impl fmt::Display for CalendarSchedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let join = |values: &[u32]| {
+            values
+                .iter()
+                .map(|v| v.to_string())
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+
+        write!(f, "at {}", self.times_of_day.join(","))?;
+
+        if !self.days_of_week.is_empty() {
+            let days: Vec<&str> = self
+                .days_of_week
+                .iter()
+                .map(|d| DAY_NAMES.get(*d as usize).copied().unwrap_or("?"))
+                .collect();
+            write!(f, " on {}", days.join(","))?;
+        }
+        if !self.days_of_month.is_empty() {
+            write!(f, " on day {}", join(&self.days_of_month))?;
+        }
+        if !self.months_of_year.is_empty() {
+            write!(f, " in month {}", join(&self.months_of_year))?;
+        }
+        if self.flexible {
+            write!(f, " (flexible)")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    const UTC: ScheduleTimezone = ScheduleTimezone::Utc;
+
+    fn calendar(times: &[&str], days_of_week: &[u32]) -> CalendarSchedule {
+        CalendarSchedule {
+            times_of_day: times.iter().map(|t| t.to_string()).collect(),
+            days_of_week: days_of_week.to_vec(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_next_after_daily_times() {
+        let calendar = calendar(&["23:00", "13:00"], &[]);
+        let after = Utc.with_ymd_and_hms(2025, 1, 1, 14, 0, 0).unwrap();
+
+        let next = calendar.next_after(after, &UTC).unwrap();
+        assert_eq!(next, Utc.with_ymd_and_hms(2025, 1, 1, 23, 0, 0).unwrap());
+
+        let next = calendar.next_after(next, &UTC).unwrap();
+        assert_eq!(next, Utc.with_ymd_and_hms(2025, 1, 2, 13, 0, 0).unwrap());
+    }
+
+    #[test]
+    fn test_next_after_days_of_week() {
+        // 2025-01-01 is a Wednesday
+        let calendar = calendar(&["01:30"], &[0, 6]);
+        let after = Utc.with_ymd_and_hms(2025, 1, 1, 12, 0, 0).unwrap();
+
+        let next = calendar.next_after(after, &UTC).unwrap();
+        assert_eq!(next, Utc.with_ymd_and_hms(2025, 1, 4, 1, 30, 0).unwrap());
+    }
+
+    #[test]
+    fn test_next_after_days_of_month_and_months() {
+        let end_of_month = CalendarSchedule {
+            days_of_month: vec![31],
+            months_of_year: vec![2, 3],
+            ..calendar(&["12:00"], &[])
+        };
+        let after = Utc.with_ymd_and_hms(2025, 1, 15, 0, 0, 0).unwrap();
+
+        let next = end_of_month.next_after(after, &UTC).unwrap();
+        assert_eq!(next, Utc.with_ymd_and_hms(2025, 3, 31, 12, 0, 0).unwrap());
+
+        let never = CalendarSchedule {
+            days_of_month: vec![30],
+            months_of_year: vec![2],
+            ..calendar(&["12:00"], &[])
+        };
+        assert!(never.next_after(after, &UTC).is_err());
+    }
+
+    #[test]
+    fn test_next_after_in_time_zone() {
+        let prague = ScheduleTimezone::parse("Europe/Prague").unwrap();
+        let calendar = calendar(&["02:30"], &[]);
+
+        let after = Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+        let next = calendar.next_after(after, &prague).unwrap();
+        assert_eq!(next, Utc.with_ymd_and_hms(2025, 1, 1, 1, 30, 0).unwrap());
+
+        // 02:30 does not exist on 2025-03-30 and runs at 03:30 CEST instead
+        let after = Utc.with_ymd_and_hms(2025, 3, 29, 12, 0, 0).unwrap();
+        let next = calendar.next_after(after, &prague).unwrap();
+        assert_eq!(next, Utc.with_ymd_and_hms(2025, 3, 30, 1, 30, 0).unwrap());
+
+        // 02:30 happens twice on 2025-10-26 and runs only the first time
+        let after = Utc.with_ymd_and_hms(2025, 10, 25, 12, 0, 0).unwrap();
+        let next = calendar.next_after(after, &prague).unwrap();
+        assert_eq!(next, Utc.with_ymd_and_hms(2025, 10, 26, 0, 30, 0).unwrap());
+        let next = calendar.next_after(next, &prague).unwrap();
+        assert_eq!(next, Utc.with_ymd_and_hms(2025, 10, 27, 1, 30, 0).unwrap());
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(calendar(&["13:00"], &[0, 6]).validate().is_ok());
+        assert!(calendar(&[], &[]).validate().is_err());
+        assert!(calendar(&["24:00"], &[]).validate().is_err());
+        assert!(calendar(&["noon"], &[]).validate().is_err());
+        assert!(calendar(&["13:00"], &[7]).validate().is_err());
+    }
+
+    #[test]
+    fn test_display() {
+        let mut calendar = calendar(&["13:00", "23:00"], &[1, 3]);
+        calendar.flexible = true;
+        assert_eq!(calendar.to_string(), "at 13:00,23:00 on Mon,Wed (flexible)");
+    }
+}
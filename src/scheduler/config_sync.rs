@@ -0,0 +1,130 @@
+use crate::config::cache::ConfigCache;
+use crate::config::diff::ConfigDiff;
+use crate::config::load_config_from_db;
+use crate::config::remote::RemoteConfig;
+use crate::db;
+use crate::error::Result;
+use crate::events::{Event, EventBus};
+use crate::scheduler::Scheduler;
+use sqlx::PgPool;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tokio::time::{sleep, Duration};
+use tracing::{debug, info, warn};
+
+const MIN_SYNC_INTERVAL_SECONDS: u64 = 10;
+
+pub struct ConfigSync {
+    pool: Arc<PgPool>,
+    config: Arc<Mutex<RemoteConfig>>,
+    scheduler: Arc<Scheduler>,
+    device_id: String,
+    cache: ConfigCache,
+    prepared: bool,
+    connected: bool,
+    events: EventBus,
+}
+
+impl ConfigSync {
+    pub fn new(
+        pool: Arc<PgPool>,
+        config: Arc<Mutex<RemoteConfig>>,
+        scheduler: Arc<Scheduler>,
+        device_id: String,
+        cache: ConfigCache,
+        connected: bool,
+        events: EventBus,
+    ) -> Self {
+        Self {
+            pool,
+            config,
+            scheduler,
+            device_id,
+            cache,
+            prepared: connected,
+            connected,
+            events,
+        }
+    }
+
+    pub async fn start(mut self) {
+        info!("Configuration sync started");
+
+        loop {
+            let interval = self
+                .config
+                .lock()
+                .await
+                .sync_interval_seconds()
+                .max(MIN_SYNC_INTERVAL_SECONDS);
+            sleep(Duration::from_secs(interval)).await;
+
+            if let Err(e) = self.sync().await {
+                if self.connected {
+                    warn!(
+                        "Database is unreachable, keeping the last-known-good configuration: {}",
+                        e
+                    );
+                    self.connected = false;
+                    self.events
+                        .publish(Event::DatabaseConnectivityChanged { connected: false });
+                } else {
+                    debug!("Database is still unreachable: {}", e);
+                }
+            }
+        }
+    }
+
+    async fn sync(&mut self) -> Result<()> {
+        if !self.prepared {
+            db::run_migrations(&self.pool).await?;
+            db::register_device(&self.pool, self.device_id.clone()).await?;
+            self.prepared = true;
+        }
+
+        let loaded = load_config_from_db(&self.pool, self.device_id.clone()).await?;
+
+        if !self.connected {
+            info!("Database connection restored, reconciling configuration");
+            self.connected = true;
+            self.events
+                .publish(Event::DatabaseConnectivityChanged { connected: true });
+        }
+
+        let diff = ConfigDiff::between(&*self.config.lock().await, &loaded);
+        if diff.is_empty() {
+            debug!("Configuration unchanged");
+            return Ok(());
+        }
+
+        log_changes(&diff);
+
+        *self.config.lock().await = loaded.clone();
+
+        if diff.affects_schedules() {
+            self.scheduler.reload_schedules().await?;
+        }
+
+        if let Err(e) = self.cache.save(&loaded) {
+            warn!("Failed to update configuration cache: {}", e);
+        }
+
+        let changed = diff
+            .categories()
+            .iter()
+            .filter(|(_, changes)| !changes.is_empty())
+            .map(|(part, _)| part.to_string())
+            .collect();
+        self.events.publish(Event::ConfigReloaded { changed });
+
+        Ok(())
+    }
+}
+
+fn log_changes(diff: &ConfigDiff) {
+    for (part, changes) in diff.categories() {
+        if !changes.is_empty() {
+            info!(part = part, "Configuration changed: {}", changes);
+        }
+    }
+}
@@ -1,150 +1,510 @@
 use crate::backup;
+use crate::backup::journal::RunJournal;
+use crate::backup::recovery::{live_runs, recover_stale_runs};
+use crate::backup::registry::{CancelRequest, RunRegistry};
+use crate::backup::repository_label;
 use crate::config::remote::RemoteConfig;
 use crate::db;
+use crate::db::models::{QueuedJob, Repository};
 use crate::error::Result;
+use crate::events::Event;
+use crate::scheduler::queue::JobQueue;
 use sqlx::PgPool;
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
-use tokio::sync::mpsc;
-use tokio::sync::Mutex;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{Mutex, OwnedSemaphorePermit, Semaphore};
+use tokio::time::{interval, Duration};
 use tracing::{debug, error, info, warn};
 use uuid::Uuid;
 
-#[derive(Debug, Clone)]
-pub struct JobExecution {
-    pub job_id: Uuid,
-    pub triggered_by: String,
-}
+const CANCELLATION_CHECK_INTERVAL_SECONDS: u64 = 5;
+
+const STALE_RUN_CHECK_INTERVAL_SECONDS: u64 = 300;
+
+const STARTUP_RETRY_SECONDS: u64 = 30;
+
+const JOURNAL_REPLAY_INTERVAL_SECONDS: u64 = 60;
 
 pub struct JobExecutor {
     pool: Arc<PgPool>,
     config: Arc<Mutex<RemoteConfig>>,
-    running_jobs: Arc<Mutex<HashMap<String, Uuid>>>,
-    max_concurrent_per_device: usize,
+    device_id: String,
+    registry: RunRegistry,
+    journal: RunJournal,
+    job_queue: JobQueue,
+    device_slots: Slots,
+    repository_slots: Mutex<HashMap<String, Arc<Slots>>>,
+    // The queue in the database is only used once the work of the previous client was recovered
+    recovered: AtomicBool,
 }
 
 impl JobExecutor {
     pub fn new(
         pool: Arc<PgPool>,
         config: Arc<Mutex<RemoteConfig>>,
+        device_id: String,
+        registry: RunRegistry,
+        journal: RunJournal,
+        job_queue: JobQueue,
         max_concurrent_per_device: usize,
     ) -> Self {
         Self {
             pool,
             config,
-            running_jobs: Arc::new(Mutex::new(HashMap::new())),
-            max_concurrent_per_device,
+            device_id,
+            registry,
+            journal,
+            job_queue,
+            device_slots: Slots::new(max_concurrent_per_device.max(1)),
+            repository_slots: Mutex::new(HashMap::new()),
+            recovered: AtomicBool::new(false),
         }
     }
 
-    pub async fn start(self: Arc<Self>, mut job_queue: mpsc::Receiver<JobExecution>) -> Result<()> {
+    pub async fn start(self: Arc<Self>) -> Result<()> {
         info!("Job executor started");
 
-        while let Some(execution) = job_queue.recv().await {
+        // Until the database is reachable only jobs queued locally are executed
+        let recovery = self.clone();
+        tokio::spawn(async move {
+            while let Err(e) = recovery.recover_interrupted_work().await {
+                warn!(
+                    "Failed to recover work of the previous client, retrying in {} seconds: {}",
+                    STARTUP_RETRY_SECONDS, e
+                );
+                tokio::time::sleep(Duration::from_secs(STARTUP_RETRY_SECONDS)).await;
+            }
+            recovery.recovered.store(true, Ordering::SeqCst);
+        });
+
+        let watcher = self.clone();
+        tokio::spawn(async move {
+            watcher.watch_run_journal().await;
+        });
+
+        let watcher = self.clone();
+        tokio::spawn(async move {
+            watcher.watch_cancellation_requests().await;
+        });
+
+        let watcher = self.clone();
+        tokio::spawn(async move {
+            watcher.watch_stale_runs().await;
+        });
+
+        let watcher = self.clone();
+        tokio::spawn(async move {
+            watcher.watch_config_reloads().await;
+        });
+
+        loop {
+            let device_slot = self
+                .device_slots
+                .semaphore
+                .clone()
+                .acquire_owned()
+                .await
+                .expect("Backup slots are never closed");
+
+            let entry = self.next_queued_job().await;
+
             let executor = self.clone();
             tokio::spawn(async move {
-                if let Err(e) = executor.execute_job(execution).await {
+                let Some(claim) = executor.registry.claim_job(entry.job_id) else {
+                    drop(device_slot);
+                    executor.requeue_when_released(&entry).await;
+                    return;
+                };
+
+                if let Err(e) = executor.execute_job(&entry).await {
                     error!("Job execution failed: {}", e);
                 }
+                drop(claim);
+
+                // Removed first, so a pending entry of the same job can be taken right away
+                if let Err(e) = executor.job_queue.remove(&entry).await {
+                    error!(
+                        queue_id = entry.id,
+                        "Failed to remove finished job from queue: {}", e
+                    );
+                }
+                drop(device_slot);
             });
         }
+    }
+
+    async fn recover_interrupted_work(&self) -> Result<()> {
+        // Runs finished while the database was unavailable must not be taken for abandoned ones
+        self.replay_run_journal().await?;
+
+        self.recover_stale_runs().await?;
+
+        let requeued = self.job_queue.requeue_interrupted().await?;
+        if requeued > 0 {
+            info!(
+                count = requeued,
+                "Re-queued jobs interrupted by the previous shutdown"
+            );
+        }
 
-        info!("Job executor stopped");
         Ok(())
     }
 
-    async fn execute_job(&self, execution: JobExecution) -> Result<()> {
-        let job = match db::get_job_by_id(&self.pool, execution.job_id).await? {
+    async fn next_queued_job(&self) -> QueuedJob {
+        loop {
+            let next = if self.recovered.load(Ordering::SeqCst) {
+                self.job_queue.next().await
+            } else {
+                Ok(self.job_queue.next_local().await)
+            };
+
+            match next {
+                Ok(Some(entry)) => return entry,
+                Ok(None) => {}
+                Err(e) => warn!("Failed to take next job from queue: {}", e),
+            }
+
+            self.job_queue.wait().await;
+        }
+    }
+
+    // The entry stays queued, so a manual trigger or catch-up for a running job is not lost
+    async fn requeue_when_released(&self, entry: &QueuedJob) {
+        info!(
+            queue_id = entry.id,
+            job_id = %entry.job_id,
+            triggered_by = %entry.triggered_by,
+            "Job is already running, keeping it queued until the running execution ends"
+        );
+
+        self.registry.wait_for_job(entry.job_id).await;
+        if let Err(e) = self.job_queue.requeue(entry).await {
+            error!(queue_id = entry.id, "Failed to return job to queue: {}", e);
+        }
+    }
+
+    async fn execute_job(&self, entry: &QueuedJob) -> Result<()> {
+        let config = self.config.lock().await.clone();
+
+        let job = match db::get_job_by_id(&self.pool, entry.job_id).await {
+            Ok(job) => job,
+            Err(e) if e.is_database_unavailable() => {
+                debug!(
+                    job_id = %entry.job_id,
+                    "Database is unavailable, using the job of the current configuration"
+                );
+                config
+                    .jobs
+                    .iter()
+                    .find(|job| job.id == entry.job_id)
+                    .cloned()
+            }
+            Err(e) => return Err(e),
+        };
+        let job = match job {
             Some(job) => job,
             None => {
-                warn!(job_id = %execution.job_id, "Job not found, skipping execution");
+                warn!(job_id = %entry.job_id, "Job not found, skipping execution");
                 return Ok(());
             }
         };
 
-        if !self.can_execute(&job.device_id).await {
-            warn!(
-                job_id = %execution.job_id,
-                device_id = %job.device_id,
-                "Device has reached max concurrent backups, skipping"
-            );
-            return Ok(());
-        }
-
-        self.mark_running(&job.device_id, execution.job_id).await;
-
         let trace_id = Uuid::new_v4().to_string();
-        let config = self.config.lock().await.clone();
+        let destinations = config.destinations(job.id);
 
         info!(
             trace_id = trace_id,
-            job_id = %execution.job_id,
+            job_id = %entry.job_id,
             job_name = %job.name,
-            triggered_by = %execution.triggered_by,
-            "Executing scheduled backup"
+            triggered_by = %entry.triggered_by,
+            destinations = destinations.len(),
+            "Executing queued backup"
         );
 
-        let result = backup::execute_backup(&job, &config, &self.pool, trace_id.clone()).await;
+        // One destination after another, so the sources are not read several times at once
+        for repository in destinations {
+            let _repository_slot = match self.repository_slots(&config, repository).await {
+                Some(slots) => Some(acquire_slot(&slots, entry.job_id).await),
+                None => None,
+            };
 
-        self.mark_completed(&job.device_id).await;
+            let result = backup::execute_backup(
+                &job,
+                &config,
+                &self.pool,
+                &self.journal,
+                &self.registry,
+                repository,
+                &entry.triggered_by,
+                trace_id.clone(),
+            )
+            .await;
 
-        match result {
-            Ok(run_id) => {
-                info!(
-                    trace_id = trace_id,
-                    job_id = %execution.job_id,
-                    run_id = run_id,
-                    "Backup completed successfully"
-                );
+            match result {
+                Ok(run_id) => {
+                    info!(
+                        trace_id = trace_id,
+                        job_id = %entry.job_id,
+                        repository = repository_label(repository),
+                        run_id = run_id,
+                        "Backup completed"
+                    );
+                }
+                Err(e) => {
+                    error!(
+                        trace_id = trace_id,
+                        job_id = %entry.job_id,
+                        repository = repository_label(repository),
+                        "Backup failed: {}",
+                        e
+                    );
+                }
             }
-            Err(e) => {
-                error!(
-                    trace_id = trace_id,
-                    job_id = %execution.job_id,
-                    "Backup failed: {}",
-                    e
-                );
+        }
+
+        Ok(())
+    }
+
+    async fn watch_cancellation_requests(&self) {
+        let mut check_interval = interval(Duration::from_secs(CANCELLATION_CHECK_INTERVAL_SECONDS));
+
+        loop {
+            check_interval.tick().await;
+
+            let live_runs = live_runs(&self.registry, &self.journal).await;
+            if live_runs.is_empty() {
+                continue;
+            }
+
+            let run_ids = live_runs.keys().copied().collect();
+            let runs = match db::get_requested_cancellations(&self.pool, run_ids).await {
+                Ok(runs) => runs,
+                Err(e) => {
+                    warn!("Failed to check for cancellation requests: {}", e);
+                    continue;
+                }
+            };
+
+            for run in runs {
+                let request = CancelRequest {
+                    reason: run.cancel_reason.unwrap_or_default(),
+                    requested_by: run
+                        .cancel_requested_by
+                        .unwrap_or_else(|| "unknown".to_string()),
+                };
+
+                if let Some(run_id) = live_runs.get(&run.id) {
+                    self.registry.cancel(*run_id, request).await;
+                }
+            }
+        }
+    }
+
+    async fn watch_run_journal(&self) {
+        let mut check_interval = interval(Duration::from_secs(JOURNAL_REPLAY_INTERVAL_SECONDS));
+        check_interval.tick().await;
+
+        loop {
+            check_interval.tick().await;
+
+            if self.journal.is_empty().await {
+                continue;
             }
+
+            if let Err(e) = self.replay_run_journal().await {
+                debug!("Run journal not replayed yet: {}", e);
+            }
+        }
+    }
+
+    async fn replay_run_journal(&self) -> Result<()> {
+        let replayed = self.journal.replay(&self.pool).await?;
+        if replayed > 0 {
+            info!(
+                events = replayed,
+                journal = %self.journal.path().display(),
+                "Replayed runs recorded while the database was unavailable"
+            );
         }
 
         Ok(())
     }
 
-    async fn can_execute(&self, device_id: &str) -> bool {
-        let running = self.running_jobs.lock().await;
-        let count = running
-            .iter()
-            .filter(|(dev_id, _)| dev_id.as_str() == device_id)
-            .count();
+    async fn watch_stale_runs(&self) {
+        let mut check_interval = interval(Duration::from_secs(STALE_RUN_CHECK_INTERVAL_SECONDS));
+        check_interval.tick().await;
 
-        debug!(
-            device_id = device_id,
-            running = count,
-            max = self.max_concurrent_per_device,
-            "Checking if device can execute backup"
-        );
+        loop {
+            check_interval.tick().await;
 
-        count < self.max_concurrent_per_device
+            if let Err(e) = self.recover_stale_runs().await {
+                warn!("Failed to recover stale runs: {}", e);
+            }
+        }
     }
 
-    async fn mark_running(&self, device_id: &str, job_id: Uuid) {
-        let mut running = self.running_jobs.lock().await;
-        running.insert(device_id.to_string(), job_id);
-        debug!(
-            device_id = device_id,
-            job_id = %job_id,
-            total_running = running.len(),
-            "Marked job as running"
-        );
+    async fn recover_stale_runs(&self) -> Result<()> {
+        let trace_id = Uuid::new_v4().to_string();
+        let config = self.config.lock().await.clone();
+        let idle_timeout = Some(config.stale_run_timeout_minutes())
+            .filter(|minutes| *minutes > 0)
+            .map(|minutes| chrono::Duration::minutes(minutes as i64));
+        let live_run_ids = live_runs(&self.registry, &self.journal)
+            .await
+            .into_keys()
+            .collect();
+
+        let recovered = recover_stale_runs(
+            &config,
+            &self.pool,
+            self.device_id.clone(),
+            live_run_ids,
+            idle_timeout,
+            &trace_id,
+        )
+        .await?;
+
+        if recovered > 0 {
+            info!(
+                trace_id = trace_id,
+                count = recovered,
+                "Recovered stale runs"
+            );
+        }
+
+        Ok(())
     }
 
-    async fn mark_completed(&self, device_id: &str) {
-        let mut running = self.running_jobs.lock().await;
-        running.remove(device_id);
-        debug!(
-            device_id = device_id,
-            total_running = running.len(),
-            "Marked job as completed"
-        );
+    // Shared by repositories with the same URL
+    async fn repository_slots(
+        &self,
+        config: &RemoteConfig,
+        repository: Option<&Repository>,
+    ) -> Option<Arc<Semaphore>> {
+        let limit = config.max_concurrent_backups_per_repository();
+        if limit == 0 {
+            return None;
+        }
+        let url = match repository {
+            Some(repository) => repository.url.clone(),
+            None => config.repository_url().cloned().unwrap_or_default(),
+        };
+
+        let mut slots = self.repository_slots.lock().await;
+        let slots = slots
+            .entry(url)
+            .or_insert_with(|| Arc::new(Slots::new(limit)));
+        slots.resize(limit);
+        Some(slots.semaphore.clone())
+    }
+
+    async fn watch_config_reloads(&self) {
+        let mut events = self.registry.events().subscribe();
+
+        loop {
+            match events.recv().await {
+                Ok(Event::ConfigReloaded { .. }) | Err(RecvError::Lagged(_)) => {
+                    self.resize_slots().await
+                }
+                Ok(_) => {}
+                Err(RecvError::Closed) => return,
+            }
+        }
+    }
+
+    // Applies changed backup limits to the slots in use
+    async fn resize_slots(&self) {
+        let config = self.config.lock().await;
+        let device_limit = config.max_concurrent_backups().max(1);
+        let repository_limit = config.max_concurrent_backups_per_repository();
+        drop(config);
+
+        self.device_slots.resize(device_limit);
+        if repository_limit > 0 {
+            for slots in self.repository_slots.lock().await.values() {
+                slots.resize(repository_limit);
+            }
+        }
+    }
+}
+
+// Backup slots whose number follows a setting that may change while the client runs
+struct Slots {
+    semaphore: Arc<Semaphore>,
+    limit: AtomicUsize,
+}
+
+impl Slots {
+    fn new(limit: usize) -> Self {
+        Self {
+            semaphore: Arc::new(Semaphore::new(limit)),
+            limit: AtomicUsize::new(limit),
+        }
+    }
+
+    fn resize(&self, limit: usize) {
+        let previous = self.limit.swap(limit, Ordering::SeqCst);
+        if limit > previous {
+            self.semaphore.add_permits(limit - previous);
+        } else if limit < previous {
+            // Slots in use are removed once their backups finish
+            let semaphore = self.semaphore.clone();
+            let excess = (previous - limit) as u32;
+            tokio::spawn(async move {
+                if let Ok(permits) = semaphore.acquire_many_owned(excess).await {
+                    permits.forget();
+                }
+            });
+        }
+    }
+}
+
+async fn acquire_slot(slots: &Arc<Semaphore>, job_id: Uuid) -> OwnedSemaphorePermit {
+    if let Ok(permit) = slots.clone().try_acquire_owned() {
+        return permit;
+    }
+
+    info!(
+        job_id = %job_id,
+        "Maximum concurrent backups per repository reached, waiting for a running backup to finish"
+    );
+
+    let permit = slots
+        .clone()
+        .acquire_owned()
+        .await
+        .expect("Backup slots are never closed");
+
+    debug!(job_id = %job_id, "Acquired repository backup slot");
+    permit
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_slots_resize() {
+        let slots = Slots::new(2);
+
+        slots.resize(3);
+        assert_eq!(slots.semaphore.available_permits(), 3);
+
+        // A slot in use is removed once it is released
+        let permit = slots
+            .semaphore
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("Slots closed");
+        slots.resize(1);
+        tokio::task::yield_now().await;
+        assert_eq!(slots.semaphore.available_permits(), 0);
+
+        drop(permit);
+        tokio::task::yield_now().await;
+        assert_eq!(slots.semaphore.available_permits(), 1);
     }
 }
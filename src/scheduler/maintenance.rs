@@ -0,0 +1,136 @@
+use crate::error::{Result, SchedulerError};
+use crate::scheduler::timezone::ScheduleTimezone;
+use chrono::{DateTime, Duration, NaiveTime, Utc};
+
+// Windows crossing midnight (22:00-02:00) are supported
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MaintenanceWindow {
+    start: NaiveTime,
+    end: NaiveTime,
+}
+
+impl MaintenanceWindow {
+    pub fn parse(value: &str) -> Result<Self> {
+        let (start, end) = value.split_once('-').ok_or_else(|| {
+            SchedulerError::InvalidMaintenanceWindow(format!(
+                "Expected HH:MM-HH:MM, got: {}",
+                value
+            ))
+        })?;
+
+        let parse_time = |s: &str| {
+            NaiveTime::parse_from_str(s.trim(), "%H:%M").map_err(|e| {
+                SchedulerError::InvalidMaintenanceWindow(format!("Invalid time '{}': {}", s, e))
+            })
+        };
+
+        Ok(Self {
+            start: parse_time(start)?,
+            end: parse_time(end)?,
+        })
+    }
+
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+
+    pub fn is_open(&self, now: DateTime<Utc>, timezone: ScheduleTimezone) -> bool {
+        self.contains(timezone.local_time(now).time())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MaintenanceTask {
+    Prune,
+    Check,
+}
+
+impl MaintenanceTask {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MaintenanceTask::Prune => "prune",
+            MaintenanceTask::Check => "check",
+        }
+    }
+}
+
+pub fn is_maintenance_due(
+    last_run: Option<DateTime<Utc>>,
+    interval_hours: u64,
+    now: DateTime<Utc>,
+) -> bool {
+    if interval_hours == 0 {
+        return false;
+    }
+
+    match last_run {
+        Some(last) => last + Duration::hours(interval_hours as i64) <= now,
+        None => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn time(h: u32, m: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(h, m, 0).unwrap()
+    }
+
+    #[test]
+    fn test_parse_maintenance_window() {
+        let window = MaintenanceWindow::parse("01:00-05:30").expect("Failed to parse window");
+        assert!(window.contains(time(1, 0)));
+        assert!(window.contains(time(5, 29)));
+        assert!(!window.contains(time(5, 30)));
+        assert!(!window.contains(time(0, 59)));
+    }
+
+    #[test]
+    fn test_maintenance_window_across_midnight() {
+        let window = MaintenanceWindow::parse("22:00 - 02:00").expect("Failed to parse window");
+        assert!(window.contains(time(23, 0)));
+        assert!(window.contains(time(1, 59)));
+        assert!(!window.contains(time(12, 0)));
+    }
+
+    #[test]
+    fn test_maintenance_window_in_timezone() {
+        let window = MaintenanceWindow::parse("01:00-05:00").expect("Failed to parse window");
+        let timezone = ScheduleTimezone::parse("Europe/Prague").expect("Failed to parse timezone");
+        // 02:30 in Prague (CEST), 00:30 UTC
+        let now = Utc.with_ymd_and_hms(2025, 6, 1, 0, 30, 0).unwrap();
+
+        assert!(window.is_open(now, timezone));
+        assert!(!window.is_open(now, ScheduleTimezone::Utc));
+    }
+
+    #[test]
+    fn test_parse_invalid_maintenance_window() {
+        assert!(MaintenanceWindow::parse("01:00").is_err());
+        assert!(MaintenanceWindow::parse("25:00-02:00").is_err());
+    }
+
+    #[test]
+    fn test_is_maintenance_due() {
+        let now = Utc::now();
+
+        assert!(is_maintenance_due(None, 168, now));
+        assert!(!is_maintenance_due(None, 0, now));
+        assert!(!is_maintenance_due(
+            Some(now - Duration::hours(24)),
+            168,
+            now
+        ));
+        assert!(is_maintenance_due(
+            Some(now - Duration::hours(169)),
+            168,
+            now
+        ));
+    }
+}
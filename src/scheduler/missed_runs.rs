@@ -1,11 +1,84 @@
+use crate::config::remote::RemoteConfig;
 use crate::db::models::Schedule;
 use chrono::{DateTime, Duration, Utc};
 use tracing::warn;
 
-#[allow(dead_code)]
 const DEFAULT_GRACE_PERIOD_MINUTES: i64 = 5;
 
-#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CatchupPolicy {
+    RunOnce,
+    Skip,
+    RunIfOverdue(Duration),
+}
+
+impl CatchupPolicy {
+    pub fn parse(value: &str, overdue_minutes: u64) -> Option<Self> {
+        match value {
+            "run_once" => Some(CatchupPolicy::RunOnce),
+            "skip" => Some(CatchupPolicy::Skip),
+            "run_if_overdue" => Some(CatchupPolicy::RunIfOverdue(Duration::minutes(
+                overdue_minutes as i64,
+            ))),
+            _ => None,
+        }
+    }
+
+    // Calendar schedules that are not flexible skip missed runs unless set otherwise
+    pub fn for_schedule(schedule: &Schedule, config: &RemoteConfig) -> Self {
+        let overdue_minutes = schedule
+            .metadata
+            .get("catchup_overdue_minutes")
+            .and_then(|v| v.as_u64())
+            .unwrap_or_else(|| config.catchup_overdue_minutes());
+
+        let configured = schedule
+            .metadata
+            .get("catchup_policy")
+            .and_then(|v| v.as_str())
+            .or_else(|| (!schedule.is_flexible()).then_some("skip"))
+            .or_else(|| config.catchup_policy().map(|s| s.as_str()));
+
+        match configured {
+            None => CatchupPolicy::RunOnce,
+            Some(value) => Self::parse(value, overdue_minutes).unwrap_or_else(|| {
+                warn!(
+                    schedule_id = schedule.id,
+                    "Unknown catch-up policy '{}', running missed runs once", value
+                );
+                CatchupPolicy::RunOnce
+            }),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DueAction {
+    Run,
+    CatchUp,
+    Skip,
+}
+
+pub fn due_action(
+    schedule: &Schedule,
+    policy: CatchupPolicy,
+    now: DateTime<Utc>,
+    grace_period_minutes: Option<i64>,
+) -> DueAction {
+    if !is_run_missed(schedule, now, grace_period_minutes) {
+        return DueAction::Run;
+    }
+
+    match (policy, schedule.next_run_at) {
+        (CatchupPolicy::RunOnce, _) => DueAction::CatchUp,
+        (CatchupPolicy::Skip, _) => DueAction::Skip,
+        (CatchupPolicy::RunIfOverdue(limit), Some(missed_at)) if now - missed_at > limit => {
+            DueAction::CatchUp
+        }
+        (CatchupPolicy::RunIfOverdue(_), _) => DueAction::Skip,
+    }
+}
+
 pub fn is_run_missed(
     schedule: &Schedule,
     now: DateTime<Utc>,
@@ -28,7 +101,6 @@ pub fn is_run_missed(
     false
 }
 
-#[allow(dead_code)]
 pub fn count_missed_interval_runs(
     schedule: &Schedule,
     last_run: Option<DateTime<Utc>>,
@@ -72,6 +144,7 @@ pub fn count_missed_interval_runs(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::db::models::CalendarSchedule;
 
     fn create_interval_schedule(id: i32, interval_seconds: i32) -> Schedule {
         Schedule {
@@ -86,6 +159,8 @@ mod tests {
             created_at: Utc::now(),
             updated_at: Utc::now(),
             metadata: serde_json::json!({}),
+            calendar: None,
+            timezone: None,
         }
     }
 
@@ -102,6 +177,8 @@ mod tests {
             created_at: Utc::now(),
             updated_at: Utc::now(),
             metadata: serde_json::json!({}),
+            calendar: None,
+            timezone: None,
         }
     }
 
@@ -131,6 +208,107 @@ mod tests {
         assert!(!is_run_missed(&schedule, now, Some(5)));
     }
 
+    fn config(settings: &[(&str, &str)]) -> RemoteConfig {
+        RemoteConfig {
+            jobs: vec![],
+            schedules: vec![],
+            hooks: vec![],
+            repositories: vec![],
+            job_repositories: vec![],
+            settings: settings
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_catchup_policy_from_metadata_and_settings() {
+        let mut schedule = create_cron_schedule(1);
+
+        assert_eq!(
+            CatchupPolicy::for_schedule(&schedule, &config(&[])),
+            CatchupPolicy::RunOnce
+        );
+
+        let settings = config(&[
+            ("catchup_policy", "run_if_overdue"),
+            ("catchup_overdue_minutes", "120"),
+        ]);
+        assert_eq!(
+            CatchupPolicy::for_schedule(&schedule, &settings),
+            CatchupPolicy::RunIfOverdue(Duration::minutes(120))
+        );
+
+        schedule.metadata = serde_json::json!({"catchup_policy": "skip"});
+        assert_eq!(
+            CatchupPolicy::for_schedule(&schedule, &settings),
+            CatchupPolicy::Skip
+        );
+
+        schedule.metadata = serde_json::json!({"catchup_policy": "sometimes"});
+        assert_eq!(
+            CatchupPolicy::for_schedule(&schedule, &settings),
+            CatchupPolicy::RunOnce
+        );
+    }
+
+    #[test]
+    fn test_catchup_policy_of_calendar_schedules() {
+        let mut schedule = create_cron_schedule(1);
+        schedule.schedule_type = "calendar".to_string();
+        schedule.calendar = Some(sqlx::types::Json(CalendarSchedule {
+            times_of_day: vec!["13:00".to_string()],
+            flexible: false,
+            ..Default::default()
+        }));
+
+        assert_eq!(
+            CatchupPolicy::for_schedule(&schedule, &config(&[])),
+            CatchupPolicy::Skip
+        );
+
+        schedule.calendar.as_mut().unwrap().flexible = true;
+        assert_eq!(
+            CatchupPolicy::for_schedule(&schedule, &config(&[])),
+            CatchupPolicy::RunOnce
+        );
+    }
+
+    #[test]
+    fn test_due_action() {
+        let now = Utc::now();
+        let mut schedule = create_cron_schedule(1);
+
+        schedule.next_run_at = Some(now - Duration::minutes(1));
+        assert_eq!(
+            due_action(&schedule, CatchupPolicy::Skip, now, Some(5)),
+            DueAction::Run
+        );
+
+        schedule.next_run_at = Some(now - Duration::hours(3));
+        assert_eq!(
+            due_action(&schedule, CatchupPolicy::RunOnce, now, Some(5)),
+            DueAction::CatchUp
+        );
+        assert_eq!(
+            due_action(&schedule, CatchupPolicy::Skip, now, Some(5)),
+            DueAction::Skip
+        );
+
+        let overdue_hour = CatchupPolicy::RunIfOverdue(Duration::hours(1));
+        assert_eq!(
+            due_action(&schedule, overdue_hour, now, Some(5)),
+            DueAction::CatchUp
+        );
+
+        let overdue_day = CatchupPolicy::RunIfOverdue(Duration::days(1));
+        assert_eq!(
+            due_action(&schedule, overdue_day, now, Some(5)),
+            DueAction::Skip
+        );
+    }
+
     #[test]
     fn test_count_missed_interval_runs_none() {
         let now = Utc::now();
@@ -0,0 +1,276 @@
+use crate::db;
+use crate::db::models::QueuedJob;
+use crate::error::Result;
+use crate::events::{Event, EventBus};
+use chrono::Utc;
+use sqlx::PgPool;
+use std::sync::Arc;
+use tokio::sync::{Mutex, Notify};
+use tokio::time::{timeout, Duration};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+const QUEUE_POLL_INTERVAL_SECONDS: u64 = 10;
+
+pub fn priority(triggered_by: &str) -> i32 {
+    match triggered_by {
+        "manual" => 30,
+        "catchup" => 20,
+        "schedule" => 10,
+        _ => 0,
+    }
+}
+
+// Jobs queued while the database is unavailable are kept in memory with negative ids
+#[derive(Clone)]
+pub struct JobQueue {
+    pool: Arc<PgPool>,
+    device_id: String,
+    notify: Arc<Notify>,
+    local: Arc<Mutex<LocalQueue>>,
+    events: EventBus,
+}
+
+#[derive(Default)]
+struct LocalQueue {
+    next_id: i64,
+    entries: Vec<QueuedJob>,
+}
+
+impl LocalQueue {
+    fn push(&mut self, job_id: Uuid, device_id: String, triggered_by: &str) -> QueuedJob {
+        let priority = priority(triggered_by);
+
+        if let Some(entry) = self
+            .entries
+            .iter_mut()
+            .find(|e| e.job_id == job_id && e.status == "pending")
+        {
+            if priority > entry.priority {
+                entry.priority = priority;
+                entry.triggered_by = triggered_by.to_string();
+            }
+            return entry.clone();
+        }
+
+        self.next_id -= 1;
+        let entry = QueuedJob {
+            id: self.next_id,
+            job_id,
+            device_id,
+            triggered_by: triggered_by.to_string(),
+            priority,
+            status: "pending".to_string(),
+            enqueued_at: Utc::now(),
+            started_at: None,
+        };
+        self.entries.push(entry.clone());
+        entry
+    }
+
+    fn take(&mut self) -> Option<QueuedJob> {
+        let running: Vec<Uuid> = self
+            .entries
+            .iter()
+            .filter(|e| e.status == "running")
+            .map(|e| e.job_id)
+            .collect();
+
+        // Entries are in queueing order, so the first one of the highest priority is the oldest
+        let entry = self
+            .entries
+            .iter_mut()
+            .filter(|e| e.status == "pending" && !running.contains(&e.job_id))
+            .rev()
+            .max_by_key(|e| e.priority)?;
+
+        entry.status = "running".to_string();
+        entry.started_at = Some(Utc::now());
+        Some(entry.clone())
+    }
+
+    fn requeue(&mut self, id: i64) {
+        let Some(index) = self.entries.iter().position(|e| e.id == id) else {
+            return;
+        };
+        let job_id = self.entries[index].job_id;
+
+        if let Some(pending) = self
+            .entries
+            .iter()
+            .position(|e| e.job_id == job_id && e.status == "pending")
+        {
+            let entry = self.entries.remove(index);
+            let pending = &mut self.entries[if pending > index {
+                pending - 1
+            } else {
+                pending
+            }];
+            if entry.priority > pending.priority {
+                pending.priority = entry.priority;
+                pending.triggered_by = entry.triggered_by;
+            }
+            return;
+        }
+
+        let entry = &mut self.entries[index];
+        entry.status = "pending".to_string();
+        entry.started_at = None;
+    }
+}
+
+impl JobQueue {
+    pub fn new(pool: Arc<PgPool>, device_id: String, events: EventBus) -> Self {
+        Self {
+            pool,
+            device_id,
+            notify: Arc::new(Notify::new()),
+            local: Arc::new(Mutex::new(LocalQueue::default())),
+            events,
+        }
+    }
+
+    pub async fn push(&self, job_id: Uuid, triggered_by: &str) -> Result<QueuedJob> {
+        let result = db::enqueue_job(
+            &self.pool,
+            job_id,
+            self.device_id.clone(),
+            triggered_by.to_string(),
+            priority(triggered_by),
+        )
+        .await;
+
+        let entry = match result {
+            Ok(entry) => entry,
+            Err(e) if e.is_database_unavailable() => {
+                warn!(
+                    job_id = %job_id,
+                    "Database is unavailable, queueing job locally: {}", e
+                );
+                self.local
+                    .lock()
+                    .await
+                    .push(job_id, self.device_id.clone(), triggered_by)
+            }
+            Err(e) => return Err(e),
+        };
+
+        info!(
+            queue_id = entry.id,
+            job_id = %job_id,
+            triggered_by = triggered_by,
+            priority = entry.priority,
+            "Job queued"
+        );
+        self.events.publish(Event::JobQueued {
+            queue_id: entry.id,
+            job_id,
+            triggered_by: entry.triggered_by.clone(),
+            priority: entry.priority,
+        });
+
+        self.notify.notify_one();
+        Ok(entry)
+    }
+
+    pub async fn next(&self) -> Result<Option<QueuedJob>> {
+        if let Some(entry) = self.next_local().await {
+            return Ok(Some(entry));
+        }
+
+        db::claim_next_queued_job(&self.pool, self.device_id.clone()).await
+    }
+
+    pub async fn next_local(&self) -> Option<QueuedJob> {
+        self.local.lock().await.take()
+    }
+
+    pub async fn remove(&self, entry: &QueuedJob) -> Result<()> {
+        if entry.id < 0 {
+            self.local.lock().await.entries.retain(|e| e.id != entry.id);
+        } else {
+            db::delete_queued_job(&self.pool, entry.id).await?;
+        }
+        self.notify.notify_one();
+        Ok(())
+    }
+
+    pub async fn requeue(&self, entry: &QueuedJob) -> Result<()> {
+        if entry.id < 0 {
+            self.local.lock().await.requeue(entry.id);
+        } else {
+            db::requeue_queued_job(&self.pool, entry.id).await?;
+        }
+        self.notify.notify_one();
+        Ok(())
+    }
+
+    pub async fn requeue_interrupted(&self) -> Result<u64> {
+        db::requeue_interrupted_jobs(&self.pool, self.device_id.clone()).await
+    }
+
+    pub async fn wait(&self) {
+        let _ = timeout(
+            Duration::from_secs(QUEUE_POLL_INTERVAL_SECONDS),
+            self.notify.notified(),
+        )
+        .await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_priority() {
+        assert!(priority("manual") > priority("catchup"));
+        assert!(priority("catchup") > priority("schedule"));
+        assert!(priority("schedule") > priority("unknown"));
+    }
+
+    #[test]
+    fn test_local_queue() {
+        let mut queue = LocalQueue::default();
+        let (first, second) = (Uuid::new_v4(), Uuid::new_v4());
+
+        let scheduled = queue.push(first, "laptop".to_string(), "schedule");
+        assert!(scheduled.id < 0);
+        queue.push(second, "laptop".to_string(), "schedule");
+
+        // Coalesced into the pending entry, which takes over the higher priority
+        let manual = queue.push(second, "laptop".to_string(), "manual");
+        assert_eq!(queue.entries.len(), 2);
+        assert_eq!(manual.triggered_by, "manual");
+
+        let entry = queue.take().expect("No entry taken");
+        assert_eq!(entry.job_id, second);
+        assert_eq!(entry.status, "running");
+
+        // A running job may be queued once more, but waits for the running entry
+        queue.push(second, "laptop".to_string(), "schedule");
+        let entry = queue.take().expect("No entry taken");
+        assert_eq!(entry.id, scheduled.id);
+        assert!(queue.take().is_none());
+    }
+
+    #[test]
+    fn test_local_queue_requeue() {
+        let mut queue = LocalQueue::default();
+        let job_id = Uuid::new_v4();
+
+        let manual = queue.push(job_id, "laptop".to_string(), "manual");
+        queue.take().expect("No entry taken");
+        queue.requeue(manual.id);
+        let entry = queue.take().expect("Entry not requeued");
+        assert_eq!(entry.id, manual.id);
+
+        // Merged into the pending entry of the job, keeping the higher priority
+        queue.push(job_id, "laptop".to_string(), "schedule");
+        queue.requeue(manual.id);
+        assert_eq!(queue.entries.len(), 1);
+        let entry = queue.take().expect("No entry taken");
+        assert_ne!(entry.id, manual.id);
+        assert_eq!(entry.triggered_by, "manual");
+    }
+}
@@ -1,6 +1,7 @@
 use crate::db::models::Schedule;
 use crate::error::{Result, SchedulerError};
-use chrono::{DateTime, Duration, Utc};
+use crate::scheduler::timezone::ScheduleTimezone;
+use chrono::{DateTime, Duration, TimeZone, Utc};
 use cron::Schedule as CronSchedule;
 use std::str::FromStr;
 use tracing::debug;
@@ -9,11 +10,14 @@ pub fn calculate_next_run(
     schedule: &Schedule,
     last_run: Option<DateTime<Utc>>,
     now: DateTime<Utc>,
+    tz: &ScheduleTimezone,
 ) -> Result<DateTime<Utc>> {
     if schedule.is_cron() {
-        calculate_next_cron_run(schedule, now)
+        calculate_next_cron_run(schedule, now, tz)
     } else if schedule.is_interval() {
         calculate_next_interval_run(schedule, last_run, now)
+    } else if schedule.is_calendar() {
+        calculate_next_calendar_run(schedule, last_run, now, tz)
     } else {
         Err(SchedulerError::InvalidCronExpression(format!(
             "Unknown schedule type: {}",
@@ -23,7 +27,11 @@ pub fn calculate_next_run(
     }
 }
 
-fn calculate_next_cron_run(schedule: &Schedule, now: DateTime<Utc>) -> Result<DateTime<Utc>> {
+fn calculate_next_cron_run(
+    schedule: &Schedule,
+    now: DateTime<Utc>,
+    tz: &ScheduleTimezone,
+) -> Result<DateTime<Utc>> {
     let cron_expr = schedule.cron_expression.as_ref().ok_or_else(|| {
         SchedulerError::InvalidCronExpression("Cron expression is missing".to_string())
     })?;
@@ -34,14 +42,19 @@ fn calculate_next_cron_run(schedule: &Schedule, now: DateTime<Utc>) -> Result<Da
         SchedulerError::InvalidCronExpression(format!("Failed to parse cron expression: {}", e))
     })?;
 
+    // Matched against the local wall clock, so the time zone resolves daylight saving changes
+    let local_now = Utc.from_utc_datetime(&tz.local_time(now));
     let next = cron_schedule
-        .after(&now)
-        .next()
+        .after(&local_now)
+        .filter_map(|local| tz.resolve_local(local.naive_utc()))
+        .find(|next| *next > now)
         .ok_or_else(|| SchedulerError::InvalidCronExpression("No next run time".to_string()))?;
 
     debug!(
-        "Calculated next cron run for schedule {}: {}",
-        schedule.id, next
+        "Calculated next cron run for schedule {}: {} ({})",
+        schedule.id,
+        next,
+        tz.format(next)
     );
 
     Ok(next)
@@ -80,6 +93,35 @@ fn calculate_next_interval_run(
     Ok(next)
 }
 
+// A flexible schedule continues from its last run, so a missed time is due immediately
+fn calculate_next_calendar_run(
+    schedule: &Schedule,
+    last_run: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+    tz: &ScheduleTimezone,
+) -> Result<DateTime<Utc>> {
+    let calendar = schedule.calendar.as_ref().ok_or_else(|| {
+        SchedulerError::InvalidCalendar("Calendar definition is missing".to_string())
+    })?;
+
+    let after = match last_run {
+        Some(last_run_time) if calendar.flexible => last_run_time.min(now),
+        _ => now,
+    };
+
+    let next = calendar.next_after(after, tz)?;
+
+    debug!(
+        "Calculated next calendar run for schedule {}: {} ({}, {})",
+        schedule.id,
+        next,
+        tz.format(next),
+        calendar.0
+    );
+
+    Ok(next)
+}
+
 pub fn is_due(schedule: &Schedule, now: DateTime<Utc>) -> bool {
     if let Some(next_run) = schedule.next_run_at {
         next_run <= now
@@ -91,7 +133,10 @@ pub fn is_due(schedule: &Schedule, now: DateTime<Utc>) -> bool {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use chrono::{TimeZone, Timelike};
+    use crate::db::models::CalendarSchedule;
+    use chrono::Timelike;
+
+    const UTC: ScheduleTimezone = ScheduleTimezone::Utc;
 
     fn create_cron_schedule(id: i32, cron_expr: &str) -> Schedule {
         Schedule {
@@ -106,6 +151,8 @@ mod tests {
             created_at: Utc::now(),
             updated_at: Utc::now(),
             metadata: serde_json::json!({}),
+            calendar: None,
+            timezone: None,
         }
     }
 
@@ -122,6 +169,8 @@ mod tests {
             created_at: Utc::now(),
             updated_at: Utc::now(),
             metadata: serde_json::json!({}),
+            calendar: None,
+            timezone: None,
         }
     }
 
@@ -130,7 +179,7 @@ mod tests {
         let schedule = create_cron_schedule(1, "0 2 * * *");
         let now = Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
 
-        let result = calculate_next_run(&schedule, None, now);
+        let result = calculate_next_run(&schedule, None, now, &UTC);
         match &result {
             Ok(next) => {
                 assert_eq!(next.hour(), 2);
@@ -142,12 +191,45 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_calculate_next_cron_run_in_time_zone() {
+        let schedule = create_cron_schedule(1, "0 2 * * *");
+        let prague = ScheduleTimezone::parse("Europe/Prague").unwrap();
+
+        let now = Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+        let next = calculate_next_run(&schedule, None, now, &prague).unwrap();
+        assert_eq!(next, Utc.with_ymd_and_hms(2025, 1, 1, 1, 0, 0).unwrap());
+
+        let now = Utc.with_ymd_and_hms(2025, 7, 1, 0, 0, 0).unwrap();
+        let next = calculate_next_run(&schedule, None, now, &prague).unwrap();
+        assert_eq!(next, Utc.with_ymd_and_hms(2025, 7, 2, 0, 0, 0).unwrap());
+    }
+
+    #[test]
+    fn test_calculate_next_cron_run_dst_gap_and_overlap() {
+        let schedule = create_cron_schedule(1, "30 2 * * *");
+        let prague = ScheduleTimezone::parse("Europe/Prague").unwrap();
+
+        // 02:30 is skipped on 2025-03-30 and runs at 03:30 CEST
+        let now = Utc.with_ymd_and_hms(2025, 3, 29, 12, 0, 0).unwrap();
+        let next = calculate_next_run(&schedule, None, now, &prague).unwrap();
+        assert_eq!(next, Utc.with_ymd_and_hms(2025, 3, 30, 1, 30, 0).unwrap());
+
+        // 02:30 happens twice on 2025-10-26 and runs only the first time
+        let now = Utc.with_ymd_and_hms(2025, 10, 25, 12, 0, 0).unwrap();
+        let next = calculate_next_run(&schedule, None, now, &prague).unwrap();
+        assert_eq!(next, Utc.with_ymd_and_hms(2025, 10, 26, 0, 30, 0).unwrap());
+
+        let next = calculate_next_run(&schedule, Some(next), next, &prague).unwrap();
+        assert_eq!(next, Utc.with_ymd_and_hms(2025, 10, 27, 1, 30, 0).unwrap());
+    }
+
     #[test]
     fn test_calculate_next_interval_run_no_last_run() {
         let schedule = create_interval_schedule(1, 3600);
         let now = Utc.with_ymd_and_hms(2025, 1, 1, 12, 0, 0).unwrap();
 
-        let result = calculate_next_run(&schedule, None, now);
+        let result = calculate_next_run(&schedule, None, now, &UTC);
         assert!(result.is_ok());
 
         let next = result.unwrap();
@@ -160,7 +242,7 @@ mod tests {
         let last_run = Utc.with_ymd_and_hms(2025, 1, 1, 10, 0, 0).unwrap();
         let now = Utc.with_ymd_and_hms(2025, 1, 1, 12, 0, 0).unwrap();
 
-        let result = calculate_next_run(&schedule, Some(last_run), now);
+        let result = calculate_next_run(&schedule, Some(last_run), now, &UTC);
         assert!(result.is_ok());
 
         let next = result.unwrap();
@@ -172,7 +254,7 @@ mod tests {
         let schedule = create_cron_schedule(1, "invalid cron");
         let now = Utc::now();
 
-        let result = calculate_next_run(&schedule, None, now);
+        let result = calculate_next_run(&schedule, None, now, &UTC);
         assert!(result.is_err());
     }
 
@@ -181,10 +263,65 @@ mod tests {
         let schedule = create_interval_schedule(1, -100);
         let now = Utc::now();
 
-        let result = calculate_next_run(&schedule, None, now);
+        let result = calculate_next_run(&schedule, None, now, &UTC);
         assert!(result.is_err());
     }
 
+    fn create_calendar_schedule(id: i32, times_of_day: &[&str], flexible: bool) -> Schedule {
+        Schedule {
+            id,
+            job_id: uuid::Uuid::new_v4(),
+            schedule_type: "calendar".to_string(),
+            cron_expression: None,
+            interval_seconds: None,
+            enabled: true,
+            last_run_at: None,
+            next_run_at: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            metadata: serde_json::json!({}),
+            calendar: Some(sqlx::types::Json(CalendarSchedule {
+                times_of_day: times_of_day.iter().map(|t| t.to_string()).collect(),
+                flexible,
+                ..Default::default()
+            })),
+            timezone: None,
+        }
+    }
+
+    #[test]
+    fn test_calculate_next_calendar_run() {
+        let schedule = create_calendar_schedule(1, &["13:00", "23:00"], false);
+        let now = Utc.with_ymd_and_hms(2025, 1, 1, 14, 0, 0).unwrap();
+
+        let next = calculate_next_calendar_run(&schedule, None, now, &UTC).unwrap();
+        assert_eq!(next, Utc.with_ymd_and_hms(2025, 1, 1, 23, 0, 0).unwrap());
+    }
+
+    #[test]
+    fn test_calculate_next_calendar_run_flexible_catches_up() {
+        let last_run = Utc.with_ymd_and_hms(2024, 12, 30, 13, 5, 0).unwrap();
+        let now = Utc.with_ymd_and_hms(2025, 1, 1, 14, 0, 0).unwrap();
+
+        // The device was off since the last run: a flexible schedule is due right away
+        let flexible = create_calendar_schedule(1, &["13:00", "23:00"], true);
+        let next = calculate_next_calendar_run(&flexible, Some(last_run), now, &UTC).unwrap();
+        assert_eq!(next, Utc.with_ymd_and_hms(2024, 12, 30, 23, 0, 0).unwrap());
+        assert!(next <= now);
+
+        let strict = create_calendar_schedule(2, &["13:00", "23:00"], false);
+        let next = calculate_next_calendar_run(&strict, Some(last_run), now, &UTC).unwrap();
+        assert_eq!(next, Utc.with_ymd_and_hms(2025, 1, 1, 23, 0, 0).unwrap());
+    }
+
+    #[test]
+    fn test_calculate_next_calendar_run_without_calendar() {
+        let mut schedule = create_calendar_schedule(1, &["13:00"], true);
+        schedule.calendar = None;
+
+        assert!(calculate_next_run(&schedule, None, Utc::now(), &UTC).is_err());
+    }
+
     #[test]
     fn test_is_due() {
         let mut schedule = create_interval_schedule(1, 3600);
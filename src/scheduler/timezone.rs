@@ -0,0 +1,150 @@
+use crate::db::models::Schedule;
+use crate::error::{Result, SchedulerError};
+use chrono::{DateTime, Duration, Local, LocalResult, NaiveDateTime, Offset, TimeZone, Utc};
+use chrono_tz::Tz;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScheduleTimezone {
+    Utc,
+    Local,
+    Named(Tz),
+}
+
+impl ScheduleTimezone {
+    pub fn parse(name: &str) -> Result<Self> {
+        name.trim()
+            .parse::<Tz>()
+            .map(ScheduleTimezone::Named)
+            .map_err(|_| SchedulerError::InvalidTimezone(name.to_string()).into())
+    }
+
+    // Without either, cron schedules use UTC and calendar schedules the local time, like Relica
+    pub fn for_schedule(schedule: &Schedule, device_timezone: Option<&str>) -> Result<Self> {
+        match schedule.timezone.as_deref().or(device_timezone) {
+            Some(name) => Self::parse(name),
+            None if schedule.is_calendar() => Ok(ScheduleTimezone::Local),
+            None => Ok(ScheduleTimezone::Utc),
+        }
+    }
+
+    pub fn local_time(&self, time: DateTime<Utc>) -> NaiveDateTime {
+        match self {
+            ScheduleTimezone::Utc => time.naive_utc(),
+            ScheduleTimezone::Local => time.with_timezone(&Local).naive_local(),
+            ScheduleTimezone::Named(tz) => time.with_timezone(tz).naive_local(),
+        }
+    }
+
+    // A skipped time moves forward by the gap, a repeated time resolves to its first occurrence
+    pub fn resolve_local(&self, local: NaiveDateTime) -> Option<DateTime<Utc>> {
+        match self {
+            ScheduleTimezone::Utc => Some(Utc.from_utc_datetime(&local)),
+            ScheduleTimezone::Local => resolve(&Local, local),
+            ScheduleTimezone::Named(tz) => resolve(tz, local),
+        }
+    }
+
+    pub fn format(&self, time: DateTime<Utc>) -> String {
+        match self {
+            ScheduleTimezone::Utc => time.format("%Y-%m-%d %H:%M:%S UTC").to_string(),
+            ScheduleTimezone::Local => time
+                .with_timezone(&Local)
+                .format("%Y-%m-%d %H:%M:%S %:z")
+                .to_string(),
+            ScheduleTimezone::Named(tz) => time
+                .with_timezone(tz)
+                .format("%Y-%m-%d %H:%M:%S %Z")
+                .to_string(),
+        }
+    }
+}
+
+fn resolve<T: TimeZone>(tz: &T, local: NaiveDateTime) -> Option<DateTime<Utc>> {
+    match tz.from_local_datetime(&local) {
+        LocalResult::Single(time) => Some(time.with_timezone(&Utc)),
+        LocalResult::Ambiguous(earliest, _) => Some(earliest.with_timezone(&Utc)),
+        LocalResult::None => {
+            // In a gap, use the offset in effect before the change
+            let before = tz
+                .from_local_datetime(&(local - Duration::days(1)))
+                .earliest()?;
+            let offset = before.offset().fix();
+            Some(Utc.from_utc_datetime(&(local - offset)))
+        }
+    }
+}
+
+impl fmt::Display for ScheduleTimezone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScheduleTimezone::Utc => write!(f, "UTC"),
+            ScheduleTimezone::Local => write!(f, "local"),
+            ScheduleTimezone::Named(tz) => write!(f, "{}", tz.name()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn local(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(year, month, day)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            ScheduleTimezone::parse("Europe/Prague").unwrap(),
+            ScheduleTimezone::Named(chrono_tz::Europe::Prague)
+        );
+        assert!(ScheduleTimezone::parse("Europe/Atlantis").is_err());
+    }
+
+    #[test]
+    fn test_resolve_local_dst_gap_and_overlap() {
+        let prague = ScheduleTimezone::parse("Europe/Prague").unwrap();
+
+        // 2025-03-30: 02:00 CET jumps to 03:00 CEST
+        assert_eq!(
+            prague.resolve_local(local(2025, 3, 30, 2, 30)),
+            Some(Utc.with_ymd_and_hms(2025, 3, 30, 1, 30, 0).unwrap())
+        );
+        assert_eq!(
+            prague.local_time(Utc.with_ymd_and_hms(2025, 3, 30, 1, 30, 0).unwrap()),
+            local(2025, 3, 30, 3, 30)
+        );
+
+        // 2025-10-26: 03:00 CEST goes back to 02:00 CET, 02:30 happens twice
+        assert_eq!(
+            prague.resolve_local(local(2025, 10, 26, 2, 30)),
+            Some(Utc.with_ymd_and_hms(2025, 10, 26, 0, 30, 0).unwrap())
+        );
+
+        assert_eq!(
+            prague.resolve_local(local(2025, 7, 1, 2, 0)),
+            Some(Utc.with_ymd_and_hms(2025, 7, 1, 0, 0, 0).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_format() {
+        let time = Utc.with_ymd_and_hms(2025, 7, 1, 0, 0, 0).unwrap();
+
+        assert_eq!(
+            ScheduleTimezone::Utc.format(time),
+            "2025-07-01 00:00:00 UTC"
+        );
+        assert_eq!(
+            ScheduleTimezone::parse("Europe/Prague")
+                .unwrap()
+                .format(time),
+            "2025-07-01 02:00:00 CEST"
+        );
+    }
+}
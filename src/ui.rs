@@ -0,0 +1,75 @@
+use axum::http::header;
+use axum::response::IntoResponse;
+use axum::routing::get;
+use axum::Router;
+
+// Embedded, so the binary serves the UI without any files next to it
+const INDEX_HTML: &str = include_str!("ui/static/index.html");
+const APP_JS: &str = include_str!("ui/static/app.js");
+const STYLES_CSS: &str = include_str!("ui/static/styles.css");
+
+pub fn router<S>() -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    Router::new()
+        .route("/", get(index))
+        .route("/ui/app.js", get(app_js))
+        .route("/ui/styles.css", get(styles_css))
+}
+
+async fn index() -> impl IntoResponse {
+    asset("text/html; charset=utf-8", INDEX_HTML)
+}
+
+async fn app_js() -> impl IntoResponse {
+    asset("text/javascript; charset=utf-8", APP_JS)
+}
+
+async fn styles_css() -> impl IntoResponse {
+    asset("text/css; charset=utf-8", STYLES_CSS)
+}
+
+fn asset(content_type: &'static str, body: &'static str) -> impl IntoResponse {
+    // Revalidated, so the UI of an updated binary is picked up right away
+    (
+        [
+            (header::CONTENT_TYPE, content_type),
+            (header::CACHE_CONTROL, "no-cache"),
+        ],
+        body,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::{to_bytes, Body};
+    use axum::http::{Request, StatusCode};
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_serves_embedded_assets() {
+        for (uri, content_type) in [
+            ("/", "text/html; charset=utf-8"),
+            ("/ui/app.js", "text/javascript; charset=utf-8"),
+            ("/ui/styles.css", "text/css; charset=utf-8"),
+        ] {
+            let response = router::<()>()
+                .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+
+            assert_eq!(response.status(), StatusCode::OK, "{}", uri);
+            assert_eq!(response.headers()[header::CONTENT_TYPE], content_type);
+            let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            assert!(!body.is_empty(), "{}", uri);
+        }
+    }
+
+    #[test]
+    fn test_index_references_assets() {
+        assert!(INDEX_HTML.contains("/ui/app.js"));
+        assert!(INDEX_HTML.contains("/ui/styles.css"));
+    }
+}
@@ -0,0 +1,491 @@
+"use strict";
+
+// Single page UI on top of the HTTP API. Views are selected by the URL fragment:
+// #/ (overview), #/runs (run history) and #/runs/<id> (run detail).
+// The API token is exchanged for an HttpOnly session cookie on login, so it never reaches scripts.
+
+const REFRESH_INTERVAL_MS = 5000;
+const ACTIVE_REFRESH_INTERVAL_MS = 2000;
+// While the event stream is connected, changes arrive as events and polling is only a fallback
+const LIVE_REFRESH_INTERVAL_MS = 30000;
+const EVENT_REFRESH_DELAY_MS = 300;
+const RUNS_PAGE_SIZE = 25;
+
+const state = {
+    jobs: new Map(),
+    runsOffset: 0,
+    refreshTimer: null,
+    live: false,
+    // Role of the session token, null until logged in
+    role: null,
+    events: null,
+};
+
+class AuthError extends Error {
+}
+
+// Builds an element; strings become text nodes, so API data is never parsed as HTML
+function el(tag, attrs, ...children) {
+    const node = document.createElement(tag);
+    for (const [key, value] of Object.entries(attrs || {})) {
+        if (value === null || value === undefined || value === false) {
+            continue;
+        }
+        if (key.startsWith("on")) {
+            node.addEventListener(key.slice(2), value);
+        } else {
+            node.setAttribute(key, value === true ? "" : value);
+        }
+    }
+    for (const child of children.flat()) {
+        if (child !== null && child !== undefined) {
+            node.append(child instanceof Node ? child : String(child));
+        }
+    }
+    return node;
+}
+
+function $(id) {
+    return document.getElementById(id);
+}
+
+async function api(method, path, body) {
+    const options = {method, headers: {}};
+    if (body !== undefined) {
+        options.headers["Content-Type"] = "application/json";
+        options.body = JSON.stringify(body);
+    }
+    const response = await fetch(path, options);
+    const text = await response.text();
+    const data = text ? JSON.parse(text) : null;
+    if (response.status === 401) {
+        throw new AuthError((data && data.error) || "Authentication required");
+    }
+    if (!response.ok) {
+        throw new Error((data && data.error) || `${response.status} ${response.statusText}`);
+    }
+    return data;
+}
+
+function showMessage(text, info) {
+    const message = $("message");
+    message.textContent = text;
+    message.classList.toggle("info", !!info);
+    message.classList.remove("hidden");
+    clearTimeout(showMessage.timer);
+    showMessage.timer = setTimeout(() => message.classList.add("hidden"), 6000);
+}
+
+function formatTime(value) {
+    return value ? new Date(value).toLocaleString() : "–";
+}
+
+function formatRelative(value) {
+    if (!value) {
+        return "–";
+    }
+    const seconds = Math.round((new Date(value) - Date.now()) / 1000);
+    const abs = Math.abs(seconds);
+    const [amount, unit] =
+        abs < 60 ? [seconds, "second"] :
+            abs < 3600 ? [Math.round(seconds / 60), "minute"] :
+                abs < 86400 ? [Math.round(seconds / 3600), "hour"] :
+                    [Math.round(seconds / 86400), "day"];
+    return new Intl.RelativeTimeFormat(undefined, {numeric: "auto"}).format(amount, unit);
+}
+
+function formatDuration(seconds) {
+    if (seconds === null || seconds === undefined) {
+        return "–";
+    }
+    const h = Math.floor(seconds / 3600);
+    const m = Math.floor((seconds % 3600) / 60);
+    const s = seconds % 60;
+    return h ? `${h}h ${m}m` : m ? `${m}m ${s}s` : `${s}s`;
+}
+
+function formatBytes(bytes) {
+    if (bytes === null || bytes === undefined) {
+        return "–";
+    }
+    const units = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let value = bytes;
+    let unit = 0;
+    while (value >= 1024 && unit < units.length - 1) {
+        value /= 1024;
+        unit += 1;
+    }
+    return `${value.toFixed(unit ? 1 : 0)} ${units[unit]}`;
+}
+
+function statusBadge(status) {
+    return el("span", {class: `badge status-${status}`}, status);
+}
+
+function jobName(jobId) {
+    const job = state.jobs.get(jobId);
+    return job ? job.name : jobId;
+}
+
+function describeSchedule(schedule) {
+    let text;
+    if (schedule.schedule_type === "cron") {
+        text = `cron ${schedule.cron_expression}`;
+    } else if (schedule.schedule_type === "interval") {
+        text = `every ${formatDuration(schedule.interval_seconds)}`;
+    } else if (schedule.calendar) {
+        text = `at ${schedule.calendar.times_of_day.join(", ")}`;
+    } else {
+        text = schedule.schedule_type;
+    }
+    if (schedule.timezone) {
+        text += ` (${schedule.timezone})`;
+    }
+    return schedule.enabled ? text : `${text} (disabled)`;
+}
+
+function isAdmin() {
+    return state.role === "admin";
+}
+
+async function triggerJob(jobId) {
+    try {
+        await api("POST", `/jobs/${jobId}/run`);
+        showMessage(`Backup of ${jobName(jobId)} queued`, true);
+        refresh();
+    } catch (e) {
+        showMessage(`Failed to start backup: ${e.message}`);
+    }
+}
+
+async function cancelRun(runId) {
+    if (!confirm(`Cancel run ${runId}?`)) {
+        return;
+    }
+    try {
+        await api("POST", `/runs/${runId}/cancel`, {reason: "Cancelled from the web UI"});
+        showMessage(`Cancellation of run ${runId} requested`, true);
+        refresh();
+    } catch (e) {
+        showMessage(`Failed to cancel run: ${e.message}`);
+    }
+}
+
+function progressView(progress) {
+    if (!progress) {
+        return el("div", {class: "muted"}, "Waiting for progress…");
+    }
+    const percent = Math.min(100, Math.max(0, progress.percent_done * 100));
+    const files = progress.total_files
+        ? `${progress.files_done} / ${progress.total_files} files`
+        : `${progress.files_done} files`;
+    const bytes = progress.total_bytes
+        ? `${formatBytes(progress.bytes_done)} / ${formatBytes(progress.total_bytes)}`
+        : formatBytes(progress.bytes_done);
+    const remaining = progress.seconds_remaining !== null && progress.seconds_remaining !== undefined
+        ? `, ${formatDuration(progress.seconds_remaining)} left` : "";
+    const errors = progress.error_count ? `, ${progress.error_count} errors` : "";
+
+    return el("div", {},
+        el("div", {class: "progress"}, el("div", {style: `width: ${percent.toFixed(1)}%`})),
+        el("div", {}, `${percent.toFixed(1)}% · ${files} · ${bytes}${remaining}${errors}`),
+        (progress.current_files || []).map((file) => el("div", {class: "current-file"}, file)),
+    );
+}
+
+function renderStatus(status) {
+    $("device-name").textContent = status.device ? status.device.name : status.device_id;
+    document.title = `rbackup2 – ${$("device-name").textContent}`;
+
+    const connection = $("connection");
+    connection.textContent = status.database_connected ? "database connected" : "database offline";
+    connection.className = `badge ${status.database_connected ? "status-success" : "status-failed"}`;
+
+    const activeRuns = $("active-runs");
+    activeRuns.replaceChildren(...(status.active_runs.length
+        ? status.active_runs.map((run) => el("div", {class: "card"},
+            el("div", {class: "title"},
+                el("span", {},
+                    el("strong", {}, jobName(run.job_id)), " ",
+                    el("a", {href: `#/runs/${run.run_id}`}, `run ${run.run_id}`), " ",
+                    el("span", {class: "muted"}, `started ${formatRelative(run.started_at)}`)),
+                isAdmin() ? el("button", {class: "danger", onclick: () => cancelRun(run.run_id)}, "Cancel") : null),
+            el("div", {"data-progress-run": run.run_id}, progressView(run.progress))))
+        : [el("div", {class: "empty"}, "No backup is running.")]));
+
+    const queue = $("queue");
+    queue.replaceChildren(...(status.queue.length
+        ? [el("table", {},
+            el("tbody", {}, status.queue.map((entry) => el("tr", {},
+                el("td", {}, jobName(entry.job_id)),
+                el("td", {}, statusBadge(entry.status)),
+                el("td", {}, entry.triggered_by),
+                el("td", {class: "muted"}, `queued ${formatRelative(entry.enqueued_at)}`)))))]
+        : [el("div", {class: "empty"}, "The queue is empty.")]));
+
+    return status.active_runs.length > 0;
+}
+
+function renderJobs(jobs) {
+    state.jobs = new Map(jobs.map((job) => [job.id, job]));
+
+    $("jobs").replaceChildren(...(jobs.length
+        ? jobs.map((job) => el("tr", {},
+            el("td", {},
+                el("strong", {}, job.name), job.enabled ? null : el("span", {class: "muted"}, " (disabled)"),
+                el("div", {class: "muted"}, job.source_paths.join(", "))),
+            el("td", {}, job.schedules.length
+                ? job.schedules.map((schedule) => el("div", {}, describeSchedule(schedule)))
+                : el("span", {class: "muted"}, "manual only")),
+            el("td", {title: formatTime(job.next_run_at)}, formatRelative(job.next_run_at)),
+            el("td", {}, job.last_run
+                ? [statusBadge(job.last_run.status), " ",
+                    el("a", {href: `#/runs/${job.last_run.id}`, title: formatTime(job.last_run.start_time)},
+                        formatRelative(job.last_run.start_time))]
+                : el("span", {class: "muted"}, "never")),
+            el("td", {class: "actions"},
+                isAdmin() ? el("button", {disabled: job.running, onclick: () => triggerJob(job.id)},
+                    job.running ? "Running" : "Run now") : null)))
+        : [el("tr", {}, el("td", {colspan: 5, class: "empty"}, "No jobs are configured."))]));
+
+    const jobFilter = document.querySelector("#run-filters select[name=job_id]");
+    const selected = jobFilter.value;
+    jobFilter.replaceChildren(el("option", {value: ""}, "All"),
+        ...jobs.map((job) => el("option", {value: job.id, selected: job.id === selected}, job.name)));
+}
+
+async function refreshOverview() {
+    const [status, jobs] = await Promise.all([api("GET", "/status"), api("GET", "/jobs")]);
+    renderJobs(jobs);
+    return renderStatus(status);
+}
+
+async function refreshRuns() {
+    const params = new URLSearchParams({limit: RUNS_PAGE_SIZE, offset: state.runsOffset});
+    for (const [key, value] of new FormData($("run-filters"))) {
+        if (!value) {
+            continue;
+        }
+        params.set(key, key === "since" ? new Date(value).toISOString() : value);
+    }
+    const history = await api("GET", `/runs?${params}`);
+
+    $("runs").replaceChildren(...(history.runs.length
+        ? history.runs.map((run) => el("tr", {},
+            el("td", {}, el("a", {href: `#/runs/${run.id}`}, run.id)),
+            el("td", {}, jobName(run.job_id)),
+            el("td", {}, formatTime(run.start_time)),
+            el("td", {}, formatDuration(run.duration_seconds)),
+            el("td", {}, statusBadge(run.status)),
+            el("td", {}, `${run.files_new ?? "–"} / ${run.files_changed ?? "–"}`),
+            el("td", {}, formatBytes(run.data_added_bytes)),
+            el("td", {}, run.triggered_by)))
+        : [el("tr", {}, el("td", {colspan: 8, class: "empty"}, "No runs match the filter."))]));
+
+    const last = Math.min(history.offset + history.runs.length, history.total);
+    $("runs-page").textContent = history.total
+        ? `${history.offset + 1}–${last} of ${history.total}` : "";
+    $("runs-prev").disabled = history.offset === 0;
+    $("runs-next").disabled = last >= history.total;
+
+    return history.runs.some((run) => run.status === "running");
+}
+
+async function refreshRun(runId) {
+    const [run, status] = await Promise.all([api("GET", `/runs/${runId}`), api("GET", "/status")]);
+    const running = run.status === "running";
+
+    $("run-title").replaceChildren(`Run ${run.id} · ${jobName(run.job_id)} `, statusBadge(run.status));
+    $("run-actions").replaceChildren(...(running && isAdmin()
+        ? [el("button", {class: "danger", onclick: () => cancelRun(run.id)}, "Cancel")]
+        : []));
+
+    const fields = [
+        ["Started", formatTime(run.start_time)],
+        ["Finished", formatTime(run.end_time)],
+        ["Duration", formatDuration(run.duration_seconds)],
+        ["Triggered by", run.triggered_by],
+        ["Attempt", run.retry_of ? `${run.attempt} (retry of run ${run.retry_of})` : run.attempt],
+        ["Repository", run.repository_id],
+        ["Snapshot", run.snapshot_id],
+        ["Exit code", run.exit_code],
+        ["Files new / changed / unmodified",
+            `${run.files_new ?? "–"} / ${run.files_changed ?? "–"} / ${run.files_unmodified ?? "–"}`],
+        ["Data added", formatBytes(run.data_added_bytes)],
+        ["Processed", run.total_files_processed !== null
+            ? `${run.total_files_processed} files, ${formatBytes(run.total_bytes_processed)}` : null],
+        ["Failure reason", run.failure_reason],
+        ["Error", run.error_message],
+        ["Cancelled by", run.cancel_requested_by
+            ? `${run.cancel_requested_by}: ${run.cancel_reason || ""}` : null],
+        ["Unreadable files", (run.failed_paths || []).map((failed) => `${failed.path}: ${failed.error}`).join("\n")],
+    ];
+    $("run-summary").replaceChildren(...fields
+        .filter(([, value]) => value !== null && value !== undefined && value !== "")
+        .flatMap(([label, value]) => [el("dt", {}, label), el("dd", {style: "white-space: pre-wrap"}, value)]));
+
+    const active = status.active_runs.find((active) => active.run_id === run.id);
+    $("run-progress").replaceChildren(...(active
+        ? [el("div", {class: "card", "data-progress-run": run.id}, progressView(active.progress))]
+        : []));
+
+    $("run-hooks").replaceChildren(...(run.hook_executions.length
+        ? [el("table", {},
+            el("thead", {}, el("tr", {},
+                ["Timing", "Command", "Status", "Exit code", "Duration", "Output"].map((h) => el("th", {}, h)))),
+            el("tbody", {}, run.hook_executions.map((hook) => el("tr", {},
+                el("td", {}, hook.timing),
+                el("td", {}, el("code", {}, hook.command)),
+                el("td", {}, statusBadge(hook.status)),
+                el("td", {}, hook.exit_code ?? "–"),
+                el("td", {}, formatDuration(hook.duration_seconds)),
+                el("td", {}, el("pre", {class: "output"},
+                    [hook.stdout, hook.stderr, hook.error_message].filter(Boolean).join("\n")))))))]
+        : [el("div", {class: "empty"}, "No hooks ran.")]));
+
+    $("run-stdout").textContent = run.restic_output || "(none)";
+    $("run-stderr").textContent = run.restic_errors || "(none)";
+
+    return running;
+}
+
+function currentRoute() {
+    const path = location.hash.replace(/^#/, "") || "/";
+    const match = path.match(/^\/runs\/(-?\d+)$/);
+    if (match) {
+        return {view: "run", runId: Number(match[1])};
+    }
+    return {view: path === "/runs" ? "runs" : "overview"};
+}
+
+async function refresh() {
+    clearTimeout(state.refreshTimer);
+    if (!state.role) {
+        return;
+    }
+    const route = currentRoute();
+    let active = false;
+    try {
+        if (state.jobs.size === 0 && route.view !== "overview") {
+            renderJobs(await api("GET", "/jobs"));
+        }
+        if (route.view === "run") {
+            active = await refreshRun(route.runId);
+        } else if (route.view === "runs") {
+            active = await refreshRuns();
+        } else {
+            active = await refreshOverview();
+        }
+    } catch (e) {
+        if (e instanceof AuthError) {
+            showLogin();
+            return;
+        }
+        showMessage(e.message);
+    }
+    // Without the event stream, runs in progress are refreshed more often so their progress is live
+    const delay = state.live ? LIVE_REFRESH_INTERVAL_MS
+        : active ? ACTIVE_REFRESH_INTERVAL_MS : REFRESH_INTERVAL_MS;
+    state.refreshTimer = setTimeout(refresh, delay);
+}
+
+// Progress is updated in place, every other event reloads the current view
+function connectEvents() {
+    const source = new EventSource("/events");
+    state.events = source;
+
+    source.addEventListener("open", () => {
+        state.live = true;
+        // Events may have been missed while disconnected
+        refresh();
+    });
+    source.addEventListener("error", () => {
+        state.live = false;
+    });
+
+    source.addEventListener("run_progress", (message) => {
+        const event = JSON.parse(message.data);
+        for (const node of document.querySelectorAll(`[data-progress-run="${event.run_id}"]`)) {
+            node.replaceChildren(progressView(event.progress));
+        }
+    });
+
+    for (const name of ["job_queued", "run_started", "run_finished", "config_reloaded",
+        "database_connectivity_changed"]) {
+        source.addEventListener(name, () => {
+            clearTimeout(state.refreshTimer);
+            state.refreshTimer = setTimeout(refresh, EVENT_REFRESH_DELAY_MS);
+        });
+    }
+}
+
+function showView() {
+    const route = state.role ? currentRoute() : {view: "login"};
+    for (const view of ["login", "overview", "runs", "run"]) {
+        $(`view-${view}`).classList.toggle("hidden", view !== route.view);
+    }
+    $("logout").classList.toggle("hidden", !state.role);
+    refresh();
+}
+
+function showLogin() {
+    state.role = null;
+    state.live = false;
+    clearTimeout(state.refreshTimer);
+    if (state.events) {
+        state.events.close();
+        state.events = null;
+    }
+    showView();
+}
+
+// Starts the UI if the session cookie is still valid, otherwise asks for the token
+async function start() {
+    try {
+        state.role = (await api("GET", "/session")).role;
+    } catch (e) {
+        if (!(e instanceof AuthError)) {
+            showMessage(e.message);
+        }
+        showLogin();
+        return;
+    }
+    showView();
+    connectEvents();
+}
+
+$("run-filters").addEventListener("change", () => {
+    state.runsOffset = 0;
+    refresh();
+});
+$("run-filters").addEventListener("submit", (event) => event.preventDefault());
+$("runs-prev").addEventListener("click", () => {
+    state.runsOffset = Math.max(0, state.runsOffset - RUNS_PAGE_SIZE);
+    refresh();
+});
+$("runs-next").addEventListener("click", () => {
+    state.runsOffset += RUNS_PAGE_SIZE;
+    refresh();
+});
+$("login").addEventListener("submit", async (event) => {
+    event.preventDefault();
+    const form = event.target;
+    try {
+        await api("POST", "/login", {token: form.elements.token.value});
+        form.reset();
+        start();
+    } catch (e) {
+        showMessage(e instanceof AuthError ? "The token is not valid" : e.message);
+    }
+});
+$("logout").addEventListener("click", async () => {
+    try {
+        await api("POST", "/logout");
+    } catch (e) {
+        showMessage(e.message);
+    }
+    showLogin();
+});
+window.addEventListener("hashchange", showView);
+
+start();
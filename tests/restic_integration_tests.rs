@@ -1,8 +1,15 @@
 use chrono::Utc;
-use rbackup2::backup::output::parse_restic_json_output;
+use rbackup2::backup::check::ReadDataSubset;
+use rbackup2::backup::output::{
+    parse_check_errors, parse_forget_json_output, parse_restic_json_output,
+    parse_restore_json_output,
+};
 use rbackup2::backup::restic::ResticCommand;
+use rbackup2::backup::restore::{
+    resolve_snapshot, OverwritePolicy, RestoreOptions, SnapshotSelector,
+};
 use rbackup2::config::remote::RemoteConfig;
-use rbackup2::db::models::BackupJob;
+use rbackup2::db::models::{BackupJob, RetentionPolicy};
 use std::collections::HashMap;
 use std::env;
 use std::fs;
@@ -82,6 +89,9 @@ fn create_test_config(repo_path: &str, password: &str) -> RemoteConfig {
     RemoteConfig {
         jobs: vec![],
         schedules: vec![],
+        hooks: vec![],
+        repositories: vec![],
+        job_repositories: vec![],
         settings,
     }
 }
@@ -103,6 +113,7 @@ fn create_test_job(source_paths: Vec<String>) -> BackupJob {
         origin_name: Some("test-origin".to_string()),
         origin_id: None,
         account_id: None,
+        retention: RetentionPolicy::default(),
     }
 }
 
@@ -181,7 +192,7 @@ fn test_command_builder_basic() {
     let repo_path = temp_dir.path().join("test-repo");
     let config = create_test_config(repo_path.to_str().unwrap(), "test-password");
 
-    let restic_cmd = ResticCommand::new(&config).expect("Failed to create ResticCommand");
+    let restic_cmd = ResticCommand::new(&config, None).expect("Failed to create ResticCommand");
 
     let job = create_test_job(vec![temp_dir.path().to_str().unwrap().to_string()]);
     let command = restic_cmd.build_backup_command(&job);
@@ -201,7 +212,7 @@ fn test_command_builder_with_excludes() {
     let repo_path = temp_dir.path().join("test-repo");
     let config = create_test_config(repo_path.to_str().unwrap(), "test-password");
 
-    let restic_cmd = ResticCommand::new(&config).expect("Failed to create ResticCommand");
+    let restic_cmd = ResticCommand::new(&config, None).expect("Failed to create ResticCommand");
 
     let mut job = create_test_job(vec![temp_dir.path().to_str().unwrap().to_string()]);
     job.exclude_patterns = Some(vec!["*.tmp".to_string(), "*.log".to_string()]);
@@ -227,7 +238,7 @@ async fn test_restic_init_and_backup() {
         .expect("Failed to initialize restic repository");
 
     let config = create_test_config(repo_path.to_str().unwrap(), password);
-    let restic_cmd = ResticCommand::new(&config).expect("Failed to create ResticCommand");
+    let restic_cmd = ResticCommand::new(&config, None).expect("Failed to create ResticCommand");
 
     let job = create_test_job(vec![source_dir.to_str().unwrap().to_string()]);
     let mut command = restic_cmd.build_backup_command(&job);
@@ -265,7 +276,7 @@ async fn test_restic_backup_with_json_output() {
         .expect("Failed to initialize restic repository");
 
     let config = create_test_config(repo_path.to_str().unwrap(), password);
-    let restic_cmd = ResticCommand::new(&config).expect("Failed to create ResticCommand");
+    let restic_cmd = ResticCommand::new(&config, None).expect("Failed to create ResticCommand");
 
     let job = create_test_job(vec![source_dir.to_str().unwrap().to_string()]);
     let mut command = restic_cmd.build_backup_command(&job);
@@ -311,7 +322,7 @@ async fn test_restic_backup_with_tags() {
         .expect("Failed to initialize restic repository");
 
     let config = create_test_config(repo_path.to_str().unwrap(), password);
-    let restic_cmd = ResticCommand::new(&config).expect("Failed to create ResticCommand");
+    let restic_cmd = ResticCommand::new(&config, None).expect("Failed to create ResticCommand");
 
     let mut job = create_test_job(vec![source_dir.to_str().unwrap().to_string()]);
     job.tags = Some(vec![
@@ -365,7 +376,7 @@ async fn test_restic_incremental_backup() {
         .expect("Failed to initialize restic repository");
 
     let config = create_test_config(repo_path.to_str().unwrap(), password);
-    let restic_cmd = ResticCommand::new(&config).expect("Failed to create ResticCommand");
+    let restic_cmd = ResticCommand::new(&config, None).expect("Failed to create ResticCommand");
 
     let job = create_test_job(vec![source_dir.to_str().unwrap().to_string()]);
 
@@ -409,3 +420,187 @@ async fn test_restic_incremental_backup() {
         "Snapshots should have different IDs"
     );
 }
+
+#[tokio::test]
+async fn test_restic_restore_latest_snapshot() {
+    setup_restic_in_path();
+
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let repo_path = temp_dir.path().join("test-repo");
+    let source_dir = temp_dir.path().join("source");
+    let target_dir = temp_dir.path().join("target");
+    fs::create_dir_all(source_dir.join("docs")).expect("Failed to create source dir");
+
+    fs::write(source_dir.join("docs/keep.txt"), "first version")
+        .expect("Failed to write test file");
+    fs::write(source_dir.join("skip.txt"), "not restored").expect("Failed to write test file");
+
+    let password = "test-password-restore";
+
+    init_restic_repo(repo_path.to_str().unwrap(), password)
+        .expect("Failed to initialize restic repository");
+
+    let config = create_test_config(repo_path.to_str().unwrap(), password);
+    let restic_cmd = ResticCommand::new(&config, None).expect("Failed to create ResticCommand");
+    let job = create_test_job(vec![source_dir.to_str().unwrap().to_string()]);
+
+    let output1 = restic_cmd
+        .build_backup_command(&job)
+        .output()
+        .await
+        .expect("Failed to execute first backup");
+    assert!(output1.status.success(), "First backup failed");
+
+    fs::write(source_dir.join("docs/keep.txt"), "second version")
+        .expect("Failed to update test file");
+
+    let output2 = restic_cmd
+        .build_backup_command(&job)
+        .output()
+        .await
+        .expect("Failed to execute second backup");
+    assert!(output2.status.success(), "Second backup failed");
+    let stats2 = parse_restic_json_output(&String::from_utf8_lossy(&output2.stdout))
+        .expect("Failed to parse second backup output");
+
+    let snapshot = resolve_snapshot(&restic_cmd, &job, &SnapshotSelector::Latest, "test")
+        .await
+        .expect("Failed to resolve latest snapshot");
+    assert_eq!(snapshot.id, stats2.snapshot_id);
+
+    let other_job = create_test_job(vec![source_dir.to_str().unwrap().to_string()]);
+    assert!(
+        resolve_snapshot(&restic_cmd, &other_job, &SnapshotSelector::Latest, "test")
+            .await
+            .is_err(),
+        "Snapshots of another job must not be resolved"
+    );
+
+    let options = RestoreOptions {
+        snapshot: SnapshotSelector::Id(snapshot.id.clone()),
+        target: target_dir.clone(),
+        include: vec![source_dir.join("docs").to_str().unwrap().to_string()],
+        exclude: vec![],
+        overwrite: OverwritePolicy::Always,
+    };
+
+    let output = restic_cmd
+        .build_restore_command(&snapshot.id, &options)
+        .output()
+        .await
+        .expect("Failed to execute restic restore");
+
+    assert!(
+        output.status.success(),
+        "Restic restore failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    parse_restore_json_output(&String::from_utf8_lossy(&output.stdout))
+        .expect("Failed to parse restore output");
+
+    let restored_root = target_dir.join(source_dir.strip_prefix("/").unwrap_or(&source_dir));
+    let restored = fs::read_to_string(restored_root.join("docs/keep.txt"))
+        .expect("Restored file should exist");
+    assert_eq!(restored, "second version");
+    assert!(
+        !restored_root.join("skip.txt").exists(),
+        "Excluded file should not be restored"
+    );
+}
+
+#[tokio::test]
+async fn test_restic_forget_applies_job_retention_only() {
+    setup_restic_in_path();
+
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let repo_path = temp_dir.path().join("test-repo");
+    let source_dir = temp_dir.path().join("source");
+    fs::create_dir_all(&source_dir).expect("Failed to create source dir");
+
+    let password = "test-password-forget";
+
+    init_restic_repo(repo_path.to_str().unwrap(), password)
+        .expect("Failed to initialize restic repository");
+
+    let config = create_test_config(repo_path.to_str().unwrap(), password);
+    let restic_cmd = ResticCommand::new(&config, None).expect("Failed to create ResticCommand");
+
+    let mut job = create_test_job(vec![source_dir.to_str().unwrap().to_string()]);
+    job.retention.keep_last = Some(1);
+    let other_job = create_test_job(vec![source_dir.to_str().unwrap().to_string()]);
+
+    for (i, backup_job) in [&job, &job, &job, &other_job].iter().enumerate() {
+        fs::write(source_dir.join("file.txt"), format!("version {}", i))
+            .expect("Failed to write test file");
+        let output = restic_cmd
+            .build_backup_command(backup_job)
+            .output()
+            .await
+            .expect("Failed to execute backup");
+        assert!(output.status.success(), "Backup {} failed", i);
+    }
+
+    let output = restic_cmd
+        .build_forget_command(&job)
+        .output()
+        .await
+        .expect("Failed to execute restic forget");
+
+    assert!(
+        output.status.success(),
+        "Restic forget failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let stats = parse_forget_json_output(&String::from_utf8_lossy(&output.stdout))
+        .expect("Failed to parse forget output");
+    assert_eq!(stats.snapshots_kept, 1);
+    assert_eq!(stats.snapshots_removed, 2);
+
+    let other_snapshots =
+        resolve_snapshot(&restic_cmd, &other_job, &SnapshotSelector::Latest, "test").await;
+    assert!(
+        other_snapshots.is_ok(),
+        "Snapshots of other jobs must not be forgotten"
+    );
+}
+
+#[tokio::test]
+async fn test_restic_check_with_read_data_subset() {
+    setup_restic_in_path();
+
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let repo_path = temp_dir.path().join("test-repo");
+    let source_dir = temp_dir.path().join("source");
+    fs::create_dir_all(&source_dir).expect("Failed to create source dir");
+    fs::write(source_dir.join("file.txt"), "checked content").expect("Failed to write test file");
+
+    let password = "test-password-check";
+
+    init_restic_repo(repo_path.to_str().unwrap(), password)
+        .expect("Failed to initialize restic repository");
+
+    let config = create_test_config(repo_path.to_str().unwrap(), password);
+    let restic_cmd = ResticCommand::new(&config, None).expect("Failed to create ResticCommand");
+    let job = create_test_job(vec![source_dir.to_str().unwrap().to_string()]);
+
+    let output = restic_cmd
+        .build_backup_command(&job)
+        .output()
+        .await
+        .expect("Failed to execute backup");
+    assert!(output.status.success(), "Backup failed");
+
+    let subset = ReadDataSubset::next_after(None, 2);
+    let output = restic_cmd
+        .build_check_command(subset.as_ref())
+        .output()
+        .await
+        .expect("Failed to execute restic check");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert!(output.status.success(), "Restic check failed: {}", stderr);
+    assert!(parse_check_errors(&stdout, &stderr).is_empty());
+}